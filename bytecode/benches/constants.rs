@@ -0,0 +1,42 @@
+//! Runs a loop that loads a constant on nearly every instruction, to measure
+//! the cost of fetching constants. Run with `cargo bench -p bytecode`.
+
+#![feature(test)]
+
+extern crate test;
+
+use bytecode::vm::Vm;
+use common::sink::Sink;
+use test::Bencher;
+
+const NUMBERS: &str = r#"
+var total = 0;
+for (var i = 0; i < 10000; i = i + 1) {
+  total = total + 1 + 2 + 3 + 4;
+}
+"#;
+
+const STRINGS: &str = r#"
+var s;
+for (var i = 0; i < 10000; i = i + 1) {
+  s = "a";
+  s = "b";
+  s = "c";
+}
+"#;
+
+fn run(source: &str) {
+    let mut vm = Vm::new();
+    vm.set_output(Sink::buffer().0, Sink::buffer().0);
+    let _ = vm.interpret(source);
+}
+
+#[bench]
+fn numbers(b: &mut Bencher) {
+    b.iter(|| run(NUMBERS));
+}
+
+#[bench]
+fn strings(b: &mut Bencher) {
+    b.iter(|| run(STRINGS));
+}
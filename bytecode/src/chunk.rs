@@ -2,21 +2,98 @@ use num_enum::{FromPrimitive, IntoPrimitive};
 
 use crate::value::Value;
 
-#[derive(FromPrimitive, IntoPrimitive)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive, IntoPrimitive)]
 #[repr(u8)]
 pub enum OpCode {
     Constant,
+    Nil,
+    True,
+    False,
+    Pop,
+    GetLocal,
+    SetLocal,
+    GetUpvalue,
+    SetUpvalue,
+    GetGlobal,
+    DefineGlobal,
+    SetGlobal,
+    GetProperty,
+    SetProperty,
+    GetSuper,
+    Equal,
+    Greater,
+    Less,
     Add,
     Subtract,
     Multiply,
     Divide,
+    Not,
     Negate,
+    Print,
+    Jump,
+    JumpIfFalse,
+    Loop,
+    Call,
+    Invoke,
+    SuperInvoke,
+    Closure,
+    CloseUpvalue,
     Return,
+    Class,
+    Inherit,
+    Method,
 
     #[num_enum(catch_all)]
     Unknown(u8),
 }
 
+impl OpCode {
+    /// How many operand bytes follow the opcode. A closure is also followed
+    /// by two bytes per upvalue, which only its function constant knows.
+    pub fn operand_len(self) -> usize {
+        match self {
+            OpCode::Constant
+            | OpCode::GetLocal
+            | OpCode::SetLocal
+            | OpCode::GetUpvalue
+            | OpCode::SetUpvalue
+            | OpCode::GetGlobal
+            | OpCode::DefineGlobal
+            | OpCode::SetGlobal
+            | OpCode::GetProperty
+            | OpCode::SetProperty
+            | OpCode::GetSuper
+            | OpCode::Call
+            | OpCode::Closure
+            | OpCode::Class
+            | OpCode::Method => 1,
+            OpCode::Jump
+            | OpCode::JumpIfFalse
+            | OpCode::Loop
+            | OpCode::Invoke
+            | OpCode::SuperInvoke => 2,
+
+            _ => 0,
+        }
+    }
+}
+
+/// A jump or loop whose distance, in bytes, doesn't fit in its 16-bit
+/// operand.
+#[derive(Debug, PartialEq, Eq)]
+pub struct JumpTooFar(pub usize);
+
+impl std::fmt::Display for JumpTooFar {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "jump of {} bytes exceeds the maximum of {}",
+            self.0,
+            u16::MAX
+        )
+    }
+}
+
 #[derive(Debug)]
 pub struct Chunk {
     pub code: Vec<u8>,
@@ -34,7 +111,51 @@ impl Chunk {
         }
     }
 
+    /// The number of bytes of code, counting operands.
+    pub fn code_len(&self) -> usize {
+        self.code.len()
+    }
+
+    /// The number of values in the constant pool.
+    pub fn constants_len(&self) -> usize {
+        self.constants.len()
+    }
+
+    /// The constant at `index`, or `None` if the pool isn't that long.
+    pub fn constant(&self, index: usize) -> Option<&Value> {
+        self.constants.get(index)
+    }
+
+    /// The decoded instructions with their offsets, skipping operands.
+    pub fn instructions(&self) -> Instructions<'_> {
+        Instructions {
+            chunk: self,
+            offset: 0,
+        }
+    }
+
+    /// The offset of the instruction after the one at `offset`.
+    fn next_offset(&self, offset: usize) -> usize {
+        let op = OpCode::from(self.code[offset]);
+        let mut next = offset + 1 + op.operand_len();
+
+        if op == OpCode::Closure
+            && let Some(&constant) = self.code.get(offset + 1)
+            && let Some(Value::Function(function)) = self.constant(constant.into())
+        {
+            next += 2 * function.upvalue_count;
+        }
+
+        next
+    }
+
+    /// Adds `value` to the constant pool, reusing the slot of an equal value
+    /// already there.
     pub fn add_constant(&mut self, value: Value) -> usize {
+        if let Some(index) = self.constants.iter().position(|c| c.same_constant(&value)) {
+            return index;
+        }
+
         self.constants.push(value);
 
         self.constants.len() - 1
@@ -58,6 +179,60 @@ impl Chunk {
     pub fn write_instruction(&mut self, instruction: OpCode, line: usize) {
         self.write_byte(instruction.into(), line);
     }
+
+    /// Writes a jump with a placeholder distance, returning the site to give
+    /// [`Chunk::patch_jump`] once the target is known.
+    pub fn emit_jump(&mut self, instruction: OpCode, line: usize) -> usize {
+        self.write_instruction(instruction, line);
+        self.write_byte(0xff, line);
+        self.write_byte(0xff, line);
+
+        self.code.len() - 2
+    }
+
+    /// Points the jump at `site` to the end of the code so far.
+    pub fn patch_jump(&mut self, site: usize) -> Result<(), JumpTooFar> {
+        // -2 to adjust for the jump offset itself.
+        let jump = self.code.len() - site - 2;
+        let jump = u16::try_from(jump).map_err(|_| JumpTooFar(jump))?;
+
+        self.code[site..site + 2].copy_from_slice(&jump.to_be_bytes());
+
+        Ok(())
+    }
+
+    /// Writes a loop back to `start`. Its distance is left as 0 if too far,
+    /// so the chunk stays well-formed.
+    pub fn emit_loop(&mut self, start: usize, line: usize) -> Result<(), JumpTooFar> {
+        self.write_instruction(OpCode::Loop, line);
+
+        // +2 to skip over the loop's own offset.
+        let distance = self.code.len() - start + 2;
+        let offset = u16::try_from(distance);
+        for byte in offset.unwrap_or(0).to_be_bytes() {
+            self.write_byte(byte, line);
+        }
+
+        offset.map(|_| ()).map_err(|_| JumpTooFar(distance))
+    }
+}
+
+/// See [`Chunk::instructions`].
+pub struct Instructions<'a> {
+    chunk: &'a Chunk,
+    offset: usize,
+}
+
+impl Iterator for Instructions<'_> {
+    type Item = (usize, OpCode);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let offset = self.offset;
+        let byte = *self.chunk.code.get(offset)?;
+        self.offset = self.chunk.next_offset(offset);
+
+        Some((offset, OpCode::from(byte)))
+    }
 }
 
 impl Default for Chunk {
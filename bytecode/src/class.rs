@@ -0,0 +1,72 @@
+use std::cell::RefCell;
+use std::fmt::Display;
+use std::rc::Rc;
+
+use crate::function::Closure;
+use crate::table::Table;
+use crate::value::Value;
+
+#[derive(Debug)]
+pub struct Class {
+    pub name: Rc<str>,
+    /// Filled in by `OP_METHOD` after the class is created.
+    pub methods: RefCell<Table<Rc<Closure>>>,
+}
+
+impl Class {
+    #[must_use]
+    pub fn new(name: Rc<str>) -> Self {
+        Class {
+            name,
+            methods: RefCell::new(Table::new()),
+        }
+    }
+
+    pub fn find_method(&self, name: &str) -> Option<Rc<Closure>> {
+        self.methods.borrow().get(name).cloned()
+    }
+}
+
+impl Display for Class {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "<class {}>", self.name)
+    }
+}
+
+#[derive(Debug)]
+pub struct Instance {
+    pub class: Rc<Class>,
+    pub fields: RefCell<Table<Value>>,
+}
+
+impl Instance {
+    #[must_use]
+    pub fn new(class: Rc<Class>) -> Self {
+        Instance {
+            class,
+            fields: RefCell::new(Table::new()),
+        }
+    }
+}
+
+impl Display for Instance {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "<instance of {}>", self.class.name)
+    }
+}
+
+/// A method looked up on an instance, remembering the instance to use as
+/// `this` when it's called.
+#[derive(Debug)]
+pub struct BoundMethod {
+    pub receiver: Rc<Instance>,
+    pub method: Rc<Closure>,
+}
+
+impl Display for BoundMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let class = &self.receiver.class.name;
+        let name = self.method.function.name.as_deref().unwrap_or_default();
+        write!(f, "<bound method {class}.{name}>")
+    }
+}
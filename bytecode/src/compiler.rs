@@ -1,22 +1,1083 @@
-use crate::scanner::{Scanner, TokenType};
+use std::rc::Rc;
 
-pub fn compile(source: &str) {
-    let mut scanner = Scanner::new(source);
+use common::sink::Sink;
 
-    let mut line = 0;
+use crate::chunk::{Chunk, OpCode};
+use crate::function::Function;
+use crate::scanner::{Scanner, Token, TokenType};
+use crate::table::Strings;
+use crate::value::Value;
 
-    loop {
-        let token = scanner.scan_token();
-        if token.line != line {
-            print!("{:04} ", token.line);
-            line = token.line;
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Precedence {
+    None,
+    Assignment, // =
+    Or,         // or
+    And,        // and
+    Equality,   // == !=
+    Comparison, // < > <= >=
+    Term,       // + -
+    Factor,     // * /
+    Unary,      // ! -
+    Call,       // . ()
+    Primary,
+}
+
+impl Precedence {
+    /// The next-higher precedence, used to make binary operators
+    /// left-associative.
+    fn next(self) -> Self {
+        match self {
+            Precedence::None => Precedence::Assignment,
+            Precedence::Assignment => Precedence::Or,
+            Precedence::Or => Precedence::And,
+            Precedence::And => Precedence::Equality,
+            Precedence::Equality => Precedence::Comparison,
+            Precedence::Comparison => Precedence::Term,
+            Precedence::Term => Precedence::Factor,
+            Precedence::Factor => Precedence::Unary,
+            Precedence::Unary => Precedence::Call,
+            Precedence::Call | Precedence::Primary => Precedence::Primary,
+        }
+    }
+}
+
+/// A prefix or infix parser. The flag says whether an `=` may follow, so
+/// that `a * b = c` is rejected rather than parsed as `a * (b = c)`.
+type ParseFn<'src> = fn(&mut Compiler<'src>, bool);
+
+struct ParseRule<'src> {
+    prefix: Option<ParseFn<'src>>,
+    infix: Option<ParseFn<'src>>,
+    precedence: Precedence,
+}
+
+impl<'src> ParseRule<'src> {
+    fn new(
+        prefix: Option<ParseFn<'src>>,
+        infix: Option<ParseFn<'src>>,
+        precedence: Precedence,
+    ) -> Self {
+        ParseRule {
+            prefix,
+            infix,
+            precedence,
+        }
+    }
+
+    fn get(kind: TokenType) -> Self {
+        use TokenType as TT;
+
+        match kind {
+            TT::LeftParen => ParseRule::new(
+                Some(Compiler::grouping),
+                Some(Compiler::call),
+                Precedence::Call,
+            ),
+            TT::Minus => ParseRule::new(
+                Some(Compiler::unary),
+                Some(Compiler::binary),
+                Precedence::Term,
+            ),
+            TT::Plus => ParseRule::new(None, Some(Compiler::binary), Precedence::Term),
+            TT::Slash | TT::Star => {
+                ParseRule::new(None, Some(Compiler::binary), Precedence::Factor)
+            }
+            TT::Bang => ParseRule::new(Some(Compiler::unary), None, Precedence::None),
+            TT::BangEqual | TT::EqualEqual => {
+                ParseRule::new(None, Some(Compiler::binary), Precedence::Equality)
+            }
+            TT::Greater | TT::GreaterEqual | TT::Less | TT::LessEqual => {
+                ParseRule::new(None, Some(Compiler::binary), Precedence::Comparison)
+            }
+            TT::Identifier => ParseRule::new(Some(Compiler::variable), None, Precedence::None),
+            TT::String => ParseRule::new(Some(Compiler::string), None, Precedence::None),
+            TT::Number => ParseRule::new(Some(Compiler::number), None, Precedence::None),
+            TT::False | TT::Nil | TT::True => {
+                ParseRule::new(Some(Compiler::literal), None, Precedence::None)
+            }
+            TT::And => ParseRule::new(None, Some(Compiler::and), Precedence::And),
+            TT::Or => ParseRule::new(None, Some(Compiler::or), Precedence::Or),
+            TT::Dot => ParseRule::new(None, Some(Compiler::dot), Precedence::Call),
+            TT::Super => ParseRule::new(Some(Compiler::super_), None, Precedence::None),
+            TT::This => ParseRule::new(Some(Compiler::this), None, Precedence::None),
+
+            _ => ParseRule::new(None, None, Precedence::None),
+        }
+    }
+}
+
+struct Local<'src> {
+    name: &'src str,
+    /// The scope depth the local was declared at, or `None` while its
+    /// initializer is still being compiled.
+    depth: Option<usize>,
+    /// Whether a closure captures the local, so it must be moved off the
+    /// stack when it goes out of scope.
+    is_captured: bool,
+}
+
+/// Where a function finds a captured variable when its closure is created:
+/// a local slot of the enclosing function, or one of that function's own
+/// upvalues.
+#[derive(Clone, Copy)]
+struct UpvalueRef {
+    index: u8,
+    is_local: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum FunctionKind {
+    Function,
+    Initializer,
+    Method,
+    Script,
+}
+
+/// A class declaration being compiled.
+struct ClassState {
+    has_superclass: bool,
+}
+
+/// A function being compiled.
+struct FunctionState<'src> {
+    function: Function,
+    kind: FunctionKind,
+    /// Locals in scope, in stack slot order. Slot 0 holds the function being
+    /// called, or `this` in a method.
+    locals: Vec<Local<'src>>,
+    upvalues: Vec<UpvalueRef>,
+    scope_depth: usize,
+}
+
+impl FunctionState<'_> {
+    fn new(kind: FunctionKind, name: Option<Rc<str>>) -> Self {
+        // Only methods can refer to their receiver.
+        let receiver = match kind {
+            FunctionKind::Initializer | FunctionKind::Method => "this",
+            FunctionKind::Function | FunctionKind::Script => "",
+        };
+
+        FunctionState {
+            function: Function::new(name),
+            kind,
+            locals: vec![Local {
+                name: receiver,
+                depth: Some(0),
+                is_captured: false,
+            }],
+            upvalues: vec![],
+            scope_depth: 0,
+        }
+    }
+}
+
+struct Compiler<'src> {
+    scanner: Scanner<'src>,
+    current: Token<'src>,
+    previous: Token<'src>,
+    had_error: bool,
+    panic_mode: bool,
+    /// The functions being compiled, innermost last. The script is first.
+    functions: Vec<FunctionState<'src>>,
+    /// The class declarations enclosing the code being compiled, innermost
+    /// last.
+    classes: Vec<ClassState>,
+    err: &'src Sink,
+    strings: &'src mut Strings,
+}
+
+impl<'src> Compiler<'src> {
+    fn new(source: &'src str, err: &'src Sink, strings: &'src mut Strings) -> Self {
+        let placeholder = Token::new(TokenType::Eof, "", 0);
+
+        Compiler {
+            scanner: Scanner::new(source),
+            current: placeholder,
+            previous: placeholder,
+            had_error: false,
+            panic_mode: false,
+            functions: vec![FunctionState::new(FunctionKind::Script, None)],
+            classes: vec![],
+            err,
+            strings,
+        }
+    }
+
+    fn state(&self) -> &FunctionState<'src> {
+        self.functions.last().unwrap()
+    }
+
+    fn state_mut(&mut self) -> &mut FunctionState<'src> {
+        self.functions.last_mut().unwrap()
+    }
+
+    fn chunk(&mut self) -> &mut Chunk {
+        &mut self.state_mut().function.chunk
+    }
+
+    fn error_at(&mut self, token: Token<'src>, message: &str) {
+        // Suppress cascading errors until the parser resynchronizes.
+        if self.panic_mode {
+            return;
+        }
+        self.panic_mode = true;
+
+        let at = match token.kind {
+            TokenType::Eof => " at end".to_owned(),
+            // The lexeme of an error token is the message itself.
+            TokenType::Error => String::new(),
+            _ => format!(" at '{}'", token.lexeme),
+        };
+        // Tokens made up by the compiler have no column to give.
+        let position = match token.column {
+            0 => token.line.to_string(),
+            column => format!("{}:{column}", token.line),
+        };
+        writeln!(self.err, "[line {position}] Error{at}: {message}");
+
+        self.had_error = true;
+    }
+
+    fn error(&mut self, message: &str) {
+        self.error_at(self.previous, message);
+    }
+
+    fn error_at_current(&mut self, message: &str) {
+        self.error_at(self.current, message);
+    }
+
+    fn advance(&mut self) {
+        self.previous = self.current;
+
+        loop {
+            self.current = self.scanner.scan_token();
+            if self.current.kind != TokenType::Error {
+                break;
+            }
+
+            self.error_at_current(self.current.lexeme);
+        }
+    }
+
+    fn consume(&mut self, kind: TokenType, message: &str) {
+        if self.current.kind == kind {
+            self.advance();
+            return;
+        }
+
+        self.error_at_current(message);
+    }
+
+    fn check(&self, kind: TokenType) -> bool {
+        self.current.kind == kind
+    }
+
+    fn catch(&mut self, kind: TokenType) -> bool {
+        if !self.check(kind) {
+            return false;
+        }
+
+        self.advance();
+        true
+    }
+
+    fn emit(&mut self, instruction: OpCode) {
+        let line = self.previous.line;
+        self.chunk().write_instruction(instruction, line);
+    }
+
+    fn emit_with_operand(&mut self, instruction: OpCode, operand: u8) {
+        self.emit(instruction);
+
+        let line = self.previous.line;
+        self.chunk().write_byte(operand, line);
+    }
+
+    /// Emits a jump with a placeholder offset, returning where to patch it.
+    fn emit_jump(&mut self, instruction: OpCode) -> usize {
+        let line = self.previous.line;
+
+        self.chunk().emit_jump(instruction, line)
+    }
+
+    fn patch_jump(&mut self, offset: usize) {
+        if self.chunk().patch_jump(offset).is_err() {
+            self.error("Too much code to jump over.");
+        }
+    }
+
+    fn emit_loop(&mut self, loop_start: usize) {
+        let line = self.previous.line;
+
+        if self.chunk().emit_loop(loop_start, line).is_err() {
+            self.error("Loop body too large.");
+        }
+    }
+
+    fn emit_return(&mut self) {
+        // An initializer always returns its instance.
+        if self.state().kind == FunctionKind::Initializer {
+            self.emit_with_operand(OpCode::GetLocal, 0);
         } else {
-            print!("   | ");
+            self.emit(OpCode::Nil);
+        }
+
+        self.emit(OpCode::Return);
+    }
+
+    fn make_constant(&mut self, value: Value) -> u8 {
+        let constant = self.chunk().add_constant(value);
+
+        u8::try_from(constant).unwrap_or_else(|_| {
+            self.error("Too many constants in one chunk.");
+            0
+        })
+    }
+
+    fn emit_constant(&mut self, value: Value) {
+        let constant = self.make_constant(value);
+        self.emit_with_operand(OpCode::Constant, constant);
+    }
+
+    fn begin_function(&mut self, kind: FunctionKind) {
+        let name = self.previous.lexeme.into();
+        self.functions.push(FunctionState::new(kind, Some(name)));
+    }
+
+    /// Finishes the innermost function, returning it along with the
+    /// variables its closure captures.
+    fn end_function(&mut self) -> (Function, Vec<UpvalueRef>) {
+        self.emit_return();
+        let state = self.functions.pop().unwrap();
+
+        #[cfg(feature = "print-code")]
+        if !self.had_error {
+            state
+                .function
+                .chunk
+                .disassemble(&state.function.to_string());
+        }
+
+        (state.function, state.upvalues)
+    }
+
+    fn begin_scope(&mut self) {
+        self.state_mut().scope_depth += 1;
+    }
+
+    fn end_scope(&mut self) {
+        self.state_mut().scope_depth -= 1;
+
+        while self
+            .state()
+            .locals
+            .last()
+            .is_some_and(|local| local.depth > Some(self.state().scope_depth))
+        {
+            let local = self.state_mut().locals.pop().unwrap();
+            if local.is_captured {
+                self.emit(OpCode::CloseUpvalue);
+            } else {
+                self.emit(OpCode::Pop);
+            }
+        }
+    }
+
+    fn identifier_constant(&mut self, name: Token<'src>) -> u8 {
+        let name = self.strings.intern(name.lexeme);
+        self.make_constant(Value::String(name))
+    }
+
+    fn add_local(&mut self, name: &'src str) {
+        if self.state().locals.len() > u8::MAX as usize {
+            self.error("Too many local variables in function.");
+            return;
+        }
+
+        self.state_mut().locals.push(Local {
+            name,
+            depth: None,
+            is_captured: false,
+        });
+    }
+
+    fn declare_variable(&mut self) {
+        let scope_depth = self.state().scope_depth;
+        if scope_depth == 0 {
+            return;
+        }
+
+        let name = self.previous.lexeme;
+
+        let duplicate = self
+            .state()
+            .locals
+            .iter()
+            .rev()
+            .take_while(|local| local.depth.is_none_or(|depth| depth >= scope_depth))
+            .any(|local| local.name == name);
+        if duplicate {
+            self.error("Already a variable with this name in this scope.");
+        }
+
+        self.add_local(name);
+    }
+
+    /// Parses a variable name, returning its name constant if it's a global.
+    fn parse_variable(&mut self, message: &str) -> u8 {
+        self.consume(TokenType::Identifier, message);
+
+        self.declare_variable();
+        if self.state().scope_depth > 0 {
+            return 0;
+        }
+
+        self.identifier_constant(self.previous)
+    }
+
+    fn mark_initialized(&mut self) {
+        let state = self.state_mut();
+
+        // Functions are bound as soon as they're declared, even globals.
+        if state.scope_depth == 0 {
+            return;
+        }
+
+        if let Some(local) = state.locals.last_mut() {
+            local.depth = Some(state.scope_depth);
+        }
+    }
+
+    fn define_variable(&mut self, global: u8) {
+        if self.state().scope_depth > 0 {
+            self.mark_initialized();
+            return;
+        }
+
+        self.emit_with_operand(OpCode::DefineGlobal, global);
+    }
+
+    /// Finds `name` among the locals of the function at `level` in
+    /// `self.functions`.
+    fn resolve_local(&mut self, level: usize, name: Token<'src>) -> Option<u8> {
+        let (slot, local) = self.functions[level]
+            .locals
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, local)| local.name == name.lexeme)?;
+
+        if local.depth.is_none() {
+            self.error("Can't read local variable in its own initializer.");
+        }
+
+        // There are never more than 256 locals.
+        Some(slot as u8)
+    }
+
+    /// Finds `name` in a function enclosing the one at `level`, capturing it
+    /// as an upvalue of every function in between.
+    fn resolve_upvalue(&mut self, level: usize, name: Token<'src>) -> Option<u8> {
+        let enclosing = level.checked_sub(1)?;
+
+        if let Some(slot) = self.resolve_local(enclosing, name) {
+            self.functions[enclosing].locals[slot as usize].is_captured = true;
+            return Some(self.add_upvalue(level, slot, true));
+        }
+
+        let index = self.resolve_upvalue(enclosing, name)?;
+        Some(self.add_upvalue(level, index, false))
+    }
+
+    fn add_upvalue(&mut self, level: usize, index: u8, is_local: bool) -> u8 {
+        let upvalues = &self.functions[level].upvalues;
+
+        // Closures referring to the same variable twice share one upvalue.
+        if let Some(existing) = upvalues
+            .iter()
+            .position(|upvalue| upvalue.index == index && upvalue.is_local == is_local)
+        {
+            return existing as u8;
         }
-        println!("{:02} '{}'", token.kind, token.lexeme);
 
-        if token.kind == TokenType::Eof {
-            break;
+        if upvalues.len() > u8::MAX as usize {
+            self.error("Too many closure variables in function.");
+            return 0;
+        }
+
+        let state = &mut self.functions[level];
+        state.upvalues.push(UpvalueRef { index, is_local });
+        state.function.upvalue_count = state.upvalues.len();
+
+        (state.upvalues.len() - 1) as u8
+    }
+
+    fn parse_precedence(&mut self, precedence: Precedence) {
+        self.advance();
+
+        let Some(prefix) = ParseRule::get(self.previous.kind).prefix else {
+            self.error("Expect expression.");
+            return;
+        };
+
+        let can_assign = precedence <= Precedence::Assignment;
+        prefix(self, can_assign);
+
+        while precedence <= ParseRule::get(self.current.kind).precedence {
+            self.advance();
+            if let Some(infix) = ParseRule::get(self.previous.kind).infix {
+                infix(self, can_assign);
+            }
+        }
+
+        if can_assign && self.catch(TokenType::Equal) {
+            self.error("Invalid assignment target.");
+        }
+    }
+
+    fn expression(&mut self) {
+        self.parse_precedence(Precedence::Assignment);
+    }
+
+    fn number(&mut self, _can_assign: bool) {
+        let value: f64 = self.previous.lexeme.parse().unwrap();
+        self.emit_constant(Value::Number(value));
+    }
+
+    fn string(&mut self, _can_assign: bool) {
+        // Trim the surrounding quotes.
+        let lexeme = self.previous.lexeme;
+        let string = self.strings.intern(&lexeme[1..lexeme.len() - 1]);
+        self.emit_constant(Value::String(string));
+    }
+
+    fn literal(&mut self, _can_assign: bool) {
+        match self.previous.kind {
+            TokenType::False => self.emit(OpCode::False),
+            TokenType::Nil => self.emit(OpCode::Nil),
+            TokenType::True => self.emit(OpCode::True),
+
+            _ => unreachable!(),
         }
     }
+
+    fn named_variable(&mut self, name: Token<'src>, can_assign: bool) {
+        let level = self.functions.len() - 1;
+
+        let (get, set, operand) = if let Some(slot) = self.resolve_local(level, name) {
+            (OpCode::GetLocal, OpCode::SetLocal, slot)
+        } else if let Some(index) = self.resolve_upvalue(level, name) {
+            (OpCode::GetUpvalue, OpCode::SetUpvalue, index)
+        } else {
+            let global = self.identifier_constant(name);
+            (OpCode::GetGlobal, OpCode::SetGlobal, global)
+        };
+
+        if can_assign && self.catch(TokenType::Equal) {
+            self.expression();
+            self.emit_with_operand(set, operand);
+        } else {
+            self.emit_with_operand(get, operand);
+        }
+    }
+
+    fn variable(&mut self, can_assign: bool) {
+        self.named_variable(self.previous, can_assign);
+    }
+
+    fn and(&mut self, _can_assign: bool) {
+        let end_jump = self.emit_jump(OpCode::JumpIfFalse);
+
+        self.emit(OpCode::Pop);
+        self.parse_precedence(Precedence::And);
+
+        self.patch_jump(end_jump);
+    }
+
+    fn or(&mut self, _can_assign: bool) {
+        let else_jump = self.emit_jump(OpCode::JumpIfFalse);
+        let end_jump = self.emit_jump(OpCode::Jump);
+
+        self.patch_jump(else_jump);
+        self.emit(OpCode::Pop);
+
+        self.parse_precedence(Precedence::Or);
+        self.patch_jump(end_jump);
+    }
+
+    fn argument_list(&mut self) -> u8 {
+        let mut count: usize = 0;
+
+        if !self.check(TokenType::RightParen) {
+            loop {
+                self.expression();
+                if count == u8::MAX as usize {
+                    self.error("Can't have more than 255 arguments.");
+                }
+                count += 1;
+
+                if !self.catch(TokenType::Comma) {
+                    break;
+                }
+            }
+        }
+        self.consume(TokenType::RightParen, "Expect ')' after arguments.");
+
+        count.min(u8::MAX as usize) as u8
+    }
+
+    fn call(&mut self, _can_assign: bool) {
+        let count = self.argument_list();
+        self.emit_with_operand(OpCode::Call, count);
+    }
+
+    fn dot(&mut self, can_assign: bool) {
+        self.consume(TokenType::Identifier, "Expect property name after '.'.");
+        let name = self.identifier_constant(self.previous);
+
+        if can_assign && self.catch(TokenType::Equal) {
+            self.expression();
+            self.emit_with_operand(OpCode::SetProperty, name);
+        } else if self.catch(TokenType::LeftParen) {
+            // Calling a method directly skips creating a bound method.
+            let arg_count = self.argument_list();
+            self.emit_with_operand(OpCode::Invoke, name);
+            let line = self.previous.line;
+            self.chunk().write_byte(arg_count, line);
+        } else {
+            self.emit_with_operand(OpCode::GetProperty, name);
+        }
+    }
+
+    fn super_(&mut self, _can_assign: bool) {
+        match self.classes.last() {
+            None => self.error("Can't use 'super' outside of a class."),
+            Some(class) if !class.has_superclass => {
+                self.error("Can't use 'super' in a class with no superclass.");
+            }
+            Some(_) => (),
+        }
+
+        self.consume(TokenType::Dot, "Expect '.' after 'super'.");
+        self.consume(TokenType::Identifier, "Expect superclass method name.");
+        let name = self.identifier_constant(self.previous);
+
+        let line = self.previous.line;
+        self.named_variable(Token::new(TokenType::This, "this", line), false);
+        let super_ = Token::new(TokenType::Super, "super", line);
+
+        if self.catch(TokenType::LeftParen) {
+            let arg_count = self.argument_list();
+            self.named_variable(super_, false);
+            self.emit_with_operand(OpCode::SuperInvoke, name);
+            let line = self.previous.line;
+            self.chunk().write_byte(arg_count, line);
+        } else {
+            self.named_variable(super_, false);
+            self.emit_with_operand(OpCode::GetSuper, name);
+        }
+    }
+
+    fn this(&mut self, _can_assign: bool) {
+        if self.classes.is_empty() {
+            self.error("Can't use 'this' outside of a class.");
+            return;
+        }
+
+        self.variable(false);
+    }
+
+    fn grouping(&mut self, _can_assign: bool) {
+        self.expression();
+        self.consume(TokenType::RightParen, "Expect ')' after expression.");
+    }
+
+    fn unary(&mut self, _can_assign: bool) {
+        let operator = self.previous.kind;
+
+        self.parse_precedence(Precedence::Unary);
+
+        match operator {
+            TokenType::Bang => self.emit(OpCode::Not),
+            TokenType::Minus => self.emit(OpCode::Negate),
+
+            _ => unreachable!(),
+        }
+    }
+
+    fn binary(&mut self, _can_assign: bool) {
+        let operator = self.previous.kind;
+
+        let rule = ParseRule::get(operator);
+        self.parse_precedence(rule.precedence.next());
+
+        match operator {
+            TokenType::BangEqual => {
+                self.emit(OpCode::Equal);
+                self.emit(OpCode::Not);
+            }
+            TokenType::EqualEqual => self.emit(OpCode::Equal),
+            TokenType::Greater => self.emit(OpCode::Greater),
+            TokenType::GreaterEqual => {
+                self.emit(OpCode::Less);
+                self.emit(OpCode::Not);
+            }
+            TokenType::Less => self.emit(OpCode::Less),
+            TokenType::LessEqual => {
+                self.emit(OpCode::Greater);
+                self.emit(OpCode::Not);
+            }
+            TokenType::Plus => self.emit(OpCode::Add),
+            TokenType::Minus => self.emit(OpCode::Subtract),
+            TokenType::Star => self.emit(OpCode::Multiply),
+            TokenType::Slash => self.emit(OpCode::Divide),
+
+            _ => unreachable!(),
+        }
+    }
+
+    fn block(&mut self) {
+        while !self.check(TokenType::RightBrace) && !self.check(TokenType::Eof) {
+            self.declaration();
+        }
+
+        self.consume(TokenType::RightBrace, "Expect '}' after block.");
+    }
+
+    fn var_declaration(&mut self) {
+        let global = self.parse_variable("Expect variable name.");
+
+        if self.catch(TokenType::Equal) {
+            self.expression();
+        } else {
+            self.emit(OpCode::Nil);
+        }
+        self.consume(
+            TokenType::Semicolon,
+            "Expect ';' after variable declaration.",
+        );
+
+        self.define_variable(global);
+    }
+
+    fn function(&mut self, kind: FunctionKind) {
+        self.begin_function(kind);
+        self.begin_scope();
+
+        self.consume(TokenType::LeftParen, "Expect '(' after function name.");
+        if !self.check(TokenType::RightParen) {
+            loop {
+                self.state_mut().function.arity += 1;
+                if self.state().function.arity > u8::MAX as usize {
+                    self.error_at_current("Can't have more than 255 parameters.");
+                }
+
+                let constant = self.parse_variable("Expect parameter name.");
+                self.define_variable(constant);
+
+                if !self.catch(TokenType::Comma) {
+                    break;
+                }
+            }
+        }
+        self.consume(TokenType::RightParen, "Expect ')' after parameters.");
+        self.consume(TokenType::LeftBrace, "Expect '{' before function body.");
+        self.block();
+
+        // No end_scope: the frame's slots are discarded wholesale on return.
+        let (function, upvalues) = self.end_function();
+        let constant = self.make_constant(Value::Function(Rc::new(function)));
+        self.emit_with_operand(OpCode::Closure, constant);
+
+        let line = self.previous.line;
+        for upvalue in upvalues {
+            self.chunk().write_byte(upvalue.is_local.into(), line);
+            self.chunk().write_byte(upvalue.index, line);
+        }
+    }
+
+    fn method(&mut self) {
+        self.consume(TokenType::Identifier, "Expect method name.");
+        let constant = self.identifier_constant(self.previous);
+
+        let kind = if self.previous.lexeme == "init" {
+            FunctionKind::Initializer
+        } else {
+            FunctionKind::Method
+        };
+        self.function(kind);
+
+        self.emit_with_operand(OpCode::Method, constant);
+    }
+
+    fn class_declaration(&mut self) {
+        self.consume(TokenType::Identifier, "Expect class name.");
+        let class_name = self.previous;
+        let name_constant = self.identifier_constant(class_name);
+        self.declare_variable();
+
+        self.emit_with_operand(OpCode::Class, name_constant);
+        self.define_variable(name_constant);
+
+        self.classes.push(ClassState {
+            has_superclass: false,
+        });
+
+        if self.catch(TokenType::Less) {
+            self.consume(TokenType::Identifier, "Expect superclass name.");
+            self.variable(false);
+
+            if self.previous.lexeme == class_name.lexeme {
+                self.error("A class can't inherit from itself.");
+            }
+
+            // Methods close over a local holding the superclass.
+            self.begin_scope();
+            self.add_local("super");
+            self.define_variable(0);
+
+            self.named_variable(class_name, false);
+            self.emit(OpCode::Inherit);
+            self.classes.last_mut().unwrap().has_superclass = true;
+        }
+
+        // Methods are attached to the class on top of the stack.
+        self.named_variable(class_name, false);
+        self.consume(TokenType::LeftBrace, "Expect '{' before class body.");
+        while !self.check(TokenType::RightBrace) && !self.check(TokenType::Eof) {
+            self.method();
+        }
+        self.consume(TokenType::RightBrace, "Expect '}' after class body.");
+        self.emit(OpCode::Pop);
+
+        if self.classes.pop().unwrap().has_superclass {
+            self.end_scope();
+        }
+    }
+
+    fn fun_declaration(&mut self) {
+        let global = self.parse_variable("Expect function name.");
+        // A function may refer to itself, so it's usable before its body.
+        self.mark_initialized();
+        self.function(FunctionKind::Function);
+        self.define_variable(global);
+    }
+
+    fn print_statement(&mut self) {
+        self.expression();
+        self.consume(TokenType::Semicolon, "Expect ';' after value.");
+        self.emit(OpCode::Print);
+    }
+
+    fn return_statement(&mut self) {
+        if self.state().kind == FunctionKind::Script {
+            self.error("Can't return from top-level code.");
+        }
+
+        if self.catch(TokenType::Semicolon) {
+            self.emit_return();
+        } else {
+            if self.state().kind == FunctionKind::Initializer {
+                self.error("Can't return a value from an initializer.");
+            }
+
+            self.expression();
+            self.consume(TokenType::Semicolon, "Expect ';' after return value.");
+            self.emit(OpCode::Return);
+        }
+    }
+
+    fn expression_statement(&mut self) {
+        self.expression();
+        self.consume(TokenType::Semicolon, "Expect ';' after expression.");
+        self.emit(OpCode::Pop);
+    }
+
+    fn if_statement(&mut self) {
+        self.consume(TokenType::LeftParen, "Expect '(' after 'if'.");
+        self.expression();
+        self.consume(TokenType::RightParen, "Expect ')' after condition.");
+
+        let then_jump = self.emit_jump(OpCode::JumpIfFalse);
+        self.emit(OpCode::Pop);
+        self.statement();
+
+        let else_jump = self.emit_jump(OpCode::Jump);
+
+        self.patch_jump(then_jump);
+        self.emit(OpCode::Pop);
+
+        if self.catch(TokenType::Else) {
+            self.statement();
+        }
+        self.patch_jump(else_jump);
+    }
+
+    fn while_statement(&mut self) {
+        let loop_start = self.chunk().code.len();
+
+        self.consume(TokenType::LeftParen, "Expect '(' after 'while'.");
+        self.expression();
+        self.consume(TokenType::RightParen, "Expect ')' after condition.");
+
+        let exit_jump = self.emit_jump(OpCode::JumpIfFalse);
+        self.emit(OpCode::Pop);
+        self.statement();
+        self.emit_loop(loop_start);
+
+        self.patch_jump(exit_jump);
+        self.emit(OpCode::Pop);
+    }
+
+    fn for_statement(&mut self) {
+        self.begin_scope();
+
+        self.consume(TokenType::LeftParen, "Expect '(' after 'for'.");
+        let mut loop_variable = None;
+        if self.catch(TokenType::Semicolon) {
+            // No initializer.
+        } else if self.catch(TokenType::Var) {
+            self.var_declaration();
+            loop_variable = Some(self.state().locals.len() - 1);
+        } else {
+            self.expression_statement();
+        }
+
+        let mut loop_start = self.chunk().code.len();
+
+        let mut exit_jump = None;
+        if !self.catch(TokenType::Semicolon) {
+            self.expression();
+            self.consume(TokenType::Semicolon, "Expect ';' after loop condition.");
+
+            exit_jump = Some(self.emit_jump(OpCode::JumpIfFalse));
+            self.emit(OpCode::Pop);
+        }
+
+        if !self.catch(TokenType::RightParen) {
+            // The increment runs after the body, so jump over it for now.
+            let body_jump = self.emit_jump(OpCode::Jump);
+            let increment_start = self.chunk().code.len();
+
+            self.expression();
+            self.emit(OpCode::Pop);
+            self.consume(TokenType::RightParen, "Expect ')' after for clauses.");
+
+            self.emit_loop(loop_start);
+            loop_start = increment_start;
+            self.patch_jump(body_jump);
+        }
+
+        // Each run of the body gets its own copy of the loop variable, so
+        // closures capture that iteration's value. Any change to the copy is
+        // written back for the increment to see.
+        if let Some(outer) = loop_variable {
+            let name = self.state().locals[outer].name;
+
+            self.begin_scope();
+            self.emit_with_operand(OpCode::GetLocal, outer as u8);
+            self.add_local(name);
+            self.mark_initialized();
+
+            self.statement();
+
+            let inner = self.state().locals.len() - 1;
+            self.emit_with_operand(OpCode::GetLocal, inner as u8);
+            self.emit_with_operand(OpCode::SetLocal, outer as u8);
+            self.emit(OpCode::Pop);
+            self.end_scope();
+        } else {
+            self.statement();
+        }
+        self.emit_loop(loop_start);
+
+        if let Some(exit_jump) = exit_jump {
+            self.patch_jump(exit_jump);
+            self.emit(OpCode::Pop);
+        }
+
+        self.end_scope();
+    }
+
+    fn statement(&mut self) {
+        if self.catch(TokenType::Print) {
+            self.print_statement();
+        } else if self.catch(TokenType::For) {
+            self.for_statement();
+        } else if self.catch(TokenType::If) {
+            self.if_statement();
+        } else if self.catch(TokenType::Return) {
+            self.return_statement();
+        } else if self.catch(TokenType::While) {
+            self.while_statement();
+        } else if self.catch(TokenType::LeftBrace) {
+            self.begin_scope();
+            self.block();
+            self.end_scope();
+        } else {
+            self.expression_statement();
+        }
+    }
+
+    fn synchronize(&mut self) {
+        use TokenType as TT;
+
+        self.panic_mode = false;
+
+        while !self.check(TT::Eof) {
+            if self.previous.kind == TT::Semicolon {
+                return;
+            }
+
+            match self.current.kind {
+                TT::Class
+                | TT::Fun
+                | TT::Var
+                | TT::For
+                | TT::If
+                | TT::While
+                | TT::Print
+                | TT::Return => return,
+
+                _ => (),
+            }
+
+            self.advance();
+        }
+    }
+
+    fn declaration(&mut self) {
+        if self.catch(TokenType::Class) {
+            self.class_declaration();
+        } else if self.catch(TokenType::Fun) {
+            self.fun_declaration();
+        } else if self.catch(TokenType::Var) {
+            self.var_declaration();
+        } else {
+            self.statement();
+        }
+
+        if self.panic_mode {
+            self.synchronize();
+        }
+    }
+}
+
+/// Compiles `source` into the script's function, reporting any errors to
+/// `err` and interning its strings in `strings`.
+pub fn compile(source: &str, err: &Sink, strings: &mut Strings) -> Option<Function> {
+    let mut compiler = Compiler::new(source, err, strings);
+
+    compiler.advance();
+    while !compiler.catch(TokenType::Eof) {
+        compiler.declaration();
+    }
+    let (script, _) = compiler.end_function();
+
+    if compiler.had_error {
+        None
+    } else {
+        Some(script)
+    }
 }
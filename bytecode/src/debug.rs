@@ -1,4 +1,6 @@
 use crate::chunk::{Chunk, OpCode};
+use crate::function::Function;
+use crate::value::Value;
 
 impl Chunk {
     fn simple_instruction(name: &'static str, offset: usize) -> usize {
@@ -9,12 +11,64 @@ impl Chunk {
 
     fn constant_instruction(name: &'static str, chunk: &Chunk, offset: usize) -> usize {
         let constant = chunk.code[offset + 1];
-        let value = chunk.constants[constant as usize];
+        let value = &chunk.constants[constant as usize];
         println!("{name:<16} {constant:>4} '{value}'");
 
         offset + 2
     }
 
+    fn byte_instruction(name: &'static str, chunk: &Chunk, offset: usize) -> usize {
+        let slot = chunk.code[offset + 1];
+        println!("{name:<16} {slot:>4}");
+
+        offset + 2
+    }
+
+    fn invoke_instruction(name: &'static str, chunk: &Chunk, offset: usize) -> usize {
+        let constant = chunk.code[offset + 1];
+        let arg_count = chunk.code[offset + 2];
+        let value = &chunk.constants[constant as usize];
+        println!("{name:<16} ({arg_count} args) {constant:>4} '{value}'");
+
+        offset + 3
+    }
+
+    fn jump_instruction(name: &'static str, forward: bool, chunk: &Chunk, offset: usize) -> usize {
+        let jump = u16::from_be_bytes([chunk.code[offset + 1], chunk.code[offset + 2]]) as usize;
+        let target = if forward {
+            offset + 3 + jump
+        } else {
+            offset + 3 - jump
+        };
+        println!("{name:<16} {offset:>4} -> {target}");
+
+        offset + 3
+    }
+
+    /// A closure is followed by an (is_local, index) pair for each upvalue it
+    /// captures.
+    fn closure_instruction(name: &'static str, chunk: &Chunk, offset: usize) -> usize {
+        let constant = chunk.code[offset + 1];
+        let value = &chunk.constants[constant as usize];
+        println!("{name:<16} {constant:>4} '{value}'");
+
+        let Value::Function(function) = value else {
+            unreachable!("closures always wrap a function constant");
+        };
+
+        let mut offset = offset + 2;
+        for _ in 0..function.upvalue_count {
+            let is_local = chunk.code[offset];
+            let index = chunk.code[offset + 1];
+            let kind = if is_local == 1 { "local" } else { "upvalue" };
+            println!("{offset:04}    |                     {kind} {index}");
+
+            offset += 2;
+        }
+
+        offset
+    }
+
     pub fn disassemble_instruction(&self, offset: usize) -> usize {
         print!("{offset:04} ");
 
@@ -27,12 +81,42 @@ impl Chunk {
 
         match self.code[offset].into() {
             OpCode::Constant => Chunk::constant_instruction("OP_CONSTANT", self, offset),
+            OpCode::Not => Chunk::simple_instruction("OP_NOT", offset),
+            OpCode::Nil => Chunk::simple_instruction("OP_NIL", offset),
+            OpCode::True => Chunk::simple_instruction("OP_TRUE", offset),
+            OpCode::False => Chunk::simple_instruction("OP_FALSE", offset),
+            OpCode::Pop => Chunk::simple_instruction("OP_POP", offset),
+            OpCode::GetLocal => Chunk::byte_instruction("OP_GET_LOCAL", self, offset),
+            OpCode::SetLocal => Chunk::byte_instruction("OP_SET_LOCAL", self, offset),
+            OpCode::GetUpvalue => Chunk::byte_instruction("OP_GET_UPVALUE", self, offset),
+            OpCode::SetUpvalue => Chunk::byte_instruction("OP_SET_UPVALUE", self, offset),
+            OpCode::GetGlobal => Chunk::constant_instruction("OP_GET_GLOBAL", self, offset),
+            OpCode::DefineGlobal => Chunk::constant_instruction("OP_DEFINE_GLOBAL", self, offset),
+            OpCode::SetGlobal => Chunk::constant_instruction("OP_SET_GLOBAL", self, offset),
+            OpCode::GetProperty => Chunk::constant_instruction("OP_GET_PROPERTY", self, offset),
+            OpCode::SetProperty => Chunk::constant_instruction("OP_SET_PROPERTY", self, offset),
+            OpCode::GetSuper => Chunk::constant_instruction("OP_GET_SUPER", self, offset),
+            OpCode::Equal => Chunk::simple_instruction("OP_EQUAL", offset),
+            OpCode::Greater => Chunk::simple_instruction("OP_GREATER", offset),
+            OpCode::Less => Chunk::simple_instruction("OP_LESS", offset),
             OpCode::Negate => Chunk::simple_instruction("OP_NEGATE", offset),
+            OpCode::Print => Chunk::simple_instruction("OP_PRINT", offset),
+            OpCode::Jump => Chunk::jump_instruction("OP_JUMP", true, self, offset),
+            OpCode::JumpIfFalse => Chunk::jump_instruction("OP_JUMP_IF_FALSE", true, self, offset),
+            OpCode::Loop => Chunk::jump_instruction("OP_LOOP", false, self, offset),
+            OpCode::Call => Chunk::byte_instruction("OP_CALL", self, offset),
+            OpCode::Invoke => Chunk::invoke_instruction("OP_INVOKE", self, offset),
+            OpCode::SuperInvoke => Chunk::invoke_instruction("OP_SUPER_INVOKE", self, offset),
+            OpCode::Closure => Chunk::closure_instruction("OP_CLOSURE", self, offset),
+            OpCode::CloseUpvalue => Chunk::simple_instruction("OP_CLOSE_UPVALUE", offset),
             OpCode::Add => Chunk::simple_instruction("OP_ADD", offset),
             OpCode::Subtract => Chunk::simple_instruction("OP_SUBTRACT", offset),
             OpCode::Multiply => Chunk::simple_instruction("OP_MULTIPLY", offset),
             OpCode::Divide => Chunk::simple_instruction("OP_DIVIDE", offset),
             OpCode::Return => Chunk::simple_instruction("OP_RETURN", offset),
+            OpCode::Class => Chunk::constant_instruction("OP_CLASS", self, offset),
+            OpCode::Inherit => Chunk::simple_instruction("OP_INHERIT", offset),
+            OpCode::Method => Chunk::constant_instruction("OP_METHOD", self, offset),
 
             OpCode::Unknown(byte) => {
                 println!("Unknown opcode {byte}");
@@ -42,7 +126,7 @@ impl Chunk {
         }
     }
 
-    pub fn disassemble(&self, name: &'static str) {
+    pub fn disassemble(&self, name: &str) {
         println!("== {name} ==");
 
         let mut offset = 0;
@@ -52,3 +136,17 @@ impl Chunk {
         }
     }
 }
+
+impl Function {
+    /// Disassembles this function's chunk, then those of the functions
+    /// declared in it, depth first.
+    pub fn disassemble_all(&self) {
+        self.chunk.disassemble(&self.to_string());
+
+        for constant in &self.chunk.constants {
+            if let Value::Function(function) = constant {
+                function.disassemble_all();
+            }
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use std::cell::RefCell;
+use std::fmt::{Debug, Display};
+use std::rc::Rc;
+
+use crate::chunk::Chunk;
+use crate::value::Value;
+
+/// A compiled function. The top-level script is one too, without a name.
+#[derive(Debug)]
+pub struct Function {
+    pub arity: usize,
+    pub upvalue_count: usize,
+    pub chunk: Chunk,
+    pub name: Option<Rc<str>>,
+}
+
+impl Function {
+    #[must_use]
+    pub fn new(name: Option<Rc<str>>) -> Self {
+        Function {
+            arity: 0,
+            upvalue_count: 0,
+            chunk: Chunk::new(),
+            name,
+        }
+    }
+}
+
+impl Display for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "<fn {name}>"),
+            None => write!(f, "<script>"),
+        }
+    }
+}
+
+/// A variable captured by a closure. It stays open, pointing at the stack
+/// slot, for as long as the variable lives there; once that slot is popped
+/// the value moves into the upvalue itself.
+#[derive(Debug)]
+pub enum Upvalue {
+    Open(usize),
+    Closed(Value),
+}
+
+/// A function together with the variables it captured when it was created.
+#[derive(Debug)]
+pub struct Closure {
+    pub function: Rc<Function>,
+    pub upvalues: Vec<Rc<RefCell<Upvalue>>>,
+}
+
+impl Closure {
+    #[must_use]
+    pub fn new(function: Rc<Function>) -> Self {
+        Closure {
+            function,
+            upvalues: vec![],
+        }
+    }
+}
+
+impl Display for Closure {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.function)
+    }
+}
+
+pub type NativeCode = fn(&[Value]) -> Value;
+
+pub struct NativeFn {
+    pub name: &'static str,
+    pub arity: usize,
+    pub code: NativeCode,
+}
+
+impl Debug for NativeFn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NativeFn")
+            .field("name", &self.name)
+            .field("arity", &self.arity)
+            .finish_non_exhaustive()
+    }
+}
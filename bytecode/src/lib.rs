@@ -1,10 +1,11 @@
-#![feature(derive_from)]
 pub mod chunk;
+pub mod class;
 pub mod compiler;
-#[cfg(debug_assertions)]
 pub mod debug;
+pub mod function;
 #[warn(clippy::pedantic)]
 pub mod scanner;
 pub mod stack;
+pub mod table;
 pub mod value;
 pub mod vm;
@@ -1,14 +1,83 @@
+use std::fs::read_to_string;
+
+use bytecode::compiler::compile;
+use bytecode::scanner::{Scanner, TokenType};
+use bytecode::table::Strings;
 use bytecode::vm::{repl, run_file};
+use common::cli::{Args, Emit};
+use common::exit::{IO_ERROR, SYNTAX_ERROR, TOO_MANY_ARGS};
+use common::sink::Sink;
+
+const USAGE: &str = "Usage: bytecode [--check] [--emit=run|tokens|bytecode] [path]";
+
+fn usage() -> ! {
+    eprintln!("{USAGE}");
+    std::process::exit(TOO_MANY_ARGS);
+}
+
+fn emit_tokens(source: &str) {
+    let mut scanner = Scanner::new(source);
+
+    loop {
+        let token = scanner.scan_token();
+        println!("{token}");
+
+        if token.kind == TokenType::Eof {
+            break;
+        }
+    }
+}
 
 fn main() {
-    let args: Vec<_> = std::env::args().collect();
-
-    if args.len() == 1 {
-        repl();
-    } else if args.len() == 2 {
-        run_file(&args[1]);
-    } else {
-        eprintln!("Usage: bytecode [path]");
-        std::process::exit(64);
+    let args = Args::parse(std::env::args().skip(1)).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        usage();
+    });
+
+    // These answer and exit before anything else is looked at.
+    if args.help {
+        println!("{USAGE}");
+        return;
+    }
+    if args.version {
+        println!("bytecode {}", env!("CARGO_PKG_VERSION"));
+        return;
+    }
+    if !args.flags.is_empty() || !args.script_args.is_empty() {
+        usage();
+    }
+
+    if args.emit == Emit::Run && !args.check {
+        match args.script {
+            None => repl(),
+            Some(path) => run_file(&path),
+        }
+
+        return;
+    }
+
+    let Some(path) = args.script else {
+        usage();
+    };
+    let Ok(source) = read_to_string(&path) else {
+        eprintln!("Could not read file \"{path}\".");
+        std::process::exit(IO_ERROR);
+    };
+
+    match args.emit {
+        Emit::Tokens => emit_tokens(&source),
+        Emit::Run | Emit::Bytecode => {
+            let Some(script) = compile(&source, &Sink::Stderr, &mut Strings::new()) else {
+                std::process::exit(SYNTAX_ERROR);
+            };
+
+            if args.emit == Emit::Bytecode {
+                script.disassemble_all();
+            }
+        }
+        Emit::Ast => {
+            eprintln!("bytecode has no syntax tree to emit.");
+            usage();
+        }
     }
 }
@@ -51,16 +51,51 @@ pub enum TokenType {
     Eof,
 }
 
+#[derive(Debug, Clone, Copy)]
 pub struct Token<'src> {
     pub kind: TokenType,
     pub lexeme: &'src str,
     pub line: usize,
+    /// The 1-based column, in characters, where the token starts. Zero for
+    /// tokens made up by the compiler.
+    pub column: usize,
+    /// The byte offset in the source where the token starts.
+    pub start: usize,
+    /// How many bytes of source the token covers. For error tokens, whose
+    /// lexeme is the message, this is the offending text instead.
+    pub len: usize,
 }
 
 impl<'src> Token<'src> {
+    /// A token made up by the compiler rather than scanned, which covers no
+    /// source.
     #[must_use]
     pub fn new(kind: TokenType, lexeme: &'src str, line: usize) -> Self {
-        Token { kind, lexeme, line }
+        Token {
+            kind,
+            lexeme,
+            line,
+            column: 0,
+            start: 0,
+            len: 0,
+        }
+    }
+}
+
+/// Formats like treewalk's tokens: the kind, the lexeme, then the literal
+/// value, which is `nil` for tokens that aren't literals.
+impl std::fmt::Display for Token<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?} {} ", self.kind, self.lexeme)?;
+
+        match self.kind {
+            TokenType::String => write!(f, "{}", &self.lexeme[1..self.lexeme.len() - 1]),
+            TokenType::Number => match self.lexeme.parse::<f64>() {
+                Ok(value) => write!(f, "{value}"),
+                Err(_) => write!(f, "nil"),
+            },
+            _ => write!(f, "nil"),
+        }
     }
 }
 
@@ -76,6 +111,10 @@ pub struct Scanner<'src> {
     start: usize,
     current: usize,
     line: usize,
+    /// The byte offset where the current line begins.
+    line_start: usize,
+    /// The column of `start`.
+    column: usize,
 }
 
 impl<'src> Scanner<'src> {
@@ -88,26 +127,53 @@ impl<'src> Scanner<'src> {
             start: 0,
             current: 0,
             line: 1,
+            line_start: 0,
+            column: 1,
         }
     }
 
     fn is_at_end(&self) -> bool {
-        self.current == self.source.len()
+        self.current >= self.source.len()
     }
 
     fn make_token(&self, kind: TokenType) -> Token<'src> {
         let lexeme = &self.source[self.start..self.current];
-        Token::new(kind, lexeme, self.line)
+        Token {
+            kind,
+            lexeme,
+            line: self.line,
+            column: self.column,
+            start: self.start,
+            len: self.current - self.start,
+        }
     }
 
     fn error_token(&self, message: &'static str) -> Token<'src> {
-        Token::new(TokenType::Error, message, self.line)
+        Token {
+            kind: TokenType::Error,
+            lexeme: message,
+            line: self.line,
+            column: self.column,
+            start: self.start,
+            len: self.current - self.start,
+        }
     }
 
+    /// Consumes the next character whole, so `current` always stays on a
+    /// character boundary.
     fn advance(&mut self) -> char {
-        let byte = self.source.as_bytes()[self.current];
-        self.current += 1;
-        byte as char
+        let c = self.source[self.current..]
+            .chars()
+            .next()
+            .expect("not at the end");
+        self.current += c.len_utf8();
+        c
+    }
+
+    /// Notes that a newline was just consumed.
+    fn new_line(&mut self) {
+        self.line += 1;
+        self.line_start = self.current;
     }
 
     fn catch(&mut self, expected: char) -> bool {
@@ -120,16 +186,19 @@ impl<'src> Scanner<'src> {
         true
     }
 
+    /// Returns the next character, or `'\0'` at the end of the source.
     fn peek(&self) -> char {
-        self.source.as_bytes()[self.current] as char
+        self.source
+            .as_bytes()
+            .get(self.current)
+            .map_or('\0', |&byte| byte as char)
     }
 
     fn peek_next(&self) -> Option<char> {
-        if self.is_at_end() {
-            None
-        } else {
-            Some(self.source.as_bytes()[self.current + 1] as char)
-        }
+        self.source
+            .as_bytes()
+            .get(self.current + 1)
+            .map(|&byte| byte as char)
     }
 
     fn skip_whitespace(&mut self) {
@@ -138,11 +207,11 @@ impl<'src> Scanner<'src> {
 
             match c {
                 c if c.is_ascii_whitespace() => {
+                    self.advance();
+
                     if c == '\n' {
-                        self.line += 1;
+                        self.new_line();
                     }
-
-                    self.advance();
                 }
 
                 '/' => {
@@ -163,10 +232,9 @@ impl<'src> Scanner<'src> {
 
     fn string(&mut self) -> Token<'src> {
         while self.peek() != '"' && !self.is_at_end() {
-            if self.peek() == '\n' {
-                self.line += 1;
+            if self.advance() == '\n' {
+                self.new_line();
             }
-            self.advance();
         }
 
         if self.is_at_end() {
@@ -235,6 +303,7 @@ impl<'src> Scanner<'src> {
 
         self.skip_whitespace();
         self.start = self.current;
+        self.column = self.source[self.line_start..self.start].chars().count() + 1;
 
         if self.is_at_end() {
             return self.make_token(TokenType::Eof);
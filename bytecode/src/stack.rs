@@ -52,6 +52,41 @@ impl<T, const N: usize> Stack<T, N> {
         }
     }
 
+    /// Returns the value `distance` slots down from the top.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the stack holds `distance` values or fewer.
+    pub fn peek(&self, distance: usize) -> &T {
+        &self.inner[self.inner.len() - 1 - distance]
+    }
+
+    /// Returns the value in `slot`, counting up from the bottom.
+    pub fn get(&self, slot: usize) -> &T {
+        &self.inner[slot]
+    }
+
+    pub fn set(&mut self, slot: usize, value: T) {
+        self.inner[slot] = value;
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Drops every value above the first `len`.
+    pub fn truncate(&mut self, len: usize) {
+        self.inner.truncate(len);
+    }
+
+    pub fn reset(&mut self) {
+        self.inner.clear();
+    }
+
     pub const fn as_slice(&self) -> &[T] {
         self.inner.as_slice()
     }
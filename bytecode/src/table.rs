@@ -0,0 +1,263 @@
+use std::rc::Rc;
+
+/// The fraction of a table's slots, tombstones included, that may be in use
+/// before it grows.
+const MAX_LOAD: f64 = 0.75;
+
+/// FNV-1a, as clox hashes its strings.
+fn hash_string(key: &str) -> u32 {
+    let mut hash = 2_166_136_261u32;
+    for byte in key.bytes() {
+        hash ^= u32::from(byte);
+        hash = hash.wrapping_mul(16_777_619);
+    }
+
+    hash
+}
+
+#[derive(Debug, Clone)]
+enum Entry<V> {
+    Empty,
+    /// A deleted entry. Lookups probe past it; inserts may reuse it.
+    Tombstone,
+    Occupied {
+        key: Rc<str>,
+        hash: u32,
+        value: V,
+    },
+}
+
+/// A hash table keyed by strings, using open addressing with linear probing.
+#[derive(Debug, Clone)]
+pub struct Table<V> {
+    entries: Vec<Entry<V>>,
+    /// Occupied entries plus tombstones, since both lengthen probe sequences.
+    count: usize,
+    /// Occupied entries alone.
+    len: usize,
+}
+
+impl<V> Table<V> {
+    #[must_use]
+    pub fn new() -> Self {
+        Table {
+            entries: vec![],
+            count: 0,
+            len: 0,
+        }
+    }
+
+    /// The number of slots. The table grows before more than `MAX_LOAD` of
+    /// them are in use, so fewer entries than this fit.
+    pub fn capacity(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Whether one more entry would push the table past its load factor.
+    fn is_full(&self) -> bool {
+        (self.count + 1) as f64 > self.entries.len() as f64 * MAX_LOAD
+    }
+
+    /// Finds the slot `key` is in, or else the one it should go in: the first
+    /// tombstone passed on the way, or the empty slot that ended the search.
+    ///
+    /// The table must have at least one empty slot.
+    fn find_slot(entries: &[Entry<V>], key: &str, hash: u32) -> usize {
+        let capacity = entries.len();
+        let mut index = hash as usize % capacity;
+        let mut tombstone = None;
+
+        loop {
+            match &entries[index] {
+                Entry::Empty => return tombstone.unwrap_or(index),
+                Entry::Tombstone => {
+                    tombstone.get_or_insert(index);
+                }
+                Entry::Occupied {
+                    key: existing,
+                    hash: existing_hash,
+                    ..
+                } => {
+                    // Interned keys compare by identity; the fallback keeps
+                    // the table correct for keys that aren't.
+                    if *existing_hash == hash
+                        && (std::ptr::eq(&**existing, key) || **existing == *key)
+                    {
+                        return index;
+                    }
+                }
+            }
+
+            index = (index + 1) % capacity;
+        }
+    }
+
+    fn grow(&mut self) {
+        let capacity = if self.entries.is_empty() {
+            8
+        } else {
+            self.entries.len() * 2
+        };
+
+        self.rebuild(capacity);
+    }
+
+    /// Moves every entry into a fresh array of `capacity` slots.
+    fn rebuild(&mut self, capacity: usize) {
+        let old = std::mem::replace(
+            &mut self.entries,
+            (0..capacity).map(|_| Entry::Empty).collect(),
+        );
+
+        // Tombstones aren't carried over.
+        self.count = 0;
+        for entry in old {
+            if let Entry::Occupied { key, hash, value } = entry {
+                let index = Self::find_slot(&self.entries, &key, hash);
+                self.entries[index] = Entry::Occupied { key, hash, value };
+                self.count += 1;
+            }
+        }
+        self.len = self.count;
+    }
+
+    pub fn get(&self, key: &str) -> Option<&V> {
+        if self.entries.is_empty() {
+            return None;
+        }
+
+        let index = Self::find_slot(&self.entries, key, hash_string(key));
+        match &self.entries[index] {
+            Entry::Occupied { value, .. } => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Sets `key` to `value`, returning whether the key is new.
+    pub fn insert(&mut self, key: Rc<str>, value: V) -> bool {
+        if self.is_full() {
+            self.grow();
+        }
+
+        let hash = hash_string(&key);
+        let index = Self::find_slot(&self.entries, &key, hash);
+        let entry = &mut self.entries[index];
+
+        let is_new = !matches!(entry, Entry::Occupied { .. });
+        // A reused tombstone is already counted.
+        if matches!(entry, Entry::Empty) {
+            self.count += 1;
+        }
+        if is_new {
+            self.len += 1;
+        }
+
+        *entry = Entry::Occupied { key, hash, value };
+
+        is_new
+    }
+
+    /// Removes `key`, returning whether it was present.
+    pub fn remove(&mut self, key: &str) -> bool {
+        if self.entries.is_empty() {
+            return false;
+        }
+
+        let index = Self::find_slot(&self.entries, key, hash_string(key));
+        let entry = &mut self.entries[index];
+        if !matches!(entry, Entry::Occupied { .. }) {
+            return false;
+        }
+
+        *entry = Entry::Tombstone;
+        self.len -= 1;
+
+        true
+    }
+
+    /// Removes every entry for which `keep` returns false, reclaiming their
+    /// slots outright rather than leaving tombstones.
+    pub fn retain(&mut self, mut keep: impl FnMut(&Rc<str>, &V) -> bool) {
+        for entry in &mut self.entries {
+            if let Entry::Occupied { key, value, .. } = entry
+                && !keep(key, value)
+            {
+                *entry = Entry::Tombstone;
+            }
+        }
+
+        self.rebuild(self.entries.len());
+    }
+
+    /// Finds the key equal to `key`, so callers can share it.
+    pub fn find_key(&self, key: &str) -> Option<&Rc<str>> {
+        if self.entries.is_empty() {
+            return None;
+        }
+
+        let index = Self::find_slot(&self.entries, key, hash_string(key));
+        match &self.entries[index] {
+            Entry::Occupied { key, .. } => Some(key),
+            _ => None,
+        }
+    }
+}
+
+impl<V> Default for Table<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The set of interned strings, so equal strings share one allocation.
+///
+/// The set doesn't keep its strings alive: one only it refers to is dropped
+/// the next time the set would otherwise grow.
+#[derive(Debug, Default)]
+pub struct Strings {
+    table: Table<()>,
+}
+
+impl Strings {
+    #[must_use]
+    pub fn new() -> Self {
+        Strings {
+            table: Table::new(),
+        }
+    }
+
+    pub fn intern(&mut self, string: &str) -> Rc<str> {
+        if let Some(interned) = self.table.find_key(string) {
+            return interned.clone();
+        }
+
+        if self.table.is_full() {
+            self.table.retain(|key, ()| Rc::strong_count(key) > 1);
+        }
+
+        let interned: Rc<str> = string.into();
+        self.table.insert(interned.clone(), ());
+
+        interned
+    }
+
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+}
@@ -1,14 +1,22 @@
+use std::cell::RefCell;
 use std::fs::read_to_string;
 use std::io::{Write, stdin, stdout};
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use common::exit::{IO_ERROR, RUNTIME_ERROR, SYNTAX_ERROR};
+use common::sink::Sink;
 
-use crate::chunk::{Chunk, OpCode};
+use crate::chunk::OpCode;
+use crate::class::{BoundMethod, Class, Instance};
 use crate::compiler::compile;
+use crate::function::{Closure, NativeCode, NativeFn, Upvalue};
 use crate::stack::Stack;
+use crate::table::{Strings, Table};
 use crate::value::Value;
 
-pub const STACK_MAX: usize = 256;
+pub const FRAMES_MAX: usize = 64;
+pub const STACK_MAX: usize = FRAMES_MAX * (u8::MAX as usize + 1);
 
 pub enum InterpretError {
     IoError(std::io::Error),
@@ -24,19 +32,56 @@ impl From<std::io::Error> for InterpretError {
 
 pub type InterpretResult = Result<(), InterpretError>;
 
-pub struct Vm {
-    chunk: Chunk,
+/// An ongoing function call.
+struct CallFrame {
+    closure: Rc<Closure>,
     ip: usize,
+    /// Where the frame's slots start on the stack. Slot 0 is the function
+    /// itself, followed by its arguments and locals.
+    slot_base: usize,
+}
+
+pub struct Vm {
+    frames: Vec<CallFrame>,
     stack: Stack<Value, STACK_MAX>,
+    globals: Table<Value>,
+    strings: Strings,
+    /// Upvalues still pointing into the stack, ordered by slot so closing
+    /// them on scope exit only looks at the tail.
+    open_upvalues: Vec<Rc<RefCell<Upvalue>>>,
+    /// Where `print` writes.
+    out: Sink,
+    /// Where compile and runtime errors are written.
+    err: Sink,
 }
 
 impl Vm {
     pub fn new() -> Self {
-        Vm {
-            chunk: Chunk::new(),
-            ip: 0,
+        let mut vm = Vm {
+            frames: Vec::with_capacity(FRAMES_MAX),
             stack: Stack::new(),
-        }
+            globals: Table::new(),
+            strings: Strings::new(),
+            open_upvalues: vec![],
+            out: Sink::Stdout,
+            err: Sink::Stderr,
+        };
+
+        vm.define_native("clock", 0, clock_native);
+
+        vm
+    }
+
+    fn define_native(&mut self, name: &'static str, arity: usize, code: NativeCode) {
+        let native = NativeFn { name, arity, code };
+        let name = self.strings.intern(name);
+        self.globals.insert(name, Value::Native(Rc::new(native)));
+    }
+
+    /// Redirects program output and error reporting, e.g. to capture them.
+    pub fn set_output(&mut self, out: Sink, err: Sink) {
+        self.out = out;
+        self.err = err;
     }
 
     fn push(&mut self, value: Value) {
@@ -47,41 +92,261 @@ impl Vm {
         self.stack.pop()
     }
 
+    fn peek(&self, distance: usize) -> &Value {
+        self.stack.peek(distance)
+    }
+
+    fn frame(&self) -> &CallFrame {
+        self.frames.last().unwrap()
+    }
+
+    fn frame_mut(&mut self) -> &mut CallFrame {
+        self.frames.last_mut().unwrap()
+    }
+
+    fn runtime_error(&mut self, message: &str) -> InterpretError {
+        writeln!(self.err, "{message}");
+
+        for frame in self.frames.iter().rev() {
+            let function = &frame.closure.function;
+            let line = function.chunk.lines[frame.ip - 1];
+
+            match &function.name {
+                Some(name) => writeln!(self.err, "[line {line}] in {name}()"),
+                None => writeln!(self.err, "[line {line}] in script"),
+            }
+        }
+
+        self.stack.reset();
+        self.frames.clear();
+        self.open_upvalues.clear();
+        InterpretError::RuntimeError
+    }
+
+    fn call(&mut self, closure: Rc<Closure>, arg_count: usize) -> InterpretResult {
+        if arg_count != closure.function.arity {
+            return Err(self.runtime_error(&format!(
+                "Expected {} arguments but got {arg_count}.",
+                closure.function.arity
+            )));
+        }
+
+        if self.frames.len() == FRAMES_MAX {
+            return Err(self.runtime_error("Stack overflow."));
+        }
+
+        let slot_base = self.stack.len() - arg_count - 1;
+        self.frames.push(CallFrame {
+            closure,
+            ip: 0,
+            slot_base,
+        });
+
+        Ok(())
+    }
+
+    fn call_value(&mut self, callee: Value, arg_count: usize) -> InterpretResult {
+        match callee {
+            Value::Closure(closure) => self.call(closure, arg_count),
+            Value::BoundMethod(bound) => {
+                // The receiver takes the callee's slot, becoming `this`.
+                let slot = self.stack.len() - arg_count - 1;
+                self.stack
+                    .set(slot, Value::Instance(bound.receiver.clone()));
+                self.call(bound.method.clone(), arg_count)
+            }
+            Value::Class(class) => {
+                let slot = self.stack.len() - arg_count - 1;
+                let instance = Instance::new(class.clone());
+                self.stack.set(slot, Value::Instance(Rc::new(instance)));
+
+                match class.find_method("init") {
+                    Some(initializer) => self.call(initializer, arg_count),
+                    None if arg_count != 0 => {
+                        Err(self
+                            .runtime_error(&format!("Expected 0 arguments but got {arg_count}.")))
+                    }
+                    None => Ok(()),
+                }
+            }
+            Value::Native(native) => {
+                if arg_count != native.arity {
+                    return Err(self.runtime_error(&format!(
+                        "Expected {} arguments but got {arg_count}.",
+                        native.arity
+                    )));
+                }
+
+                let args_start = self.stack.len() - arg_count;
+                let result = (native.code)(&self.stack.as_slice()[args_start..]);
+
+                // Drop the arguments and the native itself.
+                self.stack.truncate(args_start - 1);
+                self.push(result);
+
+                Ok(())
+            }
+
+            _ => Err(self.runtime_error("Can only call functions and classes.")),
+        }
+    }
+
+    fn invoke_from_class(
+        &mut self,
+        class: &Class,
+        name: &str,
+        arg_count: usize,
+    ) -> InterpretResult {
+        let Some(method) = class.find_method(name) else {
+            return Err(self.runtime_error(&format!("Undefined property '{name}'.")));
+        };
+
+        self.call(method, arg_count)
+    }
+
+    /// Calls the method `name` on the receiver below the arguments, without
+    /// creating a bound method for it.
+    fn invoke(&mut self, name: &str, arg_count: usize) -> InterpretResult {
+        let Value::Instance(instance) = self.peek(arg_count).clone() else {
+            return Err(self.runtime_error("Only instances have methods."));
+        };
+
+        // A field holding a function shadows any method.
+        let field = instance.fields.borrow().get(name).cloned();
+        if let Some(field) = field {
+            let slot = self.stack.len() - arg_count - 1;
+            self.stack.set(slot, field.clone());
+            return self.call_value(field, arg_count);
+        }
+
+        self.invoke_from_class(&instance.class, name, arg_count)
+    }
+
+    /// Replaces the instance on top of the stack with the method `name` of
+    /// `class`, bound to it.
+    fn bind_method(
+        &mut self,
+        class: &Class,
+        instance: Rc<Instance>,
+        name: &str,
+    ) -> InterpretResult {
+        let Some(method) = class.find_method(name) else {
+            return Err(self.runtime_error(&format!("Undefined property '{name}'.")));
+        };
+
+        let bound = BoundMethod {
+            receiver: instance,
+            method,
+        };
+        self.pop();
+        self.push(Value::BoundMethod(Rc::new(bound)));
+
+        Ok(())
+    }
+
+    /// Returns the upvalue for the variable in `slot`, reusing an open one if
+    /// another closure already captured it.
+    fn capture_upvalue(&mut self, slot: usize) -> Rc<RefCell<Upvalue>> {
+        let position = self
+            .open_upvalues
+            .iter()
+            .rposition(|upvalue| matches!(*upvalue.borrow(), Upvalue::Open(open) if open <= slot));
+
+        if let Some(position) = position
+            && matches!(*self.open_upvalues[position].borrow(), Upvalue::Open(open) if open == slot)
+        {
+            return self.open_upvalues[position].clone();
+        }
+
+        let upvalue = Rc::new(RefCell::new(Upvalue::Open(slot)));
+        let index = position.map_or(0, |position| position + 1);
+        self.open_upvalues.insert(index, upvalue.clone());
+
+        upvalue
+    }
+
+    /// Moves every variable at or above `from` off the stack and into the
+    /// upvalues that captured it.
+    fn close_upvalues(&mut self, from: usize) {
+        while let Some(upvalue) = self.open_upvalues.last() {
+            let Upvalue::Open(slot) = *upvalue.borrow() else {
+                unreachable!("only open upvalues are tracked");
+            };
+            if slot < from {
+                break;
+            }
+
+            let value = self.stack.get(slot).clone();
+            *upvalue.borrow_mut() = Upvalue::Closed(value);
+            self.open_upvalues.pop();
+        }
+    }
+
     pub fn run(&mut self) -> InterpretResult {
         macro_rules! read_byte {
             () => {{
-                let byte = self.chunk.code[self.ip];
-                self.ip += 1;
+                let frame = self.frame_mut();
+                let byte = frame.closure.function.chunk.code[frame.ip];
+                frame.ip += 1;
                 byte
             }};
         }
 
+        macro_rules! read_short {
+            () => {
+                u16::from_be_bytes([read_byte!(), read_byte!()]) as usize
+            };
+        }
+
+        // Constants are cloned onto the stack, which is a copy or an `Rc`
+        // increment, and their indices are checked on every fetch. Neither
+        // shows up next to the rest of the dispatch loop (see
+        // `benches/constants.rs`), so the pool stays a plain `Vec`.
         macro_rules! read_constant {
+            () => {{
+                let index = read_byte!() as usize;
+                self.frame().closure.function.chunk.constants[index].clone()
+            }};
+        }
+
+        macro_rules! read_string {
             () => {
-                self.chunk.constants[read_byte!() as usize]
+                match read_constant!() {
+                    Value::String(name) => name,
+                    _ => unreachable!("names are always string constants"),
+                }
             };
         }
 
         macro_rules! binary_op {
-            ($op:tt) => {{
-                let b = self.pop();
-                let a = self.pop();
-                self.push(a $op b);
+            ($kind:path, $op:tt) => {{
+                let (Value::Number(b), Value::Number(a)) = (self.peek(0), self.peek(1)) else {
+                    return Err(self.runtime_error("Operands must be numbers."));
+                };
+                let value = $kind(a $op b);
+                self.pop();
+                self.pop();
+                self.push(value);
             }};
         }
 
         loop {
             let instruction: OpCode = read_byte!().into();
 
-            #[cfg(debug_assertions)]
+            #[cfg(feature = "trace-execution")]
             {
                 print!("          ");
                 for slot in self.stack.iter() {
-                    print!("[ {slot} ]")
+                    print!("[ {slot:?} ]")
                 }
                 println!();
 
-                self.chunk.disassemble_instruction(self.ip - 1);
+                let frame = self.frame();
+                frame
+                    .closure
+                    .function
+                    .chunk
+                    .disassemble_instruction(frame.ip - 1);
             }
 
             match instruction {
@@ -89,18 +354,237 @@ impl Vm {
                     let constant = read_constant!();
                     self.push(constant);
                 }
-                OpCode::Add => binary_op!(+),
-                OpCode::Subtract => binary_op!(-),
-                OpCode::Multiply => binary_op!(*),
-                OpCode::Divide => binary_op!(/),
+                OpCode::Nil => self.push(Value::Nil),
+                OpCode::True => self.push(Value::Bool(true)),
+                OpCode::False => self.push(Value::Bool(false)),
+                OpCode::Pop => {
+                    self.pop();
+                }
+                OpCode::GetLocal => {
+                    let slot = self.frame().slot_base + read_byte!() as usize;
+                    self.push(self.stack.get(slot).clone());
+                }
+                OpCode::SetLocal => {
+                    // Assignment is an expression, so the value stays put.
+                    let slot = self.frame().slot_base + read_byte!() as usize;
+                    self.stack.set(slot, self.peek(0).clone());
+                }
+                OpCode::GetUpvalue => {
+                    let index = read_byte!() as usize;
+                    let upvalue = self.frame().closure.upvalues[index].clone();
+                    let value = match &*upvalue.borrow() {
+                        Upvalue::Open(slot) => self.stack.get(*slot).clone(),
+                        Upvalue::Closed(value) => value.clone(),
+                    };
+                    self.push(value);
+                }
+                OpCode::SetUpvalue => {
+                    let index = read_byte!() as usize;
+                    let upvalue = self.frame().closure.upvalues[index].clone();
+                    let value = self.peek(0).clone();
+                    match &mut *upvalue.borrow_mut() {
+                        Upvalue::Open(slot) => self.stack.set(*slot, value),
+                        Upvalue::Closed(closed) => *closed = value,
+                    }
+                }
+                OpCode::GetGlobal => {
+                    let name = read_string!();
+                    let Some(value) = self.globals.get(&name).cloned() else {
+                        return Err(self.runtime_error(&format!("Undefined variable '{name}'.")));
+                    };
+                    self.push(value);
+                }
+                OpCode::DefineGlobal => {
+                    let name = read_string!();
+                    let value = self.pop();
+                    self.globals.insert(name, value);
+                }
+                OpCode::SetGlobal => {
+                    let name = read_string!();
+                    if self.globals.insert(name.clone(), self.peek(0).clone()) {
+                        // Assignment doesn't declare, so undo it.
+                        self.globals.remove(&name);
+                        return Err(self.runtime_error(&format!("Undefined variable '{name}'.")));
+                    }
+                }
+                OpCode::GetProperty => {
+                    let Value::Instance(instance) = self.peek(0).clone() else {
+                        return Err(self.runtime_error("Only instances have properties."));
+                    };
+                    let name = read_string!();
+
+                    let field = instance.fields.borrow().get(&name).cloned();
+                    match field {
+                        Some(value) => {
+                            self.pop();
+                            self.push(value);
+                        }
+                        None => self.bind_method(&instance.class.clone(), instance, &name)?,
+                    }
+                }
+                OpCode::SetProperty => {
+                    let Value::Instance(instance) = self.peek(1).clone() else {
+                        return Err(self.runtime_error("Only instances have fields."));
+                    };
+                    let name = read_string!();
+
+                    let value = self.pop();
+                    instance.fields.borrow_mut().insert(name, value.clone());
+                    // Replace the instance with the assigned value.
+                    self.pop();
+                    self.push(value);
+                }
+                OpCode::GetSuper => {
+                    let name = read_string!();
+                    let Value::Class(superclass) = self.pop() else {
+                        unreachable!("'super' is always bound to a class");
+                    };
+                    let Value::Instance(instance) = self.peek(0).clone() else {
+                        unreachable!("'this' is always bound to an instance");
+                    };
+
+                    self.bind_method(&superclass, instance, &name)?;
+                }
+                OpCode::Equal => {
+                    let b = self.pop();
+                    let a = self.pop();
+                    self.push(Value::Bool(a == b));
+                }
+                OpCode::Greater => binary_op!(Value::Bool, >),
+                OpCode::Less => binary_op!(Value::Bool, <),
+                OpCode::Add => match (self.peek(1), self.peek(0)) {
+                    (Value::String(a), Value::String(b)) => {
+                        let value = Value::String(self.strings.intern(&format!("{a}{b}")));
+                        self.pop();
+                        self.pop();
+                        self.push(value);
+                    }
+                    (Value::Number(_), Value::Number(_)) => binary_op!(Value::Number, +),
+
+                    _ => {
+                        return Err(
+                            self.runtime_error("Operands must be two numbers or two strings.")
+                        );
+                    }
+                },
+                OpCode::Subtract => binary_op!(Value::Number, -),
+                OpCode::Multiply => binary_op!(Value::Number, *),
+                OpCode::Divide => binary_op!(Value::Number, /),
+                OpCode::Not => {
+                    let value = self.pop();
+                    self.push(Value::Bool(value.is_falsy()));
+                }
                 OpCode::Negate => {
+                    let Value::Number(value) = *self.peek(0) else {
+                        return Err(self.runtime_error("Operand must be a number."));
+                    };
+                    self.pop();
+                    self.push(Value::Number(-value));
+                }
+                OpCode::Print => {
                     let value = self.pop();
-                    self.push(-value);
+                    writeln!(self.out, "{value}");
+                }
+                OpCode::Jump => {
+                    let offset = read_short!();
+                    self.frame_mut().ip += offset;
+                }
+                OpCode::JumpIfFalse => {
+                    let offset = read_short!();
+                    if self.peek(0).is_falsy() {
+                        self.frame_mut().ip += offset;
+                    }
+                }
+                OpCode::Loop => {
+                    let offset = read_short!();
+                    self.frame_mut().ip -= offset;
+                }
+                OpCode::Call => {
+                    let arg_count = read_byte!() as usize;
+                    let callee = self.peek(arg_count).clone();
+                    self.call_value(callee, arg_count)?;
+                }
+                OpCode::Invoke => {
+                    let name = read_string!();
+                    let arg_count = read_byte!() as usize;
+                    self.invoke(&name, arg_count)?;
+                }
+                OpCode::SuperInvoke => {
+                    let name = read_string!();
+                    let arg_count = read_byte!() as usize;
+                    let Value::Class(superclass) = self.pop() else {
+                        unreachable!("'super' is always bound to a class");
+                    };
+
+                    self.invoke_from_class(&superclass, &name, arg_count)?;
+                }
+                OpCode::Closure => {
+                    let Value::Function(function) = read_constant!() else {
+                        unreachable!("closures always wrap a function constant");
+                    };
+
+                    let mut closure = Closure::new(function);
+                    for _ in 0..closure.function.upvalue_count {
+                        let is_local = read_byte!() == 1;
+                        let index = read_byte!() as usize;
+
+                        let upvalue = if is_local {
+                            self.capture_upvalue(self.frame().slot_base + index)
+                        } else {
+                            self.frame().closure.upvalues[index].clone()
+                        };
+                        closure.upvalues.push(upvalue);
+                    }
+
+                    self.push(Value::Closure(Rc::new(closure)));
+                }
+                OpCode::CloseUpvalue => {
+                    self.close_upvalues(self.stack.len() - 1);
+                    self.pop();
                 }
                 OpCode::Return => {
-                    println!("{}", self.pop());
-
-                    return Ok(());
+                    let result = self.pop();
+                    let frame = self.frames.pop().unwrap();
+                    self.close_upvalues(frame.slot_base);
+
+                    if self.frames.is_empty() {
+                        // Pop the script itself.
+                        self.pop();
+                        return Ok(());
+                    }
+
+                    self.stack.truncate(frame.slot_base);
+                    self.push(result);
+                }
+                OpCode::Class => {
+                    let name = read_string!();
+                    self.push(Value::Class(Rc::new(Class::new(name))));
+                }
+                OpCode::Inherit => {
+                    let Value::Class(superclass) = self.peek(1) else {
+                        return Err(self.runtime_error("Superclass must be a class."));
+                    };
+                    let Value::Class(subclass) = self.peek(0) else {
+                        unreachable!("only classes inherit");
+                    };
+
+                    // Copy down the inherited methods; the subclass's own
+                    // are added after, overriding them.
+                    let methods = superclass.methods.borrow().clone();
+                    *subclass.methods.borrow_mut() = methods;
+                    self.pop();
+                }
+                OpCode::Method => {
+                    let name = read_string!();
+                    let Value::Closure(method) = self.peek(0).clone() else {
+                        unreachable!("methods are always closures");
+                    };
+                    let Value::Class(class) = self.peek(1) else {
+                        unreachable!("methods are always defined on a class");
+                    };
+
+                    class.methods.borrow_mut().insert(name, method);
+                    self.pop();
                 }
                 OpCode::Unknown(_) => unreachable!(),
             }
@@ -108,12 +592,23 @@ impl Vm {
     }
 
     pub fn interpret(&mut self, source: &str) -> InterpretResult {
-        compile(source);
+        let script =
+            compile(source, &self.err, &mut self.strings).ok_or(InterpretError::CompileError)?;
+        let script = Rc::new(Closure::new(Rc::new(script)));
 
-        Ok(())
+        self.push(Value::Closure(script.clone()));
+        self.call(script, 0)?;
+
+        self.run()
     }
 }
 
+fn clock_native(_args: &[Value]) -> Value {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+
+    Value::Number(now.as_secs_f64())
+}
+
 impl Default for Vm {
     fn default() -> Self {
         Self::new()
@@ -134,6 +629,7 @@ fn prompt() {
 }
 
 pub fn repl() {
+    let mut vm = Vm::new();
     let mut line = String::new();
 
     loop {
@@ -146,7 +642,7 @@ pub fn repl() {
             break;
         }
 
-        let _ = Vm::new().interpret(&line);
+        let _ = vm.interpret(&line);
         line.clear();
     }
 }
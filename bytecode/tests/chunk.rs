@@ -0,0 +1,67 @@
+use bytecode::chunk::{Chunk, JumpTooFar, OpCode};
+use bytecode::value::Value;
+
+#[test]
+fn forward_jumps_land_after_the_code_they_skip() {
+    let mut chunk = Chunk::new();
+
+    let site = chunk.emit_jump(OpCode::Jump, 1);
+    chunk.write_instruction(OpCode::Nil, 1);
+    chunk.write_instruction(OpCode::Pop, 1);
+    chunk.patch_jump(site).unwrap();
+
+    assert_eq!(site, 1);
+    assert_eq!(chunk.code[1..3], [0, 2]);
+}
+
+#[test]
+fn over_long_jumps_are_errors() {
+    let mut chunk = Chunk::new();
+
+    let site = chunk.emit_jump(OpCode::JumpIfFalse, 1);
+    for _ in 0..=u16::MAX {
+        chunk.write_instruction(OpCode::Nil, 1);
+    }
+
+    assert_eq!(chunk.patch_jump(site), Err(JumpTooFar(65536)));
+}
+
+#[test]
+fn loops_jump_back_to_their_start() {
+    let mut chunk = Chunk::new();
+
+    chunk.write_instruction(OpCode::Nil, 1);
+    let start = chunk.code_len();
+    chunk.write_instruction(OpCode::Pop, 1);
+    chunk.emit_loop(start, 1).unwrap();
+
+    assert_eq!(chunk.code[2..], [OpCode::Loop.into(), 0, 4]);
+
+    for _ in 0..=u16::MAX {
+        chunk.write_instruction(OpCode::Nil, 1);
+    }
+    assert!(chunk.emit_loop(start, 1).is_err());
+}
+
+#[test]
+fn equal_constants_are_stored_once() {
+    let mut chunk = Chunk::new();
+
+    let one = chunk.add_constant(Value::Number(1.0));
+    let name = chunk.add_constant(Value::from("one"));
+
+    assert_eq!(chunk.add_constant(Value::Number(1.0)), one);
+    assert_eq!(chunk.add_constant(Value::from("one")), name);
+    assert_eq!(chunk.constants_len(), 2);
+}
+
+#[test]
+fn float_constants_compare_by_bits() {
+    let mut chunk = Chunk::new();
+
+    let zero = chunk.add_constant(Value::Number(0.0));
+    let nan = chunk.add_constant(Value::Number(f64::NAN));
+
+    assert_ne!(chunk.add_constant(Value::Number(-0.0)), zero);
+    assert_eq!(chunk.add_constant(Value::Number(f64::NAN)), nan);
+}
@@ -0,0 +1,60 @@
+use std::process::Command;
+
+fn run(flag: &str) -> (bool, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_bytecode"))
+        .arg(flag)
+        .output()
+        .unwrap();
+
+    (
+        output.status.success(),
+        String::from_utf8(output.stdout).unwrap(),
+    )
+}
+
+#[test]
+fn version_is_semver() {
+    let (success, stdout) = run("--version");
+    assert!(success);
+
+    let version = stdout.split_whitespace().nth(1).unwrap();
+    let parts: Vec<_> = version.split('.').collect();
+    assert_eq!(parts.len(), 3, "{version}");
+    assert!(
+        parts.iter().all(|part| part.parse::<u32>().is_ok()),
+        "{version}"
+    );
+}
+
+#[test]
+fn help_prints_usage() {
+    let (success, stdout) = run("--help");
+    assert!(success);
+    assert!(stdout.starts_with("Usage: bytecode"), "{stdout}");
+}
+
+#[test]
+fn blank_scripts_run_cleanly() {
+    for (name, source) in [
+        ("empty", ""),
+        ("whitespace", " \n\t\r\n"),
+        ("comment", "// just a comment"),
+    ] {
+        let path =
+            std::env::temp_dir().join(format!("lox-bytecode-{name}-{}.lox", std::process::id()));
+        std::fs::write(&path, source).unwrap();
+
+        let output = Command::new(env!("CARGO_BIN_EXE_bytecode"))
+            .arg(&path)
+            .output()
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(output.status.success(), "{name}");
+        // The debug features disassemble even an empty chunk to stdout.
+        if !cfg!(any(feature = "print-code", feature = "trace-execution")) {
+            assert!(output.stdout.is_empty(), "{name}");
+        }
+        assert!(output.stderr.is_empty(), "{name}");
+    }
+}
@@ -0,0 +1,94 @@
+use bytecode::chunk::OpCode;
+use bytecode::compiler::compile;
+use bytecode::table::Strings;
+use bytecode::value::Value;
+use common::sink::Sink;
+
+#[test]
+fn compile_errors_give_line_and_column() {
+    let (err, reported) = Sink::buffer();
+
+    let script = compile("var a = 1;\nprint a +;", &err, &mut Strings::new());
+
+    assert!(script.is_none());
+    assert_eq!(
+        reported.take(),
+        "[line 2:10] Error at ';': Expect expression.\n"
+    );
+}
+
+#[test]
+fn repeated_constants_share_a_slot() {
+    let (err, _) = Sink::buffer();
+
+    let script = compile("print 0 + 0 + 0;", &err, &mut Strings::new()).unwrap();
+    assert_eq!(script.chunk.constants, [Value::Number(0.0)]);
+
+    let script = compile("print 1 + 1 + 1;", &err, &mut Strings::new()).unwrap();
+    assert_eq!(script.chunk.constants, [Value::Number(1.0)]);
+}
+
+#[test]
+fn instructions_skip_their_operands() {
+    let (err, _) = Sink::buffer();
+
+    let source = "var a = 1; if (a) print a; fun f() { return a; } var g = f;";
+    let script = compile(source, &err, &mut Strings::new()).unwrap();
+    let chunk = &script.chunk;
+
+    let ops = chunk.instructions().map(|(_, op)| op).collect::<Vec<_>>();
+    assert_eq!(
+        ops,
+        [
+            OpCode::Constant,
+            OpCode::DefineGlobal,
+            OpCode::GetGlobal,
+            OpCode::JumpIfFalse,
+            OpCode::Pop,
+            OpCode::GetGlobal,
+            OpCode::Print,
+            OpCode::Jump,
+            OpCode::Pop,
+            OpCode::Closure,
+            OpCode::DefineGlobal,
+            OpCode::GetGlobal,
+            OpCode::DefineGlobal,
+            OpCode::Nil,
+            OpCode::Return,
+        ]
+    );
+
+    let (last, _) = chunk.instructions().last().unwrap();
+    assert_eq!(last, chunk.code_len() - 1);
+    assert_eq!(chunk.constant(0), Some(&Value::from("a")));
+    assert_eq!(chunk.constant(chunk.constants_len()), None);
+}
+
+#[test]
+fn instructions_skip_closure_upvalues() {
+    let (err, _) = Sink::buffer();
+
+    let source = "fun f() { var x = 1; fun g() { return x; } return g; }";
+    let script = compile(source, &err, &mut Strings::new()).unwrap();
+    let Some(Value::Function(f)) = script
+        .chunk
+        .constants
+        .iter()
+        .find(|c| matches!(c, Value::Function(_)))
+    else {
+        panic!("f is a constant of the script");
+    };
+
+    let ops = f.chunk.instructions().map(|(_, op)| op).collect::<Vec<_>>();
+    assert_eq!(
+        ops,
+        [
+            OpCode::Constant,
+            OpCode::Closure,
+            OpCode::GetLocal,
+            OpCode::Return,
+            OpCode::Nil,
+            OpCode::Return,
+        ]
+    );
+}
@@ -0,0 +1,104 @@
+use bytecode::scanner::{Scanner, TokenType};
+
+#[test]
+fn tokens_know_their_span() {
+    let mut scanner = Scanner::new("a >= 1");
+    scanner.scan_token();
+
+    let token = scanner.scan_token();
+    assert_eq!(token.kind, TokenType::GreaterEqual);
+    assert_eq!((token.start, token.len), (2, 2));
+    assert_eq!(token.to_string(), "GreaterEqual >= nil");
+}
+
+#[test]
+fn literals_format_with_their_value() {
+    let mut scanner = Scanner::new("\"hi\" 2.50");
+
+    let string = scanner.scan_token();
+    assert_eq!(string.to_string(), "String \"hi\" hi");
+    assert_eq!((string.start, string.len), (0, 4));
+
+    let number = scanner.scan_token();
+    assert_eq!(number.to_string(), "Number 2.50 2.5");
+    assert_eq!((number.start, number.len), (5, 4));
+}
+
+#[test]
+fn tokens_know_their_column() {
+    let mut scanner = Scanner::new("var x = 1;\n  print \"é\" + x;");
+
+    let mut columns = vec![];
+    loop {
+        let token = scanner.scan_token();
+        if token.kind == TokenType::Eof {
+            break;
+        }
+        columns.push((token.lexeme, token.line, token.column));
+    }
+
+    assert_eq!(
+        columns,
+        [
+            ("var", 1, 1),
+            ("x", 1, 5),
+            ("=", 1, 7),
+            ("1", 1, 9),
+            (";", 1, 10),
+            ("print", 2, 3),
+            ("\"é\"", 2, 9),
+            ("+", 2, 13),
+            ("x", 2, 15),
+            (";", 2, 16),
+        ]
+    );
+}
+
+#[test]
+fn blank_sources_scan_to_eof() {
+    for source in ["", " \n\t\r\n", "// just a comment", "// a comment\n"] {
+        let mut scanner = Scanner::new(source);
+
+        assert_eq!(scanner.scan_token().kind, TokenType::Eof, "{source:?}");
+        assert_eq!(scanner.scan_token().kind, TokenType::Eof, "{source:?}");
+    }
+}
+
+#[test]
+fn a_trailing_dot_is_not_part_of_the_number() {
+    let mut scanner = Scanner::new("1.");
+
+    assert_eq!(scanner.scan_token().kind, TokenType::Number);
+    assert_eq!(scanner.scan_token().kind, TokenType::Dot);
+    assert_eq!(scanner.scan_token().kind, TokenType::Eof);
+}
+
+#[test]
+fn tokens_ending_the_source_terminate() {
+    for source in ["a", "12", "1.5", "\"s\"", "!=", "<", "/", "\"open"] {
+        let mut scanner = Scanner::new(source);
+        scanner.scan_token();
+
+        assert_eq!(scanner.scan_token().kind, TokenType::Eof, "{source:?}");
+    }
+}
+
+#[test]
+fn non_ascii_outside_strings_is_an_error() {
+    let mut scanner = Scanner::new("print 1; é print 2; ☃");
+
+    let mut errors = vec![];
+    loop {
+        let token = scanner.scan_token();
+        match token.kind {
+            TokenType::Eof => break,
+            TokenType::Error => errors.push((token.lexeme, token.column)),
+            _ => (),
+        }
+    }
+
+    assert_eq!(
+        errors,
+        [("Unexpected character.", 10), ("Unexpected character.", 21)]
+    );
+}
@@ -0,0 +1,115 @@
+use std::rc::Rc;
+
+use bytecode::table::{Strings, Table};
+
+fn key(i: usize) -> Rc<str> {
+    format!("key{i}").into()
+}
+
+#[test]
+fn insert_get_and_remove() {
+    let mut table = Table::new();
+    assert_eq!(table.get("a"), None);
+
+    assert!(table.insert("a".into(), 1));
+    assert!(table.insert("b".into(), 2));
+    assert_eq!(table.get("a"), Some(&1));
+    assert_eq!(table.get("b"), Some(&2));
+
+    // Overwriting isn't a new key.
+    assert!(!table.insert("a".into(), 3));
+    assert_eq!(table.get("a"), Some(&3));
+    assert_eq!(table.len(), 2);
+
+    assert!(table.remove("a"));
+    assert!(!table.remove("a"));
+    assert_eq!(table.get("a"), None);
+    assert_eq!(table.get("b"), Some(&2));
+    assert_eq!(table.len(), 1);
+}
+
+#[test]
+fn grows_past_three_quarters_full() {
+    let mut table = Table::new();
+    assert_eq!(table.capacity(), 0);
+
+    for i in 0..6 {
+        table.insert(key(i), i);
+    }
+    assert_eq!(table.capacity(), 8);
+
+    table.insert(key(6), 6);
+    assert_eq!(table.capacity(), 16);
+
+    for i in 0..100 {
+        table.insert(key(i), i);
+    }
+    assert_eq!(table.len(), 100);
+    assert!(table.len() as f64 <= table.capacity() as f64 * 0.75);
+    for i in 0..100 {
+        assert_eq!(table.get(&key(i)), Some(&i));
+    }
+}
+
+#[test]
+fn lookups_probe_past_tombstones() {
+    let mut table = Table::new();
+    for i in 0..100 {
+        table.insert(key(i), i);
+    }
+
+    for i in (0..100).step_by(2) {
+        assert!(table.remove(&key(i)));
+    }
+
+    for i in 0..100 {
+        let expected = (i % 2 == 1).then_some(&i);
+        assert_eq!(table.get(&key(i)), expected, "key{i}");
+    }
+}
+
+#[test]
+fn inserts_reuse_tombstones() {
+    let mut table = Table::new();
+    for i in 0..5 {
+        table.insert(key(i), i);
+    }
+    assert_eq!(table.capacity(), 8);
+
+    // Each removal leaves a tombstone that the reinsertion takes back, so
+    // the table never fills up enough to grow.
+    for _ in 0..10 {
+        for i in 0..5 {
+            table.remove(&key(i));
+            table.insert(key(i), i);
+        }
+    }
+
+    assert_eq!(table.capacity(), 8);
+    assert_eq!(table.len(), 5);
+}
+
+#[test]
+fn interned_strings_are_shared() {
+    let mut strings = Strings::new();
+
+    let a = strings.intern("hello");
+    let b = strings.intern("hello");
+    assert!(Rc::ptr_eq(&a, &b));
+    assert_eq!(strings.len(), 1);
+}
+
+#[test]
+fn intern_set_drops_unused_strings() {
+    let mut strings = Strings::new();
+
+    let kept = strings.intern("kept");
+    for i in 0..100 {
+        strings.intern(&format!("garbage{i}"));
+    }
+
+    // Strings no one else holds are swept whenever the set fills up, so
+    // only the last few garbage strings remain.
+    assert!(strings.len() < 10, "{} strings interned", strings.len());
+    assert!(Rc::ptr_eq(&kept, &strings.intern("kept")));
+}
@@ -0,0 +1,33 @@
+use bytecode::value::Value;
+
+#[test]
+fn strings_print_bare_but_debug_quoted() {
+    let value = Value::from("hi");
+
+    assert_eq!(value.to_string(), "hi");
+    assert_eq!(format!("{value:?}"), "\"hi\"");
+}
+
+#[test]
+fn debug_escapes_what_display_writes_raw() {
+    let value = Value::from("one\ntwo\t\\");
+
+    assert_eq!(value.to_string(), "one\ntwo\t\\");
+    assert_eq!(format!("{value:?}"), r#""one\ntwo\t\\""#);
+}
+
+#[test]
+fn a_string_nil_is_told_apart_from_nil() {
+    assert_eq!(Value::Nil.to_string(), Value::from("nil").to_string());
+    assert_ne!(
+        format!("{:?}", Value::Nil),
+        format!("{:?}", Value::from("nil"))
+    );
+}
+
+#[test]
+fn other_values_look_the_same_either_way() {
+    for value in [Value::Nil, Value::from(true), Value::from(2.5)] {
+        assert_eq!(format!("{value:?}"), value.to_string());
+    }
+}
@@ -0,0 +1,96 @@
+//! The command line shared by both binaries.
+
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// What to do with the script, chosen with `--emit=<mode>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Emit {
+    /// Run it, the default.
+    #[default]
+    Run,
+    /// Print each token the scanner produces.
+    Tokens,
+    /// Print the parsed syntax tree. Only treewalk has one.
+    Ast,
+    /// Print the compiled chunks. Only the bytecode VM has them.
+    Bytecode,
+}
+
+impl FromStr for Emit {
+    type Err = CliError;
+
+    fn from_str(mode: &str) -> Result<Self, Self::Err> {
+        match mode {
+            "run" => Ok(Emit::Run),
+            "tokens" => Ok(Emit::Tokens),
+            "ast" => Ok(Emit::Ast),
+            "bytecode" => Ok(Emit::Bytecode),
+            _ => Err(CliError(format!("Unknown --emit mode '{mode}'."))),
+        }
+    }
+}
+
+impl Display for Emit {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let mode = match self {
+            Emit::Run => "run",
+            Emit::Tokens => "tokens",
+            Emit::Ast => "ast",
+            Emit::Bytecode => "bytecode",
+        };
+
+        write!(f, "{mode}")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CliError(pub String);
+
+impl Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// A parsed command line. Flags come before the script; everything after it
+/// belongs to the script.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Args {
+    pub emit: Emit,
+    /// Compile without running, only reporting errors.
+    pub check: bool,
+    pub help: bool,
+    pub version: bool,
+    /// Flags this parser doesn't know, for the binary to handle itself.
+    pub flags: Vec<String>,
+    pub script: Option<String>,
+    pub script_args: Vec<String>,
+}
+
+impl Args {
+    /// Parses the arguments after the program name.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Args, CliError> {
+        let mut args = args.into_iter().peekable();
+        let mut parsed = Args::default();
+
+        while let Some(flag) = args.next_if(|arg| arg.starts_with("--")) {
+            if let Some(mode) = flag.strip_prefix("--emit=") {
+                parsed.emit = mode.parse()?;
+                continue;
+            }
+
+            match flag.as_str() {
+                "--check" => parsed.check = true,
+                "--help" => parsed.help = true,
+                "--version" => parsed.version = true,
+                _ => parsed.flags.push(flag),
+            }
+        }
+
+        parsed.script = args.next();
+        parsed.script_args = args.collect();
+
+        Ok(parsed)
+    }
+}
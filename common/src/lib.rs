@@ -1 +1,3 @@
+pub mod cli;
 pub mod exit;
+pub mod sink;
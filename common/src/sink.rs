@@ -0,0 +1,40 @@
+use std::cell::RefCell;
+use std::fmt::Write;
+use std::io::Write as _;
+use std::rc::Rc;
+
+/// Where an interpreter sends program output or diagnostics.
+///
+/// Sinks are written to with `write!`/`writeln!`, which never fail: like
+/// `println!`, a broken stdout is not the program's problem.
+#[derive(Debug, Clone, Default)]
+pub enum Sink {
+    #[default]
+    Stdout,
+    Stderr,
+    /// Collects everything written, for embedders and tests to read back.
+    Buffer(Rc<RefCell<String>>),
+}
+
+impl Sink {
+    /// Returns a buffer sink along with a handle to its contents.
+    pub fn buffer() -> (Sink, Rc<RefCell<String>>) {
+        let buffer = Rc::new(RefCell::new(String::new()));
+
+        (Sink::Buffer(buffer.clone()), buffer)
+    }
+
+    pub fn write_fmt(&self, args: std::fmt::Arguments) {
+        match self {
+            Sink::Stdout => {
+                let _ = std::io::stdout().lock().write_fmt(args);
+            }
+            Sink::Stderr => {
+                let _ = std::io::stderr().lock().write_fmt(args);
+            }
+            Sink::Buffer(buffer) => {
+                let _ = buffer.borrow_mut().write_fmt(args);
+            }
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use common::cli::{Args, CliError, Emit};
+
+fn parse(args: &[&str]) -> Result<Args, CliError> {
+    Args::parse(args.iter().map(|arg| arg.to_string()))
+}
+
+#[test]
+fn emit_modes_map_from_strings() {
+    for (mode, emit) in [
+        ("run", Emit::Run),
+        ("tokens", Emit::Tokens),
+        ("ast", Emit::Ast),
+        ("bytecode", Emit::Bytecode),
+    ] {
+        assert_eq!(mode.parse(), Ok(emit));
+        assert_eq!(emit.to_string(), mode);
+    }
+}
+
+#[test]
+fn unknown_emit_modes_are_rejected() {
+    assert_eq!(
+        parse(&["--emit=assembly", "script.lox"]),
+        Err(CliError("Unknown --emit mode 'assembly'.".to_owned()))
+    );
+}
+
+#[test]
+fn defaults_to_running() {
+    let args = parse(&["script.lox"]).unwrap();
+    assert_eq!(args.emit, Emit::Run);
+    assert!(!args.check);
+    assert_eq!(args.script.as_deref(), Some("script.lox"));
+}
+
+#[test]
+fn flags_stop_at_the_script() {
+    let args = parse(&[
+        "--check",
+        "--trace",
+        "--emit=ast",
+        "script.lox",
+        "--emit=bogus",
+    ])
+    .unwrap();
+    assert_eq!(args.emit, Emit::Ast);
+    assert!(args.check);
+    assert_eq!(args.flags, ["--trace"]);
+    assert_eq!(args.script_args, ["--emit=bogus"]);
+}
@@ -0,0 +1,45 @@
+//! Runs the same Lox scripts through both backends so they can be checked
+//! against each other.
+//!
+//! Scripts state their expected output with `// expect: <line>` comments, the
+//! convention used by the Crafting Interpreters test suite.
+
+use bytecode::vm::Vm;
+use common::sink::Sink;
+use treewalk::lox::Lox;
+
+const EXPECT: &str = "// expect: ";
+
+/// The lines `source` should print, in order.
+pub fn expected_output(source: &str) -> Vec<String> {
+    source
+        .lines()
+        .filter_map(|line| line.split_once(EXPECT))
+        .map(|(_, expected)| expected.to_owned())
+        .collect()
+}
+
+/// Runs `source` with the tree-walking interpreter, returning what it
+/// printed.
+pub fn run_treewalk(source: &str) -> Vec<String> {
+    let (out, printed) = Sink::buffer();
+    let (err, _) = Sink::buffer();
+
+    let mut lox = Lox::new();
+    lox.set_output(out, err);
+    lox.run(source);
+
+    printed.borrow().lines().map(str::to_owned).collect()
+}
+
+/// Runs `source` with the bytecode VM, returning what it printed.
+pub fn run_bytecode(source: &str) -> Vec<String> {
+    let (out, printed) = Sink::buffer();
+    let (err, _) = Sink::buffer();
+
+    let mut vm = Vm::new();
+    vm.set_output(out, err);
+    let _ = vm.interpret(source);
+
+    printed.borrow().lines().map(str::to_owned).collect()
+}
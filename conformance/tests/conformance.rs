@@ -0,0 +1,44 @@
+use std::fs::{read_dir, read_to_string};
+use std::path::PathBuf;
+
+use conformance::{expected_output, run_bytecode, run_treewalk};
+
+fn scripts() -> Vec<PathBuf> {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/scripts");
+
+    let mut scripts: Vec<_> = read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "lox"))
+        .collect();
+    scripts.sort();
+
+    scripts
+}
+
+#[test]
+fn backends_match_expectations() {
+    let scripts = scripts();
+    assert!(!scripts.is_empty(), "no conformance scripts found");
+
+    let mut failures = vec![];
+
+    for path in scripts {
+        let source = read_to_string(&path).unwrap();
+        let expected = expected_output(&source);
+        let name = path.file_name().unwrap().to_string_lossy();
+
+        for (backend, actual) in [
+            ("treewalk", run_treewalk(&source)),
+            ("bytecode", run_bytecode(&source)),
+        ] {
+            if actual != expected {
+                failures.push(format!(
+                    "{name} ({backend}):\n  expected {expected:?}\n  got      {actual:?}"
+                ));
+            }
+        }
+    }
+
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
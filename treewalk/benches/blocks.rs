@@ -0,0 +1,47 @@
+//! Compares a loop whose body block declares nothing, which runs in the
+//! enclosing environment, with one whose block declares a variable and so
+//! needs a scope of its own each time round. Run with
+//! `cargo bench -p treewalk`.
+
+#![feature(test)]
+
+extern crate test;
+
+use common::sink::Sink;
+use test::Bencher;
+use treewalk::lox::Lox;
+
+const BARE: &str = r#"
+var i = 0;
+var total = 0;
+while (i < 10000) {
+  total = total + i;
+  i = i + 1;
+}
+"#;
+
+const DECLARING: &str = r#"
+var i = 0;
+var total = 0;
+while (i < 10000) {
+  var next = i + 1;
+  total = total + i;
+  i = next;
+}
+"#;
+
+fn run(source: &str) {
+    let mut lox = Lox::new();
+    lox.set_output(Sink::buffer().0, Sink::buffer().0);
+    lox.run(source);
+}
+
+#[bench]
+fn bare_block(b: &mut Bencher) {
+    b.iter(|| run(BARE));
+}
+
+#[bench]
+fn declaring_block(b: &mut Bencher) {
+    b.iter(|| run(DECLARING));
+}
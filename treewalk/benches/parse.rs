@@ -0,0 +1,45 @@
+//! Scans and parses a large program, which creates an AST node, and so an
+//! `Id`, for every expression in it. Run with `cargo bench -p treewalk`.
+
+#![feature(test)]
+
+extern crate test;
+
+use test::Bencher;
+use treewalk::parse_program;
+
+const UNIT: &str = r#"
+class Point {
+  init(x, y) {
+    this.x = x;
+    this.y = y;
+  }
+
+  add(other) {
+    return Point(this.x + other.x, this.y + other.y);
+  }
+}
+
+fun sum(n) {
+  var total = 0;
+  for (var i = 0; i < n; i = i + 1) {
+    if (i / 2 == 0 or i > 10) total = total + i * 2;
+  }
+  return total;
+}
+
+var p = Point(1, 2).add(Point(3, 4));
+print sum(p.x) + -p.y;
+"#;
+
+#[bench]
+fn large_file(b: &mut Bencher) {
+    let source = UNIT.repeat(1000);
+
+    b.iter(|| {
+        let (statements, diagnostics) = parse_program(&source);
+        assert!(diagnostics.is_empty());
+
+        statements
+    });
+}
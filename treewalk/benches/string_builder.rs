@@ -0,0 +1,41 @@
+//! Compares building a 10,000-piece string with `+` against a string
+//! builder. Run with `cargo bench -p treewalk`.
+
+#![feature(test)]
+
+extern crate test;
+
+use common::sink::Sink;
+use test::Bencher;
+use treewalk::lox::Lox;
+
+const CONCATENATION: &str = r#"
+var s = "";
+for (var i = 0; i < 10000; i = i + 1) {
+  s = s + "x";
+}
+"#;
+
+const BUILDER: &str = r#"
+var sb = sb_new();
+for (var i = 0; i < 10000; i = i + 1) {
+  sb_append(sb, "x");
+}
+var s = sb_build(sb);
+"#;
+
+fn run(source: &str) {
+    let mut lox = Lox::new();
+    lox.set_output(Sink::buffer().0, Sink::buffer().0);
+    lox.run(source);
+}
+
+#[bench]
+fn concatenation(b: &mut Bencher) {
+    b.iter(|| run(CONCATENATION));
+}
+
+#[bench]
+fn builder(b: &mut Bencher) {
+    b.iter(|| run(BUILDER));
+}
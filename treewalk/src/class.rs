@@ -0,0 +1,137 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::rc::Rc;
+
+use crate::error::Exception;
+use crate::function::{Function, LoxFunction};
+use crate::interpreter::Interpreter;
+use crate::object::Object;
+use crate::token::Token;
+
+pub struct LoxClass {
+    pub(crate) name: String,
+    superclass: Option<Rc<LoxClass>>,
+    methods: HashMap<String, Rc<LoxFunction>>,
+}
+
+impl LoxClass {
+    pub fn new(
+        name: &str,
+        superclass: Option<Rc<LoxClass>>,
+        methods: HashMap<String, Rc<LoxFunction>>,
+    ) -> Self {
+        let name = name.to_owned();
+
+        LoxClass {
+            name,
+            superclass,
+            methods,
+        }
+    }
+
+    /// Looks `name` up on this class, then on its superclasses.
+    pub fn find_method(&self, name: &str) -> Option<Rc<LoxFunction>> {
+        self.methods.get(name).cloned().or_else(|| {
+            self.superclass
+                .as_ref()
+                .and_then(|superclass| superclass.find_method(name))
+        })
+    }
+
+    pub fn arity(&self) -> usize {
+        self.find_method("init")
+            .map_or(0, |initializer| initializer.arity())
+    }
+
+    pub fn call(
+        class: &Rc<LoxClass>,
+        interpreter: &mut Interpreter,
+        arguments: &[Object],
+    ) -> Result<Object, Exception> {
+        let instance = Rc::new(RefCell::new(LoxInstance::new(class.clone())));
+
+        if let Some(initializer) = class.find_method("init") {
+            let initializer = initializer.bind(instance.clone());
+            Function::Lox(Rc::new(initializer)).call(interpreter, arguments)?;
+        } else if !arguments.is_empty() {
+            return Err(Exception::native(format!(
+                "Expected 0 arguments but got {}.",
+                arguments.len()
+            )));
+        }
+
+        Ok(Object::Instance(instance))
+    }
+}
+
+impl Debug for LoxClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LoxClass")
+            .field("name", &self.name)
+            .field_with("methods", |f| {
+                f.debug_list().entries(self.methods.keys()).finish()
+            })
+            .finish()
+    }
+}
+
+pub struct LoxInstance {
+    pub(crate) class: Rc<LoxClass>,
+    fields: HashMap<String, Object>,
+}
+
+impl LoxInstance {
+    pub fn new(class: Rc<LoxClass>) -> Self {
+        LoxInstance {
+            class,
+            fields: HashMap::new(),
+        }
+    }
+
+    pub fn has_field(&self, name: &str) -> bool {
+        self.fields.contains_key(name)
+    }
+
+    pub fn get(this: &Rc<RefCell<LoxInstance>>, name: &Token) -> Result<Object, Exception> {
+        if let Some(value) = this.borrow().fields.get(&name.lexeme) {
+            return Ok(value.clone());
+        }
+
+        if let Some(method) = this.borrow().class.find_method(&name.lexeme) {
+            return Ok(Object::from(method.bind(this.clone())));
+        }
+
+        Err(Exception::new(
+            name.clone(),
+            format!("Undefined property '{}'.", name.lexeme),
+        ))
+    }
+
+    /// Calls the method `name` on the instance, or returns `None` if its class
+    /// doesn't define one. Used for the special methods behind operators.
+    pub fn invoke(
+        this: &Rc<RefCell<LoxInstance>>,
+        interpreter: &mut Interpreter,
+        name: &str,
+        arguments: &[Object],
+    ) -> Option<Result<Object, Exception>> {
+        let method = this.borrow().class.find_method(name)?;
+        let method = Function::Lox(Rc::new(method.bind(this.clone())));
+
+        Some(method.call(interpreter, arguments))
+    }
+
+    pub fn set(&mut self, name: &Token, value: &Object) {
+        self.fields.insert(name.lexeme.to_owned(), value.clone());
+    }
+}
+
+impl Debug for LoxInstance {
+    // Fields may refer back to the instance, so only the class is shown.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LoxInstance")
+            .field("class", &self.class.name)
+            .finish_non_exhaustive()
+    }
+}
@@ -1,10 +1,10 @@
-use std::cell::RefCell;
-use std::collections::HashMap;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 
-use uuid::Uuid;
-
 use crate::error::Exception;
+use crate::function::Function;
+use crate::id::Id;
 use crate::object::Object;
 use crate::token::Token;
 
@@ -25,23 +25,39 @@ pub trait EnvLookup {
     }
 }
 
+/// Counts of the environments alive in one tree of scopes, for `gc_stats()`.
+/// Every environment enclosed by another shares its counts, so each
+/// interpreter's are kept apart from those of any other on the same thread.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EnvironmentStats {
+    pub live: usize,
+    /// Variables defined across all of them.
+    pub variables: usize,
+}
+
 #[derive(Debug)]
 pub struct Environment {
-    id: Uuid,
+    id: Id,
     enclosing: Option<Rc<RefCell<Environment>>>,
     values: HashMap<String, Object>,
+    stats: Rc<Cell<EnvironmentStats>>,
 }
 
 impl Environment {
     pub(crate) fn new_raw() -> Self {
-        let id = Uuid::new_v4();
+        let id = Id::fresh();
         let values = HashMap::new();
+        let stats = Rc::new(Cell::new(EnvironmentStats::default()));
 
-        Environment {
+        let environment = Environment {
             id,
             enclosing: None,
             values,
-        }
+            stats,
+        };
+        environment.update_stats(|stats| stats.live += 1);
+
+        environment
     }
 
     pub(crate) fn finish(self) -> Rc<RefCell<Self>> {
@@ -53,19 +69,38 @@ impl Environment {
     }
 
     pub fn new_enclosed(enclosing: Rc<RefCell<Environment>>) -> Rc<RefCell<Self>> {
-        let id = Uuid::new_v4();
+        let id = Id::fresh();
+        let stats = enclosing.borrow().stats.clone();
         let enclosing = Some(enclosing);
         let values = HashMap::new();
 
-        Rc::new(RefCell::new(Environment {
+        let environment = Environment {
             id,
             enclosing,
             values,
-        }))
+            stats,
+        };
+        environment.update_stats(|stats| stats.live += 1);
+
+        Rc::new(RefCell::new(environment))
     }
 
     pub fn define(&mut self, name: &str, value: &Object) {
-        self.values.insert(name.to_owned(), value.clone());
+        match self.values.insert(name.to_owned(), value.clone()) {
+            Some(old) => release(old),
+            None => self.update_stats(|stats| stats.variables += 1),
+        }
+    }
+
+    /// The counts shared by this environment and every other in its tree.
+    pub fn stats(&self) -> EnvironmentStats {
+        self.stats.get()
+    }
+
+    fn update_stats(&self, f: impl FnOnce(&mut EnvironmentStats)) {
+        let mut stats = self.stats.get();
+        f(&mut stats);
+        self.stats.set(stats);
     }
 
     pub fn ancestor(
@@ -97,11 +132,94 @@ impl Environment {
         name: &Token,
         value: &Object,
     ) {
-        Self::ancestor(this, distance)
+        let old = Self::ancestor(this, distance)
             .unwrap()
             .borrow_mut()
             .values
             .insert(name.lexeme.to_owned(), value.clone());
+
+        if let Some(old) = old {
+            release(old);
+        }
+    }
+
+    /// Breaks the reference cycle a scope forms with the functions declared in
+    /// it, if nothing else can reach the scope. Returns whether it did.
+    ///
+    /// A function keeps the scope it was declared in alive, and that scope
+    /// holds the function, so `Rc` alone never frees either. Making the link
+    /// weak isn't an option, since a closure returned from its scope must keep
+    /// that scope alive. Instead, whoever is done with a scope calls this with
+    /// the last handle it holds: if every other strong reference comes from a
+    /// function stored only in the scope itself, the scope is garbage, and
+    /// emptying it lets both be freed.
+    pub fn clear_cycle(this: &Rc<RefCell<Environment>>) -> bool {
+        let own_references = this
+            .borrow()
+            .values
+            .values()
+            .filter(|value| match value {
+                Object::Fn(Function::Lox(function)) => {
+                    Rc::strong_count(function) == 1 && function.captures(this)
+                }
+                _ => false,
+            })
+            .count();
+
+        if own_references == 0 || Rc::strong_count(this) != own_references + 1 {
+            return false;
+        }
+
+        // Drop the values only once the borrow is released.
+        let values = std::mem::take(&mut this.borrow_mut().values);
+        this.borrow()
+            .update_stats(|stats| stats.variables -= values.len());
+        drop(values);
+
+        true
+    }
+
+    /// The bindings in this frame, ignoring enclosing ones, in no particular
+    /// order.
+    pub fn bindings(&self) -> impl Iterator<Item = (&str, &Object)> {
+        self.values
+            .iter()
+            .map(|(name, value)| (name.as_str(), value))
+    }
+
+    /// Every binding visible from this frame, innermost frame first, leaving
+    /// out those shadowed by an inner one. Owned, since each enclosing frame
+    /// is only borrowed while it's read.
+    pub fn bindings_recursive(&self) -> Vec<(String, Object)> {
+        let mut seen = HashSet::new();
+        let mut bindings = vec![];
+
+        let mut collect = |environment: &Environment| {
+            for (name, value) in environment.bindings() {
+                if seen.insert(name.to_owned()) {
+                    bindings.push((name.to_owned(), value.clone()));
+                }
+            }
+        };
+
+        collect(self);
+        let mut enclosing = self.enclosing.clone();
+        while let Some(environment) = enclosing {
+            collect(&environment.borrow());
+            enclosing = environment.borrow().enclosing.clone();
+        }
+
+        bindings
+    }
+
+    /// Whether `name` is defined in this frame, ignoring enclosing ones.
+    pub fn contains(&self, name: &str) -> bool {
+        self.values.contains_key(name)
+    }
+
+    /// Looks `name` up in this frame only, ignoring enclosing ones.
+    pub fn get_own(&self, name: &str) -> Option<Object> {
+        self.values.get(name).cloned()
     }
 
     pub fn get(&self, name: &Token) -> Result<Object, Exception> {
@@ -121,7 +239,8 @@ impl Environment {
 
     pub fn assign(&mut self, name: &Token, value: &Object) -> Result<(), Exception> {
         if self.values.contains_key(&name.lexeme) {
-            self.values.insert(name.lexeme.to_owned(), value.clone());
+            let old = self.values.insert(name.lexeme.to_owned(), value.clone());
+            release(old.unwrap());
 
             return Ok(());
         }
@@ -139,6 +258,38 @@ impl Environment {
     }
 }
 
+/// Drops a value that was just overwritten or went out of scope. If it was
+/// the last handle to a function from outside the function's own scope,
+/// that scope is now only reachable through the cycle described at
+/// [`Environment::clear_cycle`], so it's cleared.
+///
+/// A scope that's borrowed is in use, and so still reachable, and is left
+/// alone.
+fn release(value: Object) {
+    let Object::Fn(Function::Lox(function)) = value else {
+        return;
+    };
+
+    let closure = function.closure().clone();
+    drop(function);
+
+    if closure.try_borrow_mut().is_ok() {
+        Environment::clear_cycle(&closure);
+    }
+}
+
+impl Drop for Environment {
+    fn drop(&mut self) {
+        let values = std::mem::take(&mut self.values);
+        self.update_stats(|stats| {
+            stats.live -= 1;
+            stats.variables -= values.len();
+        });
+
+        values.into_values().for_each(release);
+    }
+}
+
 impl PartialEq for Environment {
     fn eq(&self, other: &Self) -> bool {
         self.id == other.id
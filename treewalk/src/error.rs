@@ -1,42 +1,210 @@
 use std::fmt::Display;
 
 use crate::object::Object;
-use crate::token::Token;
+use crate::token::{Token, TokenType};
 
-pub struct ParseError;
+#[derive(Debug, Clone)]
+pub struct ParseError {
+    pub token: Token,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScanError {
+    pub span: Span,
+    pub message: String,
+}
+
+impl ScanError {
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            span: self.span,
+            location: String::new(),
+            message: self.message.clone(),
+            code: "scan",
+        }
+    }
+}
+
+impl ParseError {
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        Diagnostic::at(&self.token, Severity::Error, "parse", &self.message)
+    }
+
+    /// For problems the parser recovered from, like an inserted `;`.
+    pub fn to_warning(&self) -> Diagnostic {
+        Diagnostic::at(&self.token, Severity::Warning, "parse", &self.message)
+    }
+}
+
+/// A stretch of source code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub line: usize,
+    /// The byte offset where the span starts.
+    pub start: usize,
+    /// Its length in bytes. Zero for spans that only know their line.
+    pub len: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// Stops the program from running.
+    Error,
+    Warning,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "Error"),
+            Severity::Warning => write!(f, "Warning"),
+        }
+    }
+}
+
+/// A problem found before the program runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub span: Span,
+    /// Where on the line the error was found, e.g. `" at 'x'"`. May be empty.
+    pub location: String,
+    pub message: String,
+    /// The stage that found the problem: `"scan"`, `"parse"` or `"resolve"`.
+    pub code: &'static str,
+}
+
+impl Diagnostic {
+    /// A diagnostic pointing at `token`.
+    pub fn at(token: &Token, severity: Severity, code: &'static str, message: &str) -> Self {
+        let location = if token.kind == TokenType::Eof {
+            " at end".to_owned()
+        } else {
+            format!(" at '{}'", token.lexeme)
+        };
+
+        Diagnostic {
+            severity,
+            span: token.span(),
+            location,
+            message: message.to_owned(),
+            code,
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "[line {}] {}{}: {}",
+            self.span.line, self.severity, self.location, self.message
+        )
+    }
+}
+
+/// A Lox value that can't be turned into the Rust type asked for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConversionError {
+    /// What was wanted, with its article, e.g. `"a number"`.
+    pub expected: &'static str,
+    pub found: Object,
+}
+
+impl Display for ConversionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "Expected {} but got {}.",
+            self.expected,
+            self.found.repr()
+        )
+    }
+}
+
+impl std::error::Error for ConversionError {}
+
+impl From<ConversionError> for Exception {
+    fn from(error: ConversionError) -> Self {
+        Exception::native(error.to_string())
+    }
+}
 
 pub enum Exception {
-    Error { token: Token, message: String },
+    Error {
+        line: usize,
+        message: String,
+    },
+    /// Raised by native functions, which have no token of their own. The
+    /// interpreter anchors it to the call site before it escapes.
+    Native(String),
+    /// Raised by `panic`. It halts the program outright, skipping the
+    /// cleanup that `with` would otherwise run.
+    Panic(Box<Exception>),
     Return(Object),
+    /// Raised by `continue`, and caught by the loop it's in.
+    Continue,
 }
 
 impl Exception {
     pub fn new(token: Token, message: impl Into<String>) -> Self {
         let message = message.into();
 
-        Exception::Error { token, message }
+        Exception::Error {
+            line: token.line,
+            message,
+        }
+    }
+
+    pub fn native(message: impl Into<String>) -> Self {
+        Exception::Native(message.into())
+    }
+
+    /// Anchors a native error to `token`, leaving other exceptions untouched.
+    pub fn at(self, token: &Token) -> Self {
+        self.at_line(token.line)
+    }
+
+    /// Anchors a native error to `line`, for when there's no better token.
+    pub fn at_line(self, line: usize) -> Self {
+        match self {
+            Exception::Native(message) => Exception::Error { line, message },
+            Exception::Panic(err) => Exception::Panic(Box::new(err.at_line(line))),
+            x => x,
+        }
     }
 
     pub fn num(token: Token) -> Self {
         Exception::new(token, "Operand must be a number.")
     }
 
-    pub fn num_pair(token: Token) -> Self {
-        Exception::new(token, "Operands must be numbers.")
+    pub fn nums_or_strings(token: Token, lhs: &Object, rhs: &Object) -> Self {
+        let message = format!(
+            "Operands must be two numbers or two strings, got {} and {}.",
+            lhs.type_name(),
+            rhs.type_name()
+        );
+
+        Exception::new(token, message)
     }
 
-    pub fn nums_or_strings(token: Token) -> Self {
-        Exception::new(token, "Operands must be two numbers or two strings.")
+    /// Raised when a method taken from its class, rather than from an
+    /// instance, needs `this`.
+    pub fn unbound_this() -> Self {
+        Exception::native("Can't use 'this' in a method called without an instance.")
     }
 }
 
 impl Display for Exception {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
-            Exception::Error { token, message } => {
-                write!(f, "{}\n[line {}]", message, token.line)
-            }
+            Exception::Error { line, message } => write!(f, "{message}\n[line {line}]"),
+            Exception::Native(message) => write!(f, "{message}"),
+            Exception::Panic(err) => write!(f, "{err}"),
             Exception::Return(x) => write!(f, "return {x};"),
+            Exception::Continue => write!(f, "continue;"),
         }
     }
 }
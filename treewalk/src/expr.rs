@@ -2,11 +2,28 @@ use std::fmt::Debug;
 use std::hash::Hash;
 use std::ops::{Deref, DerefMut};
 
-use uuid::Uuid;
-
+use crate::id::Id;
 use crate::object::Object;
+use crate::stmt::Stmt;
 use crate::token::Token;
 
+/// What a `match` arm compares its scrutinee against.
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    /// Matches a value equal to the literal.
+    Literal(Object),
+    /// `_`, which matches anything.
+    Wildcard,
+    /// A name, which matches anything and binds it for the arm's body.
+    Binding(Token),
+}
+
+#[derive(Debug, Clone)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub body: Expr,
+}
+
 #[derive(Debug, Clone)]
 pub enum ExprData {
     Assign {
@@ -18,14 +35,45 @@ pub enum ExprData {
         lhs: SubExpr,
         rhs: SubExpr,
     },
+    /// `(a, b) = value`, assigning each element of a list to a variable.
+    Destructure {
+        equals: Token,
+        /// Always `ExprData::Variable`s.
+        targets: Vec<Expr>,
+        value: SubExpr,
+    },
     Call {
         callee: SubExpr,
         paren: Token,
+        /// Positional arguments, which always come first.
         arguments: Vec<Expr>,
+        /// `name: value` arguments, matched to parameters by name.
+        keywords: Vec<(Token, Expr)>,
+    },
+    Get {
+        object: SubExpr,
+        name: Token,
     },
     Grouping {
         expr: SubExpr,
     },
+    Index {
+        object: SubExpr,
+        bracket: Token,
+        index: SubExpr,
+    },
+    /// An arrow function, `(a, b) => body`.
+    Lambda {
+        arrow: Token,
+        parameters: Vec<Token>,
+        /// A single `Stmt::Return` of the body expression.
+        body: Vec<Stmt>,
+    },
+    List {
+        elements: Vec<Expr>,
+    },
+    /// `and` and `or`, or a logical assignment such as `x ??= y`, whose
+    /// `rhs` is then the assignment.
     Logical {
         op: Token,
         lhs: SubExpr,
@@ -34,6 +82,35 @@ pub enum ExprData {
     Literal {
         value: Object,
     },
+    Map {
+        brace: Token,
+        entries: Vec<(Expr, Expr)>,
+    },
+    /// `match scrutinee { pattern => body, ... }`, which evaluates to the
+    /// body of the first arm whose pattern matches, or `nil` if none does.
+    Match {
+        keyword: Token,
+        scrutinee: SubExpr,
+        arms: Vec<MatchArm>,
+    },
+    Set {
+        object: SubExpr,
+        name: Token,
+        value: SubExpr,
+    },
+    SetIndex {
+        object: SubExpr,
+        bracket: Token,
+        index: SubExpr,
+        value: SubExpr,
+    },
+    Super {
+        keyword: Token,
+        method: Token,
+    },
+    This {
+        keyword: Token,
+    },
     Unary {
         op: Token,
         rhs: SubExpr,
@@ -43,20 +120,97 @@ pub enum ExprData {
     },
 }
 
+/// A pass over expressions, with one method per `ExprData` variant.
+///
+/// Variants that the resolver binds to a scope (assignments, `super`, `this`
+/// and variables) also receive the whole expression, since its identity is what
+/// gets resolved.
+pub trait ExprVisitor<T> {
+    fn visit_assign(&mut self, expr: &Expr, name: &Token, value: &Expr) -> T;
+    fn visit_binary(&mut self, op: &Token, lhs: &Expr, rhs: &Expr) -> T;
+    fn visit_call(
+        &mut self,
+        callee: &Expr,
+        paren: &Token,
+        arguments: &[Expr],
+        keywords: &[(Token, Expr)],
+    ) -> T;
+    fn visit_destructure(&mut self, equals: &Token, targets: &[Expr], value: &Expr) -> T;
+    fn visit_get(&mut self, object: &Expr, name: &Token) -> T;
+    fn visit_grouping(&mut self, expr: &Expr) -> T;
+    fn visit_index(&mut self, object: &Expr, bracket: &Token, index: &Expr) -> T;
+    fn visit_lambda(&mut self, arrow: &Token, parameters: &[Token], body: &[Stmt]) -> T;
+    fn visit_list(&mut self, elements: &[Expr]) -> T;
+    fn visit_logical(&mut self, op: &Token, lhs: &Expr, rhs: &Expr) -> T;
+    fn visit_literal(&mut self, value: &Object) -> T;
+    fn visit_map(&mut self, brace: &Token, entries: &[(Expr, Expr)]) -> T;
+    fn visit_match(&mut self, keyword: &Token, scrutinee: &Expr, arms: &[MatchArm]) -> T;
+    fn visit_set(&mut self, object: &Expr, name: &Token, value: &Expr) -> T;
+    fn visit_set_index(&mut self, object: &Expr, bracket: &Token, index: &Expr, value: &Expr) -> T;
+    fn visit_super(&mut self, expr: &Expr, keyword: &Token, method: &Token) -> T;
+    fn visit_this(&mut self, expr: &Expr, keyword: &Token) -> T;
+    fn visit_unary(&mut self, op: &Token, rhs: &Expr) -> T;
+    fn visit_variable(&mut self, expr: &Expr, name: &Token) -> T;
+}
+
+impl ExprData {
+    /// The line the expression starts on, as far as its tokens tell.
+    /// Literals and lists have no tokens, so their line is 0 until the
+    /// parser gives them one.
+    fn line(&self) -> usize {
+        match self {
+            ExprData::Binary { lhs, .. } | ExprData::Logical { lhs, .. } => lhs.line(),
+            ExprData::Grouping { expr } => expr.line(),
+            ExprData::Call { callee: object, .. }
+            | ExprData::Get { object, .. }
+            | ExprData::Index { object, .. }
+            | ExprData::Set { object, .. }
+            | ExprData::SetIndex { object, .. } => object.line(),
+            ExprData::Destructure { targets, .. } => targets[0].line(),
+            ExprData::List { elements } => elements.first().map_or(0, Expr::line),
+            ExprData::Literal { .. } => 0,
+            ExprData::Lambda { arrow: token, .. }
+            | ExprData::Map { brace: token, .. }
+            | ExprData::Match { keyword: token, .. }
+            | ExprData::Super { keyword: token, .. }
+            | ExprData::This { keyword: token }
+            | ExprData::Unary { op: token, .. }
+            | ExprData::Assign { name: token, .. }
+            | ExprData::Variable { name: token } => token.line,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Expr {
     pub data: ExprData,
-    id: Uuid,
+    id: Id,
+    line: usize,
 }
 
 impl Expr {
     pub(crate) fn new(data: ExprData) -> Self {
+        let line = data.line();
+
         Expr {
             data,
-            id: Uuid::new_v4(),
+            id: Id::fresh(),
+            line,
         }
     }
 
+    /// Sets the line of an expression whose tokens don't say, like a literal.
+    pub(crate) fn at_line(mut self, line: usize) -> Self {
+        self.line = line;
+        self
+    }
+
+    /// The line the expression starts on, for errors with no better token
+    /// to point at.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
     pub fn assign(name: Token, value: Expr) -> Self {
         Expr::new(ExprData::Assign {
             name,
@@ -72,11 +226,32 @@ impl Expr {
         })
     }
 
-    pub fn call(callee: Expr, paren: Token, arguments: Vec<Expr>) -> Self {
+    pub fn call(
+        callee: Expr,
+        paren: Token,
+        arguments: Vec<Expr>,
+        keywords: Vec<(Token, Expr)>,
+    ) -> Self {
         Expr::new(ExprData::Call {
             callee: callee.into(),
             paren,
             arguments,
+            keywords,
+        })
+    }
+
+    pub fn destructure(equals: Token, targets: Vec<Expr>, value: Expr) -> Self {
+        Expr::new(ExprData::Destructure {
+            equals,
+            targets,
+            value: value.into(),
+        })
+    }
+
+    pub fn get(object: Expr, name: Token) -> Self {
+        Expr::new(ExprData::Get {
+            object: object.into(),
+            name,
         })
     }
 
@@ -84,6 +259,31 @@ impl Expr {
         Expr::new(ExprData::Grouping { expr: expr.into() })
     }
 
+    pub fn index(object: Expr, bracket: Token, index: Expr) -> Self {
+        Expr::new(ExprData::Index {
+            object: object.into(),
+            bracket,
+            index: index.into(),
+        })
+    }
+
+    pub fn lambda(arrow: Token, parameters: Vec<Token>, body: Expr) -> Self {
+        let body = vec![Stmt::Return {
+            keyword: arrow.clone(),
+            expr: Some(body),
+        }];
+
+        Expr::new(ExprData::Lambda {
+            arrow,
+            parameters,
+            body,
+        })
+    }
+
+    pub fn list(elements: Vec<Expr>) -> Self {
+        Expr::new(ExprData::List { elements })
+    }
+
     pub fn logical(op: Token, lhs: Expr, rhs: Expr) -> Self {
         Expr::new(ExprData::Logical {
             op,
@@ -101,6 +301,43 @@ impl Expr {
         })
     }
 
+    pub fn map(brace: Token, entries: Vec<(Expr, Expr)>) -> Self {
+        Expr::new(ExprData::Map { brace, entries })
+    }
+
+    pub fn set_index(object: Expr, bracket: Token, index: Expr, value: Expr) -> Self {
+        Expr::new(ExprData::SetIndex {
+            object: object.into(),
+            bracket,
+            index: index.into(),
+            value: value.into(),
+        })
+    }
+
+    pub fn match_(keyword: Token, scrutinee: Expr, arms: Vec<MatchArm>) -> Self {
+        Expr::new(ExprData::Match {
+            keyword,
+            scrutinee: scrutinee.into(),
+            arms,
+        })
+    }
+
+    pub fn set(object: Expr, name: Token, value: Expr) -> Self {
+        Expr::new(ExprData::Set {
+            object: object.into(),
+            name,
+            value: value.into(),
+        })
+    }
+
+    pub fn super_(keyword: Token, method: Token) -> Self {
+        Expr::new(ExprData::Super { keyword, method })
+    }
+
+    pub fn this(keyword: Token) -> Self {
+        Expr::new(ExprData::This { keyword })
+    }
+
     pub fn unary(op: Token, rhs: Expr) -> Self {
         Expr::new(ExprData::Unary {
             op,
@@ -115,6 +352,60 @@ impl Expr {
     pub fn nil() -> Self {
         Expr::new(ExprData::Literal { value: Object::Nil })
     }
+
+    pub fn accept<T>(&self, visitor: &mut (impl ExprVisitor<T> + ?Sized)) -> T {
+        match &self.data {
+            ExprData::Assign { name, value } => visitor.visit_assign(self, name, value),
+            ExprData::Binary { op, lhs, rhs } => visitor.visit_binary(op, lhs, rhs),
+            ExprData::Call {
+                callee,
+                paren,
+                arguments,
+                keywords,
+            } => visitor.visit_call(callee, paren, arguments, keywords),
+            ExprData::Destructure {
+                equals,
+                targets,
+                value,
+            } => visitor.visit_destructure(equals, targets, value),
+            ExprData::Get { object, name } => visitor.visit_get(object, name),
+            ExprData::Grouping { expr } => visitor.visit_grouping(expr),
+            ExprData::Index {
+                object,
+                bracket,
+                index,
+            } => visitor.visit_index(object, bracket, index),
+            ExprData::Lambda {
+                arrow,
+                parameters,
+                body,
+            } => visitor.visit_lambda(arrow, parameters, body),
+            ExprData::List { elements } => visitor.visit_list(elements),
+            ExprData::Logical { op, lhs, rhs } => visitor.visit_logical(op, lhs, rhs),
+            ExprData::Literal { value } => visitor.visit_literal(value),
+            ExprData::Map { brace, entries } => visitor.visit_map(brace, entries),
+            ExprData::Match {
+                keyword,
+                scrutinee,
+                arms,
+            } => visitor.visit_match(keyword, scrutinee, arms),
+            ExprData::Set {
+                object,
+                name,
+                value,
+            } => visitor.visit_set(object, name, value),
+            ExprData::SetIndex {
+                object,
+                bracket,
+                index,
+                value,
+            } => visitor.visit_set_index(object, bracket, index, value),
+            ExprData::Super { keyword, method } => visitor.visit_super(self, keyword, method),
+            ExprData::This { keyword } => visitor.visit_this(self, keyword),
+            ExprData::Unary { op, rhs } => visitor.visit_unary(op, rhs),
+            ExprData::Variable { name } => visitor.visit_variable(self, name),
+        }
+    }
 }
 
 impl PartialEq for Expr {
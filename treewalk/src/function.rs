@@ -3,33 +3,95 @@ use std::fmt::{Debug, Display};
 use std::hash::Hash;
 use std::rc::Rc;
 
+use crate::class::LoxInstance;
 use crate::environment::Environment;
 use crate::error::Exception;
+use crate::generator::Generator;
+use crate::id::Id;
 use crate::interpreter::Interpreter;
 use crate::object::Object;
-use crate::stmt::Stmt;
+use crate::stmt::{Contract, Stmt};
 use crate::token::Token;
 
+pub type NativeCode = fn(&mut Interpreter, &[Object]) -> Result<Object, Exception>;
+
+/// Native code that captures state from the host, such as a counter or a
+/// channel.
+pub type NativeClosure = Rc<dyn Fn(&mut Interpreter, &[Object]) -> Result<Object, Exception>>;
+
+#[derive(Clone)]
+enum Code {
+    Pointer(NativeCode),
+    Closure(NativeClosure),
+}
+
 #[derive(Clone)]
 pub struct NativeFn {
-    id: Uuid,
+    id: Id,
+    name: String,
     arity: usize,
-    code: fn(&mut Interpreter, &[Object]) -> Object,
+    /// Whether `arity` is only the minimum, with any extra arguments passed
+    /// along too.
+    variadic: bool,
+    code: Code,
 }
 
 impl NativeFn {
-    pub fn new(arity: usize, code: fn(&mut Interpreter, &[Object]) -> Object) -> Self {
-        let id = Uuid::new_v4();
+    pub fn new(name: &str, arity: usize, code: NativeCode) -> Self {
+        NativeFn::with_code(name, arity, Code::Pointer(code))
+    }
+
+    /// A native taking at least `arity` arguments.
+    pub fn variadic(name: &str, arity: usize, code: NativeCode) -> Self {
+        NativeFn {
+            variadic: true,
+            ..NativeFn::new(name, arity, code)
+        }
+    }
+
+    pub fn closure(
+        name: &str,
+        arity: usize,
+        code: impl Fn(&mut Interpreter, &[Object]) -> Result<Object, Exception> + 'static,
+    ) -> Self {
+        NativeFn::with_code(name, arity, Code::Closure(Rc::new(code)))
+    }
+
+    fn with_code(name: &str, arity: usize, code: Code) -> Self {
+        let id = Id::fresh();
+        let name = name.to_owned();
+
+        NativeFn {
+            id,
+            name,
+            arity,
+            variadic: false,
+            code,
+        }
+    }
 
-        NativeFn { id, arity, code }
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        arguments: &[Object],
+    ) -> Result<Object, Exception> {
+        match &self.code {
+            Code::Pointer(code) => code(interpreter, arguments),
+            Code::Closure(code) => code(interpreter, arguments),
+        }
     }
 }
 
 impl Debug for NativeFn {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("NativeFn")
+            .field("name", &self.name)
             .field("arity", &self.arity)
-            .field_with("code", |f| write!(f, "<$NATIVE>"))
+            .field("variadic", &self.variadic)
+            .field_with("code", |f| match self.code {
+                Code::Pointer(_) => write!(f, "<$NATIVE>"),
+                Code::Closure(_) => write!(f, "<$CLOSURE>"),
+            })
             .finish()
     }
 }
@@ -50,28 +112,179 @@ impl Hash for NativeFn {
 
 #[derive(Debug, Clone)]
 pub struct LoxFunction {
-    id: Uuid,
+    id: Id,
     name: Token,
     parameters: Vec<Token>,
+    contract: Contract,
     body: Vec<Stmt>,
     closure: Rc<RefCell<Environment>>,
+    /// The globals in effect where the function was declared, which differ
+    /// from the interpreter's for functions defined inside a module.
+    globals: Rc<RefCell<Environment>>,
+    is_initializer: bool,
+    /// Whether the body yields, so calls return a `Generator`.
+    is_generator: bool,
+    /// The class of the instance this method is bound to, if any.
+    bound_to: Option<String>,
 }
 
 impl LoxFunction {
     pub fn new(
         name: Token,
         parameters: Vec<Token>,
+        contract: Contract,
         body: Vec<Stmt>,
         closure: Rc<RefCell<Environment>>,
+        globals: Rc<RefCell<Environment>>,
+        is_initializer: bool,
     ) -> Self {
+        let is_generator = body.iter().any(Stmt::yields);
+
         LoxFunction {
-            id: Uuid::new_v4(),
+            id: Id::fresh(),
             name,
             parameters,
+            contract,
             body,
             closure,
+            globals,
+            is_initializer,
+            is_generator,
+            bound_to: None,
         }
     }
+
+    pub fn arity(&self) -> usize {
+        self.parameters.len()
+    }
+
+    pub fn parameters(&self) -> &[Token] {
+        &self.parameters
+    }
+
+    pub fn name(&self) -> &Token {
+        &self.name
+    }
+
+    pub(crate) fn body(&self) -> &[Stmt] {
+        &self.body
+    }
+
+    pub(crate) fn globals(&self) -> &Rc<RefCell<Environment>> {
+        &self.globals
+    }
+
+    /// The scope the function was declared in.
+    pub(crate) fn closure(&self) -> &Rc<RefCell<Environment>> {
+        &self.closure
+    }
+
+    /// Whether the function closes over `environment` directly.
+    pub fn captures(&self, environment: &Rc<RefCell<Environment>>) -> bool {
+        Rc::ptr_eq(&self.closure, environment)
+    }
+
+    pub fn bind(&self, instance: Rc<RefCell<LoxInstance>>) -> LoxFunction {
+        let bound_to = Some(instance.borrow().class.name.clone());
+
+        let environment = Environment::new_enclosed(self.closure.clone());
+        environment
+            .borrow_mut()
+            .define("this", &Object::Instance(instance));
+
+        LoxFunction {
+            id: Id::fresh(),
+            closure: environment,
+            bound_to,
+            ..self.clone()
+        }
+    }
+
+    /// The method as reached through its class, with `this` left
+    /// uninitialized so using it fails instead of reaching past the method.
+    pub fn unbound(&self) -> LoxFunction {
+        let environment = Environment::new_enclosed(self.closure.clone());
+        environment
+            .borrow_mut()
+            .define("this", &Object::Uninitialized);
+
+        LoxFunction {
+            id: Id::fresh(),
+            closure: environment,
+            bound_to: None,
+            ..self.clone()
+        }
+    }
+
+    /// Runs the body in `environment`, which already holds the arguments,
+    /// checking the contract around it.
+    fn run(
+        &self,
+        interpreter: &mut Interpreter,
+        environment: Rc<RefCell<Environment>>,
+    ) -> Result<Object, Exception> {
+        self.check_requires(interpreter, &environment)?;
+
+        let value = match interpreter.execute_block(&self.body, environment.clone()) {
+            Ok(()) | Err(Exception::Return(_)) if self.is_initializer => {
+                match Environment::get_at(self.closure.clone(), 0, "this") {
+                    Object::Uninitialized => return Err(Exception::unbound_this()),
+                    this => this,
+                }
+            }
+            Ok(()) => Object::Nil,
+            Err(Exception::Return(value)) => value,
+            Err(err) => return Err(err),
+        };
+
+        self.check_ensures(interpreter, &environment, value)
+    }
+
+    fn check_requires(
+        &self,
+        interpreter: &mut Interpreter,
+        environment: &Rc<RefCell<Environment>>,
+    ) -> Result<(), Exception> {
+        if let Some(requires) = &self.contract.requires
+            && !interpreter
+                .evaluate_in(&requires.condition, environment.clone())?
+                .is_truthy()
+        {
+            // The caller broke the contract, so this is reported at the call.
+            return Err(Exception::native(format!(
+                "Precondition of '{}' failed.",
+                self.name.lexeme
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Checks the postcondition against the value the body finished with,
+    /// passing the value through if it holds.
+    pub(crate) fn check_ensures(
+        &self,
+        interpreter: &mut Interpreter,
+        environment: &Rc<RefCell<Environment>>,
+        value: Object,
+    ) -> Result<Object, Exception> {
+        if let Some(ensures) = &self.contract.ensures {
+            let scope = Environment::new_enclosed(environment.clone());
+            scope.borrow_mut().define("result", &value);
+
+            if !interpreter
+                .evaluate_in(&ensures.condition, scope)?
+                .is_truthy()
+            {
+                return Err(Exception::new(
+                    ensures.keyword.clone(),
+                    format!("Postcondition of '{}' failed.", self.name.lexeme),
+                ));
+            }
+        }
+
+        Ok(value)
+    }
 }
 
 impl PartialEq for LoxFunction {
@@ -91,32 +304,18 @@ impl Hash for LoxFunction {
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Function {
     Native(NativeFn),
-    Lox(LoxFunction),
+    Lox(Rc<LoxFunction>),
 }
 
-macro_rules! native_fn {
-    ($arity:expr, $fn:expr) => {
-        $crate::object::Object::Fn($crate::function::Function::Native(
-            $crate::function::NativeFn::new($arity, $fn),
-        ))
-    };
-    ($fn:expr) => {
-        native_fn!(0, $fn)
-    };
-}
-
-pub(crate) use native_fn;
-use uuid::Uuid;
-
 impl Function {
-    pub fn native(arity: usize, code: fn(&mut Interpreter, &[Object]) -> Object) -> Self {
-        Function::Native(NativeFn::new(arity, code))
+    pub fn native(name: &str, arity: usize, code: NativeCode) -> Self {
+        Function::Native(NativeFn::new(name, arity, code))
     }
 
     pub fn arity(&self) -> usize {
         match self {
             Function::Native(f) => f.arity,
-            Function::Lox(declaration) => declaration.parameters.len(),
+            Function::Lox(declaration) => declaration.arity(),
         }
     }
 
@@ -125,8 +324,37 @@ impl Function {
         interpreter: &mut Interpreter,
         arguments: &[Object],
     ) -> Result<Object, Exception> {
+        if let Function::Native(f) = self
+            && f.variadic
+        {
+            if arguments.len() < f.arity {
+                return Err(Exception::native(format!(
+                    "Expected at least {} arguments but got {} in call to '{}'.",
+                    f.arity,
+                    arguments.len(),
+                    f.name
+                )));
+            }
+        } else if arguments.len() != self.arity() {
+            let message = match self {
+                Function::Native(f) => format!(
+                    "Expected {} arguments but got {} in call to '{}'.",
+                    f.arity,
+                    arguments.len(),
+                    f.name
+                ),
+                Function::Lox(_) => format!(
+                    "Expected {} arguments but got {}.",
+                    self.arity(),
+                    arguments.len()
+                ),
+            };
+
+            return Err(Exception::native(message));
+        }
+
         let value = match self {
-            Function::Native(f) => (f.code)(interpreter, arguments),
+            Function::Native(f) => f.call(interpreter, arguments)?,
 
             Function::Lox(declaration) => {
                 let environment = Environment::new_enclosed(declaration.closure.clone());
@@ -136,15 +364,26 @@ impl Function {
                         .define(&param.lexeme, &arguments[i]);
                 }
 
-                let result = interpreter.execute_block(&declaration.body, environment);
-
-                if let Err(Exception::Return(value)) = result {
-                    return Ok(value);
+                let globals =
+                    std::mem::replace(&mut interpreter.globals, declaration.globals.clone());
+                let result = if declaration.is_generator {
+                    // The body waits for the first value to be asked for,
+                    // though the precondition is still checked at the call.
+                    declaration
+                        .check_requires(interpreter, &environment)
+                        .map(|()| {
+                            let generator = Generator::new(declaration.clone(), environment);
+                            Object::Generator(Rc::new(RefCell::new(generator)))
+                        })
                 } else {
-                    result?; // Propagate actual errors
-                }
+                    let result = declaration.run(interpreter, environment.clone());
+                    Environment::clear_cycle(&environment);
+
+                    result
+                };
+                interpreter.globals = globals;
 
-                Object::Nil
+                result?
             }
         };
 
@@ -156,7 +395,10 @@ impl Display for Function {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         let repr = match self {
             Function::Native(_) => "<native fn>",
-            Function::Lox(declaration) => &format!("<fn {}>", declaration.name.lexeme),
+            Function::Lox(declaration) => match &declaration.bound_to {
+                Some(class) => &format!("<bound method {class}.{}>", declaration.name.lexeme),
+                None => &format!("<fn {}>", declaration.name.lexeme),
+            },
         };
 
         write!(f, "{}", repr)
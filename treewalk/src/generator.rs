@@ -0,0 +1,167 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::class::LoxInstance;
+use crate::environment::Environment;
+use crate::error::Exception;
+use crate::expr::Expr;
+use crate::function::LoxFunction;
+use crate::interpreter::Interpreter;
+use crate::object::Object;
+
+/// How far running a statement of a generator's body got.
+pub(crate) enum Step {
+    Done,
+    /// It reached a `yield`, and resumes from `Suspended` once asked for the
+    /// next value.
+    Yielded(Object, Suspended),
+}
+
+/// Where inside a statement a generator is suspended. Only statements that
+/// contain a `yield` (see [`Stmt::yields`](crate::stmt::Stmt::yields)) can
+/// be suspended, so these mirror the ones that can.
+pub(crate) enum Suspended {
+    /// At the `yield` itself, which is finished once resumed.
+    Yield,
+    Block(Cursor),
+    If {
+        then: bool,
+        inner: Box<Suspended>,
+    },
+    /// Inside the body of the current iteration.
+    While(Box<Suspended>),
+    /// Inside the body, with `resource` still to be closed.
+    With {
+        resource: Rc<RefCell<LoxInstance>>,
+        body: Cursor,
+    },
+}
+
+/// A position in a block, along with what it was running with when it was
+/// suspended.
+pub(crate) struct Cursor {
+    /// The statement to resume in, or to start with if `inner` is `None`.
+    pub index: usize,
+    pub inner: Option<Box<Suspended>>,
+    pub environment: Rc<RefCell<Environment>>,
+    /// What the block has deferred so far.
+    pub deferred: Vec<Expr>,
+}
+
+impl Cursor {
+    /// The start of a block that runs in `environment`.
+    pub fn start(environment: Rc<RefCell<Environment>>) -> Self {
+        Cursor {
+            index: 0,
+            inner: None,
+            environment,
+            deferred: vec![],
+        }
+    }
+}
+
+#[derive(Default)]
+enum State {
+    Suspended(Cursor),
+    /// Taken out while the body runs, so the generator can't resume itself.
+    #[default]
+    Running,
+    Finished,
+}
+
+/// What calling a generator function returns. Its body doesn't start until
+/// the first value is asked for, and runs only up to the next `yield` each
+/// time. If a generator is dropped while suspended, whatever its blocks
+/// deferred never runs.
+pub struct Generator {
+    pub name: String,
+    function: Rc<LoxFunction>,
+    state: State,
+    /// A value `done` ran ahead to, which `next` hands out first.
+    peeked: Option<Object>,
+}
+
+impl Generator {
+    /// A generator that runs `function`'s body in `environment`, which
+    /// already holds the arguments.
+    pub(crate) fn new(function: Rc<LoxFunction>, environment: Rc<RefCell<Environment>>) -> Self {
+        Generator {
+            name: function.name().lexeme.clone(),
+            function,
+            state: State::Suspended(Cursor::start(environment)),
+            peeked: None,
+        }
+    }
+
+    /// Runs the body up to its next `yield`, giving the yielded value, or
+    /// `None` once the body has finished.
+    pub fn resume(
+        this: &Rc<RefCell<Generator>>,
+        interpreter: &mut Interpreter,
+    ) -> Result<Option<Object>, Exception> {
+        let (function, cursor) = {
+            let mut generator = this.borrow_mut();
+            if let Some(value) = generator.peeked.take() {
+                return Ok(Some(value));
+            }
+
+            match std::mem::take(&mut generator.state) {
+                State::Suspended(cursor) => (generator.function.clone(), cursor),
+                State::Running => {
+                    return Err(Exception::native("Generator is already running."));
+                }
+                State::Finished => {
+                    generator.state = State::Finished;
+                    return Ok(None);
+                }
+            }
+        };
+
+        let environment = cursor.environment.clone();
+        let globals = std::mem::replace(&mut interpreter.globals, function.globals().clone());
+
+        let result = match interpreter.resume_block(function.body(), cursor) {
+            Ok(Step::Yielded(value, Suspended::Block(cursor))) => {
+                this.borrow_mut().state = State::Suspended(cursor);
+                Ok(Some(value))
+            }
+            Ok(Step::Yielded(..)) => unreachable!("a body is suspended in its block"),
+            step => {
+                this.borrow_mut().state = State::Finished;
+                let result = match step {
+                    Err(Exception::Return(value)) => Ok(value),
+                    Err(err) => Err(err),
+                    Ok(_) => Ok(Object::Nil),
+                }
+                .and_then(|value| function.check_ensures(interpreter, &environment, value));
+                Environment::clear_cycle(&environment);
+
+                result.map(|_| None)
+            }
+        };
+        interpreter.globals = globals;
+
+        result
+    }
+
+    /// Whether the body has finished, running it up to its next `yield` to
+    /// find out if need be.
+    pub fn done(
+        this: &Rc<RefCell<Generator>>,
+        interpreter: &mut Interpreter,
+    ) -> Result<bool, Exception> {
+        let value = Generator::resume(this, interpreter)?;
+        let done = value.is_none();
+        this.borrow_mut().peeked = value;
+
+        Ok(done)
+    }
+}
+
+impl std::fmt::Debug for Generator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Generator")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
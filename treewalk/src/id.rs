@@ -0,0 +1,14 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// A process-unique identity for values compared by reference, such as AST
+/// nodes, environments and functions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Id(u64);
+
+impl Id {
+    pub fn fresh() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+
+        Id(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
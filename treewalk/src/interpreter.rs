@@ -1,53 +1,712 @@
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs::{canonicalize, read_to_string};
 use std::ops::Deref;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use indexmap::IndexMap;
+
+use crate::class::{LoxClass, LoxInstance};
 use crate::environment::Environment;
 use crate::error::Exception;
-use crate::expr::{Expr, ExprData};
-use crate::function::{LoxFunction, native_fn};
+use crate::expr::{Expr, ExprData, Pattern};
+use crate::function::{Function, LoxFunction, NativeCode, NativeFn};
+use crate::generator::{Cursor, Generator, Step, Suspended};
+use crate::json;
 use crate::lox::{Lox, LoxState};
-use crate::object::Object;
-use crate::stmt::Stmt;
+use crate::object::{Module, Object};
+use crate::parser::Parser;
+use crate::random::Rng;
+use crate::resolver::Resolver;
+use crate::scanner::Scanner;
+use crate::stmt::{Contract, Stmt};
 use crate::token::{Token, TokenType};
 
+fn integer(value: &Object, message: &str) -> Result<i64, Exception> {
+    match value.as_f64() {
+        Some(x) if x.fract() == 0.0 => Ok(x as i64),
+
+        _ => Err(Exception::native(message)),
+    }
+}
+
+fn number(value: &Object, message: &str) -> Result<f64, Exception> {
+    value.as_f64().ok_or_else(|| Exception::native(message))
+}
+
+/// Unwraps an operand of `op`, naming which `side` of it was wrong otherwise.
+fn as_number(value: &Object, op: &Token, side: &str) -> Result<f64, Exception> {
+    value
+        .as_f64()
+        .ok_or_else(|| Exception::new(op.clone(), format!("{side} operand must be a number.")))
+}
+
+/// What integer arithmetic does when the result doesn't fit in an `i64`.
+#[cfg(feature = "integers")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Overflow {
+    /// Raise "Integer overflow." as a runtime error.
+    #[default]
+    Error,
+    /// Wrap around in two's complement.
+    Wrap,
+    /// Clamp to `i64::MIN` or `i64::MAX`.
+    Saturate,
+}
+
+#[cfg(feature = "integers")]
+impl Overflow {
+    /// Applies the mode to an operation, given its checked, wrapping and
+    /// saturating forms.
+    fn apply(
+        self,
+        op: &Token,
+        checked: Option<i64>,
+        wrapping: impl FnOnce() -> i64,
+        saturating: impl FnOnce() -> i64,
+    ) -> Result<Object, Exception> {
+        let value = match (checked, self) {
+            (Some(value), _) => value,
+            (None, Overflow::Error) => {
+                return Err(Exception::new(op.clone(), "Integer overflow."));
+            }
+            (None, Overflow::Wrap) => wrapping(),
+            (None, Overflow::Saturate) => saturating(),
+        };
+
+        Ok(Object::Int(value))
+    }
+
+    /// Arithmetic and comparison on two integers, which stays exact. `None`
+    /// for the operators that don't, like `/`, which give a float instead.
+    fn binary(self, op: &Token, lhs: i64, rhs: i64) -> Option<Result<Object, Exception>> {
+        let value = match op.kind {
+            TokenType::Plus => self.apply(
+                op,
+                lhs.checked_add(rhs),
+                || lhs.wrapping_add(rhs),
+                || lhs.saturating_add(rhs),
+            ),
+            TokenType::Minus => self.apply(
+                op,
+                lhs.checked_sub(rhs),
+                || lhs.wrapping_sub(rhs),
+                || lhs.saturating_sub(rhs),
+            ),
+            TokenType::Star => self.apply(
+                op,
+                lhs.checked_mul(rhs),
+                || lhs.wrapping_mul(rhs),
+                || lhs.saturating_mul(rhs),
+            ),
+            TokenType::Greater => Ok((lhs > rhs).into()),
+            TokenType::GreaterEqual => Ok((lhs >= rhs).into()),
+            TokenType::Less => Ok((lhs < rhs).into()),
+            TokenType::LessEqual => Ok((lhs <= rhs).into()),
+
+            _ => return None,
+        };
+
+        Some(value)
+    }
+
+    /// Negates an integer, which only overflows for `i64::MIN`.
+    fn negate(self, op: &Token, value: i64) -> Result<Object, Exception> {
+        self.apply(
+            op,
+            value.checked_neg(),
+            || value.wrapping_neg(),
+            || value.saturating_neg(),
+        )
+    }
+}
+
+/// The value of a `for` loop's variable, which `environment` declares.
+fn loop_variable(environment: &Rc<RefCell<Environment>>, name: &Token) -> Object {
+    environment
+        .borrow()
+        .get_own(&name.lexeme)
+        .expect("the loop variable is declared just outside the loop")
+}
+
+/// The elements of `value`, which must be a list of exactly `count`.
+fn unpack(value: &Object, count: usize, token: &Token) -> Result<Vec<Object>, Exception> {
+    let Object::List(list) = value else {
+        return Err(Exception::new(
+            token.clone(),
+            "Only lists and tuples can be destructured.",
+        ));
+    };
+
+    let list = list.borrow();
+    if list.len() != count {
+        return Err(Exception::new(
+            token.clone(),
+            format!("Expected {count} values to unpack but got {}.", list.len()),
+        ));
+    }
+
+    Ok(list.clone())
+}
+
+fn define_native(env: &mut Environment, name: &str, arity: usize, code: NativeCode) {
+    env.define(name, &Object::from(NativeFn::new(name, arity, code)));
+}
+
+/// Fills each `{}` in `template` with the next argument, in order. `{{`
+/// and `}}` stand for literal braces.
+fn format(template: &str, arguments: &[Object]) -> Result<String, Exception> {
+    let given = arguments.len();
+    let mut formatted = String::new();
+    let mut arguments = arguments.iter();
+    let mut placeholders = 0;
+
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+                formatted.push(c);
+            }
+            ('{', Some('}')) => {
+                chars.next();
+                placeholders += 1;
+                if let Some(argument) = arguments.next() {
+                    formatted.push_str(&argument.to_string());
+                }
+            }
+            ('{' | '}', _) => {
+                return Err(Exception::native(format!(
+                    "Unmatched '{c}' in format string."
+                )));
+            }
+
+            _ => formatted.push(c),
+        }
+    }
+
+    if placeholders != given {
+        return Err(Exception::native(format!(
+            "Format string has {placeholders} placeholders but got {given} arguments."
+        )));
+    }
+
+    Ok(formatted)
+}
+
+fn list_index(list: &[Object], index: &Object, bracket: &Token) -> Result<usize, Exception> {
+    let Some(x) = index.as_f64() else {
+        return Err(Exception::new(
+            bracket.clone(),
+            "List index must be a number.",
+        ));
+    };
+
+    if x.fract() != 0.0 || x < 0.0 || x as usize >= list.len() {
+        return Err(Exception::new(bracket.clone(), "List index out of range."));
+    }
+
+    Ok(x as usize)
+}
+
+/// Calls a function or class that was passed to a native, such as the one
+/// `map` applies. Its errors propagate as they are.
+fn call_back(
+    interpreter: &mut Interpreter,
+    callee: &Object,
+    arguments: &[Object],
+) -> Result<Object, Exception> {
+    interpreter.depth += 1;
+    let result = match callee {
+        Object::Fn(function) => function.call(interpreter, arguments),
+        Object::Class(class) => LoxClass::call(class, interpreter, arguments),
+
+        _ => Err(Exception::native("Can only call functions and classes.")),
+    };
+    interpreter.depth -= 1;
+
+    result
+}
+
+/// A list index passed to a native, which must be below `len`.
+fn native_index(index: &Object, len: usize) -> Result<usize, Exception> {
+    let index = integer(index, "List index must be an integer.")?;
+
+    usize::try_from(index)
+        .ok()
+        .filter(|&index| index < len)
+        .ok_or_else(|| Exception::native("List index out of range."))
+}
+
+fn map_key(key: Object, token: &Token) -> Result<Object, Exception> {
+    if !key.is_hashable() {
+        return Err(Exception::new(
+            token.clone(),
+            "Map keys must be nil, booleans, numbers or strings.",
+        ));
+    }
+
+    Ok(key)
+}
+
+/// Places keyword arguments in the slots of the parameters they name, after
+/// the positional ones.
+fn bind_keywords(
+    callee: &Object,
+    positional: Vec<Object>,
+    keywords: Vec<(&Token, Object)>,
+) -> Result<Vec<Object>, Exception> {
+    let parameters = match callee {
+        Object::Fn(Function::Lox(function)) => function.parameters().to_vec(),
+        Object::Class(class) => class
+            .find_method("init")
+            .map_or(vec![], |initializer| initializer.parameters().to_vec()),
+
+        _ => {
+            return Err(Exception::native(
+                "Native functions don't take keyword arguments.",
+            ));
+        }
+    };
+
+    // Any positional argument past the parameters leaves every keyword
+    // unknown or a duplicate, so none go missing here.
+    let mut slots: Vec<Option<Object>> = vec![None; parameters.len()];
+    for (slot, argument) in slots.iter_mut().zip(positional) {
+        *slot = Some(argument);
+    }
+
+    for (name, argument) in keywords {
+        let Some(index) = parameters
+            .iter()
+            .position(|parameter| parameter.lexeme == name.lexeme)
+        else {
+            return Err(Exception::new(
+                name.clone(),
+                format!("Unknown parameter '{}'.", name.lexeme),
+            ));
+        };
+
+        if slots[index].is_some() {
+            return Err(Exception::new(
+                name.clone(),
+                format!("Argument '{}' given more than once.", name.lexeme),
+            ));
+        }
+        slots[index] = Some(argument);
+    }
+
+    slots
+        .into_iter()
+        .zip(&parameters)
+        .map(|(slot, parameter)| {
+            slot.ok_or_else(|| {
+                Exception::native(format!(
+                    "Missing argument for parameter '{}'.",
+                    parameter.lexeme
+                ))
+            })
+        })
+        .collect()
+}
+
 fn stdlib(env: &mut Environment) {
+    define_native(env, "clock", 0, |_, _| {
+        Ok(Object::from(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs_f64(),
+        ))
+    });
+
+    define_native(env, "dbg", 1, |interpreter, args| {
+        let x = &args[0];
+
+        writeln!(interpreter.state.borrow().out, "{x:#?}");
+
+        Ok(Object::Nil)
+    });
+
+    define_native(env, "eprint", 1, |interpreter, args| {
+        writeln!(interpreter.state.borrow().err, "{}", args[0]);
+
+        Ok(Object::Nil)
+    });
+
+    define_native(env, "assert", 2, |_, args| {
+        if args[0].is_truthy() {
+            Ok(Object::Nil)
+        } else {
+            Err(Exception::native(format!("Assertion failed: {}", args[1])))
+        }
+    });
+
+    define_native(env, "panic", 1, |_, args| {
+        let message = Exception::native(args[0].to_string());
+
+        Err(Exception::Panic(Box::new(message)))
+    });
+
+    env.define("argv", &Object::from(vec![]));
+
+    define_native(env, "read_file", 1, |_, args| {
+        let path = String::try_from(args[0].clone())?;
+
+        read_to_string(&path)
+            .map(Object::from)
+            .map_err(|err| Exception::native(format!("Could not read file '{path}': {err}.")))
+    });
+
+    define_native(env, "write_file", 2, |_, args| {
+        let path = String::try_from(args[0].clone())?;
+        let contents = String::try_from(args[1].clone())?;
+
+        std::fs::write(&path, contents)
+            .map(|()| Object::Nil)
+            .map_err(|err| Exception::native(format!("Could not write file '{path}': {err}.")))
+    });
+
+    define_native(env, "env", 1, |interpreter, args| {
+        let name = String::try_from(args[0].clone())?;
+        let lookup = interpreter.state.borrow().env;
+
+        Ok(lookup(&name).into())
+    });
+
+    define_native(env, "env_or", 2, |interpreter, args| {
+        let name = String::try_from(args[0].clone())?;
+        let lookup = interpreter.state.borrow().env;
+
+        Ok(lookup(&name).map_or_else(|| args[1].clone(), Object::from))
+    });
+
+    define_native(env, "sb_new", 0, |_, _| {
+        Ok(Object::StringBuilder(Rc::new(RefCell::new(String::new()))))
+    });
+
+    define_native(env, "sb_append", 2, |_, args| {
+        let Object::StringBuilder(builder) = &args[0] else {
+            return Err(Exception::native("Can only append to a string builder."));
+        };
+        let piece = String::try_from(args[1].clone())?;
+        builder.borrow_mut().push_str(&piece);
+
+        Ok(args[0].clone())
+    });
+
+    define_native(env, "sb_build", 1, |_, args| {
+        let Object::StringBuilder(builder) = &args[0] else {
+            return Err(Exception::native("Can only build a string builder."));
+        };
+
+        Ok(Object::String(builder.borrow().clone()))
+    });
+
+    // Only globals are looked up by name at runtime; locals are resolved
+    // before the program runs, so they're never found here.
+    define_native(env, "defined", 1, |interpreter, args| {
+        let Object::String(name) = &args[0] else {
+            return Err(Exception::native("Variable name must be a string."));
+        };
+
+        Ok(interpreter.globals.borrow().contains(name).into())
+    });
+
+    define_native(env, "push", 2, |_, args| {
+        let Object::List(list) = &args[0] else {
+            return Err(Exception::native("Can only push to a list."));
+        };
+        list.borrow_mut().push(args[1].clone());
+
+        Ok(Object::Nil)
+    });
+
+    define_native(env, "pop", 1, |_, args| {
+        let Object::List(list) = &args[0] else {
+            return Err(Exception::native("Can only pop from a list."));
+        };
+
+        let popped = list.borrow_mut().pop();
+        popped.ok_or_else(|| Exception::native("Can't pop from an empty list."))
+    });
+
+    // Inserting at the length appends.
+    define_native(env, "insert", 3, |_, args| {
+        let Object::List(list) = &args[0] else {
+            return Err(Exception::native("Can only insert into a list."));
+        };
+        let index = native_index(&args[1], list.borrow().len() + 1)?;
+        list.borrow_mut().insert(index, args[2].clone());
+
+        Ok(Object::Nil)
+    });
+
+    define_native(env, "remove", 2, |_, args| {
+        let Object::List(list) = &args[0] else {
+            return Err(Exception::native("Can only remove from a list."));
+        };
+        let index = native_index(&args[1], list.borrow().len())?;
+        let removed = list.borrow_mut().remove(index);
+
+        Ok(removed)
+    });
+
+    define_native(env, "concat", 2, |_, args| {
+        let (Object::List(a), Object::List(b)) = (&args[0], &args[1]) else {
+            return Err(Exception::native("Can only concatenate lists."));
+        };
+
+        let mut joined = a.borrow().clone();
+        joined.extend(b.borrow().iter().cloned());
+
+        Ok(joined.into())
+    });
+
+    // The callbacks get a copy of the list, so they may change the original
+    // without disturbing the walk over it.
+    define_native(env, "map", 2, |interpreter, args| {
+        let Object::List(list) = &args[0] else {
+            return Err(Exception::native("Can only map over a list."));
+        };
+
+        let elements = list.borrow().clone();
+        let mapped = elements
+            .into_iter()
+            .map(|element| call_back(interpreter, &args[1], &[element]))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(mapped.into())
+    });
+
+    define_native(env, "filter", 2, |interpreter, args| {
+        let Object::List(list) = &args[0] else {
+            return Err(Exception::native("Can only filter a list."));
+        };
+
+        let elements = list.borrow().clone();
+        let mut kept = vec![];
+        for element in elements {
+            if call_back(interpreter, &args[1], std::slice::from_ref(&element))?.is_truthy() {
+                kept.push(element);
+            }
+        }
+
+        Ok(kept.into())
+    });
+
+    define_native(env, "reduce", 3, |interpreter, args| {
+        let Object::List(list) = &args[0] else {
+            return Err(Exception::native("Can only reduce a list."));
+        };
+
+        let elements = list.borrow().clone();
+        elements
+            .into_iter()
+            .try_fold(args[2].clone(), |acc, element| {
+                call_back(interpreter, &args[1], &[acc, element])
+            })
+    });
+
+    define_native(env, "is_function", 1, |_, args| {
+        Ok(args[0].is_callable().into())
+    });
+
+    define_native(env, "keys", 1, |_, args| {
+        let Object::Map(map) = &args[0] else {
+            return Err(Exception::native("Can only take the keys of a map."));
+        };
+
+        Ok(map.borrow().keys().cloned().collect::<Vec<_>>().into())
+    });
+
+    define_native(env, "next", 1, |interpreter, args| {
+        let Object::Generator(generator) = &args[0] else {
+            return Err(Exception::native("Can only call 'next' on a generator."));
+        };
+
+        Generator::resume(generator, interpreter)?
+            .ok_or_else(|| Exception::native("Generator is exhausted."))
+    });
+
+    define_native(env, "done", 1, |interpreter, args| {
+        let Object::Generator(generator) = &args[0] else {
+            return Err(Exception::native("Can only call 'done' on a generator."));
+        };
+
+        Ok(Generator::done(generator, interpreter)?.into())
+    });
+
     env.define(
-        "clock",
-        &native_fn!(|_, _| {
-            Object::from(
-                SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap()
-                    .as_secs_f64(),
-            )
-        }),
+        "format",
+        &Object::from(NativeFn::variadic("format", 1, |_, args| {
+            let Object::String(template) = &args[0] else {
+                return Err(Exception::native("Format string must be a string."));
+            };
+
+            format(template, &args[1..]).map(Object::String)
+        })),
     );
 
-    env.define(
-        "dbg",
-        &native_fn!(1, |_, args| {
-            let x = &args[0];
+    define_native(env, "str", 1, |_, args| {
+        Ok(Object::String(args[0].to_string()))
+    });
 
-            println!("{x:#?}");
+    define_native(env, "repr", 1, |_, args| Ok(Object::String(args[0].repr())));
 
-            Object::Nil
-        }),
-    );
+    define_native(env, "to_json", 1, |_, args| {
+        json::to_json(&args[0])
+            .map(Object::String)
+            .map_err(Exception::native)
+    });
+
+    define_native(env, "from_json", 1, |_, args| {
+        let Object::String(source) = &args[0] else {
+            return Err(Exception::native(
+                "Argument to 'from_json' must be a string.",
+            ));
+        };
+
+        json::from_json(source).map_err(Exception::native)
+    });
+
+    define_native(env, "gc_stats", 0, |interpreter, _| {
+        let stats = interpreter.environment.borrow().stats();
+        // Not counting the call to `gc_stats` itself.
+        let calls = interpreter.depth.saturating_sub(1);
+
+        #[allow(clippy::mutable_key_type)]
+        let map = [
+            ("environments", stats.live),
+            ("variables", stats.variables),
+            ("calls", calls),
+        ]
+        .into_iter()
+        .map(|(key, count)| (Object::from(key), Object::from(count as f64)))
+        .collect();
+
+        Ok(Object::Map(Rc::new(RefCell::new(map))))
+    });
+
+    define_native(env, "random", 0, |interpreter, _| {
+        Ok(Object::from(interpreter.rng.next_f64()))
+    });
+
+    define_native(env, "random_int", 2, |interpreter, args| {
+        let message = "Bounds to 'random_int' must be integers.";
+        let lo = integer(&args[0], message)?;
+        let hi = integer(&args[1], message)?;
+
+        if lo > hi {
+            return Err(Exception::native(
+                "Lower bound to 'random_int' must not exceed the upper bound.",
+            ));
+        }
+
+        Ok(Object::from(interpreter.rng.range(lo, hi) as f64))
+    });
+
+    define_native(env, "seed", 1, |interpreter, args| {
+        let seed = integer(&args[0], "Seed must be an integer.")?;
+        interpreter.rng.reseed(seed as u64);
+
+        Ok(Object::Nil)
+    });
+
+    // `==` stays exact; this is for when rounding error is expected.
+    define_native(env, "approx_eq", 3, |_, args| {
+        let message = "Arguments to 'approx_eq' must be numbers.";
+        let a = number(&args[0], message)?;
+        let b = number(&args[1], message)?;
+        let tolerance = number(&args[2], message)?;
+
+        if tolerance < 0.0 {
+            return Err(Exception::native("Tolerance must not be negative."));
+        }
+
+        Ok(((a - b).abs() <= tolerance).into())
+    });
+
+    // Negative places round to tens, hundreds and so on.
+    define_native(env, "round", 2, |_, args| {
+        let x = number(&args[0], "Can only round a number.")?;
+        let places = integer(&args[1], "Places to round to must be an integer.")?;
+
+        let factor = 10f64.powi(places.clamp(i32::MIN.into(), i32::MAX.into()) as i32);
+        let scaled = x * factor;
+
+        let rounded = if !scaled.is_finite() {
+            // More places than a float holds, so there's nothing to round.
+            x
+        } else if factor == 0.0 {
+            // Fewer places than the largest float has digits.
+            0.0
+        } else {
+            scaled.round() / factor
+        };
+
+        Ok(Object::from(rounded))
+    });
 }
 
 #[derive(Debug)]
 pub struct Interpreter {
     pub(crate) state: Rc<RefCell<LoxState>>,
-    globals: Rc<RefCell<Environment>>,
+    pub(crate) globals: Rc<RefCell<Environment>>,
     environment: Rc<RefCell<Environment>>,
     locals: HashMap<Expr, usize>,
+    modules: HashMap<PathBuf, Object>,
+    rng: Rng,
+    trace: bool,
+    strict: bool,
+    /// How many calls are in progress.
+    depth: usize,
+    /// The expressions deferred by each block being run, innermost last.
+    deferred: Vec<Vec<Expr>>,
+    /// Set from outside, e.g. by a Ctrl-C handler, to stop the running
+    /// program before its next statement.
+    interrupted: Arc<AtomicBool>,
+    /// How the `test` blocks run so far went, or `None` outside test mode,
+    /// where they're skipped.
+    tests: Option<TestResults>,
+    #[cfg(feature = "integers")]
+    overflow: Overflow,
+}
+
+/// The tally of `test` blocks run in test mode.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TestResults {
+    pub passed: usize,
+    pub failed: usize,
+}
+
+impl Display for TestResults {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "test result: {} passed; {} failed.",
+            self.passed, self.failed
+        )
+    }
 }
 
 impl Interpreter {
     pub fn new(state: Rc<RefCell<LoxState>>) -> Self {
+        Interpreter::with_rng(state, Rng::from_clock())
+    }
+
+    /// Creates an interpreter whose `random` natives replay the same sequence
+    /// for the same seed.
+    pub fn with_seed(state: Rc<RefCell<LoxState>>, seed: u64) -> Self {
+        Interpreter::with_rng(state, Rng::new(seed))
+    }
+
+    fn with_rng(state: Rc<RefCell<LoxState>>, rng: Rng) -> Self {
         let mut lib = Environment::new_raw();
 
         stdlib(&mut lib);
@@ -62,72 +721,419 @@ impl Interpreter {
             globals,
             environment,
             locals,
+            modules: HashMap::new(),
+            rng,
+            trace: false,
+            strict: false,
+            depth: 0,
+            deferred: vec![],
+            interrupted: Arc::new(AtomicBool::new(false)),
+            tests: None,
+            #[cfg(feature = "integers")]
+            overflow: Overflow::default(),
         }
     }
 
-    fn look_up_var(&self, name: &Token, expr: &Expr) -> Result<Object, Exception> {
+    /// Logs every executed statement and every call to the error sink while
+    /// enabled.
+    pub fn set_trace(&mut self, trace: bool) {
+        self.trace = trace;
+    }
+
+    /// Makes reading a variable declared without an initializer an error,
+    /// rather than yielding `nil`.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// Chooses what integer arithmetic does on overflow. By default it's a
+    /// runtime error.
+    #[cfg(feature = "integers")]
+    pub fn set_overflow(&mut self, overflow: Overflow) {
+        self.overflow = overflow;
+    }
+
+    /// Runs `test` blocks, which are otherwise skipped, keeping count of
+    /// which pass.
+    pub fn set_testing(&mut self, testing: bool) {
+        self.tests = testing.then(TestResults::default);
+    }
+
+    /// The results of the tests run so far, if in test mode.
+    pub fn test_results(&self) -> Option<TestResults> {
+        self.tests
+    }
+
+    /// Sets the `argv` global, the arguments given after the script's path.
+    /// The path itself isn't included.
+    pub fn set_args(&mut self, args: Vec<String>) {
+        let args = args.into_iter().map(Object::from).collect::<Vec<_>>();
+        self.globals
+            .borrow_mut()
+            .define("argv", &Object::from(args));
+    }
+
+    /// Adds a native function to the globals, so embedders can extend the
+    /// standard library.
+    pub fn define_native(&mut self, name: &str, arity: usize, code: NativeCode) {
+        define_native(&mut self.globals.borrow_mut(), name, arity, code);
+    }
+
+    /// Like [`Interpreter::define_native`], but for code that captures state
+    /// from the host.
+    pub fn define_native_closure(
+        &mut self,
+        name: &str,
+        arity: usize,
+        code: impl Fn(&mut Interpreter, &[Object]) -> Result<Object, Exception> + 'static,
+    ) {
+        let native = NativeFn::closure(name, arity, code);
+        self.globals
+            .borrow_mut()
+            .define(name, &Object::from(native));
+    }
+
+    /// A flag that, once set, stops the running program with an
+    /// "Interrupted." error. It may be set from another thread or a signal
+    /// handler.
+    pub fn interrupt_handle(&self) -> Arc<AtomicBool> {
+        self.interrupted.clone()
+    }
+
+    fn trace_line(&self, message: impl Display) {
+        writeln!(
+            self.state.borrow().err,
+            "[trace] {:indent$}{message}",
+            "",
+            indent = self.depth * 2
+        );
+    }
+
+    fn assign_variable(
+        &mut self,
+        expr: &Expr,
+        name: &Token,
+        value: &Object,
+    ) -> Result<(), Exception> {
         if let Some(distance) = self.locals.get(expr) {
-            Ok(Environment::get_at(
-                self.environment.clone(),
-                *distance,
-                &name.lexeme,
-            ))
+            Environment::assign_at(self.environment.clone(), *distance, name, value);
         } else {
-            self.globals.borrow().get(name)
+            self.globals.borrow_mut().assign(name, value)?;
         }
+
+        Ok(())
     }
 
+    fn look_up_var(&self, name: &Token, expr: &Expr) -> Result<Object, Exception> {
+        let value = if let Some(distance) = self.locals.get(expr) {
+            Environment::get_at(self.environment.clone(), *distance, &name.lexeme)
+        } else {
+            self.globals.borrow().get(name)?
+        };
+
+        Interpreter::initialized(name, value)
+    }
+
+    fn not_indexable(
+        instance: &Rc<RefCell<LoxInstance>>,
+        method: &str,
+        bracket: &Token,
+    ) -> Exception {
+        let class = &instance.borrow().class.name;
+
+        Exception::new(
+            bracket.clone(),
+            format!("Class '{class}' has no '{method}' method."),
+        )
+    }
+
+    fn initialized(name: &Token, value: Object) -> Result<Object, Exception> {
+        if let Object::Uninitialized = value {
+            return Err(Exception::new(
+                name.clone(),
+                format!("Variable '{}' used before assignment.", name.lexeme),
+            ));
+        }
+
+        Ok(value)
+    }
+
+    /// Evaluates `expr`. An error that escapes without a line of its own
+    /// is given the expression's.
     fn evaluate(&mut self, expr: &Expr) -> Result<Object, Exception> {
+        self.evaluate_data(expr)
+            .map_err(|err| err.at_line(expr.line()))
+    }
+
+    fn evaluate_data(&mut self, expr: &Expr) -> Result<Object, Exception> {
         let value = match &expr.data {
             ExprData::Literal { value } => value.clone(),
-            ExprData::Grouping { expr } => self.evaluate(expr.deref())?,
-            ExprData::Unary { op, rhs } => match op.kind {
-                TokenType::Bang => (!self.evaluate(rhs.deref())?.is_truthy()).into(),
+            ExprData::Get { object, name } => match self.evaluate(object)? {
+                Object::Instance(instance) => LoxInstance::get(&instance, name)?,
+                Object::Module(module) => {
+                    let value = module.environment.borrow().get_own(&name.lexeme);
 
-                TokenType::Minus => {
-                    if let ExprData::Literal {
-                        value: Object::Number(value),
-                    } = rhs.data
-                    {
-                        Object::Number(-value)
+                    let value = value.ok_or_else(|| {
+                        Exception::new(
+                            name.clone(),
+                            format!("Undefined property '{}'.", name.lexeme),
+                        )
+                    })?;
+
+                    Interpreter::initialized(name, value)?
+                }
+                Object::Class(class) => {
+                    let method = class.find_method(&name.lexeme).ok_or_else(|| {
+                        Exception::new(name.clone(), format!("Undefined method '{}'.", name.lexeme))
+                    })?;
+
+                    Object::from(method.unbound())
+                }
+
+                _ => {
+                    return Err(Exception::new(
+                        name.clone(),
+                        "Only instances have properties.",
+                    ));
+                }
+            },
+            ExprData::Match {
+                scrutinee, arms, ..
+            } => {
+                let value = self.evaluate(scrutinee)?;
+
+                let mut result = Object::Nil;
+                for arm in arms {
+                    match &arm.pattern {
+                        Pattern::Literal(literal) if *literal == value => {
+                            result = self.evaluate(&arm.body)?;
+                        }
+                        Pattern::Literal(_) => continue,
+                        Pattern::Wildcard => result = self.evaluate(&arm.body)?,
+                        Pattern::Binding(name) => {
+                            let scope = Environment::new_enclosed(self.environment.clone());
+                            scope.borrow_mut().define(&name.lexeme, &value);
+                            result = self.evaluate_in(&arm.body, scope)?;
+                        }
+                    }
+                    break;
+                }
+
+                result
+            }
+            ExprData::Set {
+                object,
+                name,
+                value,
+            } => {
+                let Object::Instance(instance) = self.evaluate(object)? else {
+                    return Err(Exception::new(name.clone(), "Only instances have fields."));
+                };
+
+                let value = self.evaluate(value)?;
+                instance.borrow_mut().set(name, &value);
+
+                value
+            }
+            ExprData::Super { keyword, method } => {
+                let distance = self.locals[expr];
+                let Object::Class(superclass) =
+                    Environment::get_at(self.environment.clone(), distance, "super")
+                else {
+                    unreachable!("'super' is always bound to a class");
+                };
+                // `this` is always in the scope just inside `super`'s, and is
+                // only left uninitialized in a method reached through its class.
+                let instance =
+                    match Environment::get_at(self.environment.clone(), distance - 1, "this") {
+                        Object::Instance(instance) => instance,
+                        Object::Uninitialized => return Err(Exception::unbound_this().at(keyword)),
+                        _ => unreachable!("'this' is always bound to an instance"),
+                    };
+
+                // Only methods are found through `super`: fields belong to the
+                // instance, whichever class set them.
+                let Some(method) = superclass.find_method(&method.lexeme) else {
+                    let name = &method.lexeme;
+                    let message = if instance.borrow().has_field(name) {
+                        format!(
+                            "Superclass '{}' has no method '{name}'; use 'this.{name}' for the field.",
+                            superclass.name
+                        )
                     } else {
-                        return Err(Exception::num(op.clone()));
+                        format!("Superclass '{}' has no method '{name}'.", superclass.name)
+                    };
+
+                    return Err(Exception::new(method.clone(), message));
+                };
+
+                Object::from(method.bind(instance))
+            }
+            ExprData::This { keyword } => match self.locals.get(expr) {
+                Some(distance) => {
+                    match Environment::get_at(self.environment.clone(), *distance, "this") {
+                        Object::Uninitialized => return Err(Exception::unbound_this().at(keyword)),
+                        this => this,
                     }
                 }
+                None => self.look_up_var(keyword, expr)?,
+            },
+            ExprData::Grouping { expr } => self.evaluate(expr.deref())?,
+            ExprData::Lambda {
+                arrow,
+                parameters,
+                body,
+            } => {
+                let name = Token::new(TokenType::Identifier, "lambda", Object::Nil, arrow.line);
+                let function = LoxFunction::new(
+                    name,
+                    parameters.clone(),
+                    Contract::default(),
+                    body.clone(),
+                    self.environment.clone(),
+                    self.globals.clone(),
+                    false,
+                );
+
+                Object::from(function)
+            }
+            ExprData::List { elements } => {
+                let mut list = vec![];
+                for element in elements {
+                    list.push(self.evaluate(element)?);
+                }
+
+                Object::List(Rc::new(RefCell::new(list)))
+            }
+            ExprData::Map { brace, entries } => {
+                #[allow(clippy::mutable_key_type)]
+                let mut map = IndexMap::new();
+                for (key, value) in entries {
+                    let key = map_key(self.evaluate(key)?, brace)?;
+                    let value = self.evaluate(value)?;
+                    map.insert(key, value);
+                }
+
+                Object::Map(Rc::new(RefCell::new(map)))
+            }
+            ExprData::Index {
+                object,
+                bracket,
+                index,
+            } => match (self.evaluate(object)?, self.evaluate(index)?) {
+                (Object::List(list), index) => {
+                    let list = list.borrow();
+                    list[list_index(&list, &index, bracket)?].clone()
+                }
+                (Object::Map(map), key) => {
+                    let key = map_key(key, bracket)?;
+                    let value = map.borrow().get(&key).cloned();
+
+                    value.ok_or_else(|| {
+                        Exception::new(bracket.clone(), format!("Undefined key '{key}'."))
+                    })?
+                }
+                (Object::Instance(instance), key) => {
+                    LoxInstance::invoke(&instance, self, "__index__", &[key])
+                        .ok_or_else(|| Interpreter::not_indexable(&instance, "__index__", bracket))?
+                        .map_err(|err| err.at(bracket))?
+                }
+
+                _ => {
+                    return Err(Exception::new(
+                        bracket.clone(),
+                        "Only lists and maps can be indexed.",
+                    ));
+                }
+            },
+            ExprData::SetIndex {
+                object,
+                bracket,
+                index,
+                value,
+            } => {
+                let (object, index) = (self.evaluate(object)?, self.evaluate(index)?);
+                let value = self.evaluate(value)?;
+
+                match object {
+                    Object::List(list) => {
+                        let mut list = list.borrow_mut();
+                        let index = list_index(&list, &index, bracket)?;
+                        list[index] = value.clone();
+                    }
+                    Object::Map(map) => {
+                        let key = map_key(index, bracket)?;
+                        map.borrow_mut().insert(key, value.clone());
+                    }
+                    Object::Instance(instance) => {
+                        LoxInstance::invoke(
+                            &instance,
+                            self,
+                            "__setindex__",
+                            &[index, value.clone()],
+                        )
+                        .ok_or_else(|| {
+                            Interpreter::not_indexable(&instance, "__setindex__", bracket)
+                        })?
+                        .map_err(|err| err.at(bracket))?;
+                    }
+
+                    _ => {
+                        return Err(Exception::new(
+                            bracket.clone(),
+                            "Only lists and maps can be indexed.",
+                        ));
+                    }
+                }
+
+                value
+            }
+            ExprData::Unary { op, rhs } => match op.kind {
+                TokenType::Bang => (!self.evaluate(rhs.deref())?.is_truthy()).into(),
+
+                TokenType::Minus => match self.evaluate(rhs.deref())? {
+                    Object::Number(value) => Object::Number(-value),
+                    #[cfg(feature = "integers")]
+                    Object::Int(value) => self.overflow.negate(op, value)?,
+                    _ => return Err(Exception::num(op.clone())),
+                },
 
                 _ => unreachable!("no other unary expression"),
             },
             ExprData::Binary { op, lhs, rhs } => {
                 let (lhs, rhs) = (self.evaluate(lhs.as_ref())?, self.evaluate(rhs.as_ref())?);
 
+                #[cfg(feature = "integers")]
+                if let (Object::Int(lhs), Object::Int(rhs)) = (&lhs, &rhs)
+                    && let Some(value) = self.overflow.binary(op, *lhs, *rhs)
+                {
+                    return value;
+                }
+
                 macro_rules! binary {
-                    ($op:tt, $kind:tt) => {
-                        if let (Object::Number(lhs), Object::Number(rhs)) = (lhs, rhs) {
-                            Ok(Object::$kind(lhs $op rhs))
-                        } else {
-                            Err(Exception::num_pair(op.clone()))
-                        }
+                    ($op:tt) => {
+                        Object::from(as_number(&lhs, op, "Left")? $op as_number(&rhs, op, "Right")?)
                     };
                 }
 
                 match op.kind {
-                    TokenType::Minus => binary!(-, Number)?,
-                    TokenType::Slash => binary!(/, Number)?,
-                    TokenType::Star => binary!(*, Number)?,
+                    TokenType::Minus => binary!(-),
+                    TokenType::Slash => binary!(/),
+                    TokenType::Star => binary!(*),
 
-                    TokenType::Plus => match (lhs, rhs) {
-                        (Object::Number(lhs), Object::Number(rhs)) => (lhs + rhs).into(),
-                        (Object::String(lhs), Object::String(rhs)) => (lhs + &rhs).as_str().into(),
+                    TokenType::Plus => match (&lhs, &rhs) {
+                        (Object::String(lhs), Object::String(rhs)) => (lhs.clone() + rhs).into(),
+                        _ if lhs.as_f64().is_some() && rhs.as_f64().is_some() => binary!(+),
 
                         _ => {
-                            return Err(Exception::nums_or_strings(op.clone()));
+                            return Err(Exception::nums_or_strings(op.clone(), &lhs, &rhs));
                         }
                     },
 
-                    TokenType::Greater => binary!(>, Boolean)?,
-                    TokenType::GreaterEqual => binary!( >=, Boolean)?,
-                    TokenType::Less => binary!(<, Boolean)?,
-                    TokenType::LessEqual => binary!(<=, Boolean)?,
+                    TokenType::Greater => binary!(>),
+                    TokenType::GreaterEqual => binary!(>=),
+                    TokenType::Less => binary!(<),
+                    TokenType::LessEqual => binary!(<=),
 
                     TokenType::BangEqual => (lhs != rhs).into(),
                     TokenType::EqualEqual => (lhs == rhs).into(),
@@ -139,23 +1145,36 @@ impl Interpreter {
             ExprData::Variable { name } => self.look_up_var(name, expr)?,
             ExprData::Assign { name, value } => {
                 let value = self.evaluate(value)?;
-                // self.environment.borrow_mut().assign(name, &value)?;
-                if let Some(distance) = self.locals.get(expr) {
-                    // self.environment.assign
-                    Environment::assign_at(self.environment.clone(), *distance, name, &value);
-                } else {
-                    self.globals.borrow_mut().assign(name, &value)?;
+                self.assign_variable(expr, name, &value)?;
+
+                value
+            }
+            ExprData::Destructure {
+                equals,
+                targets,
+                value,
+            } => {
+                let value = self.evaluate(value)?;
+                let values = unpack(&value, targets.len(), equals)?;
+
+                for (target, value) in targets.iter().zip(&values) {
+                    let ExprData::Variable { name } = &target.data else {
+                        unreachable!("destructuring targets are always variables");
+                    };
+                    self.assign_variable(target, name, value)?;
                 }
 
                 value
             }
             ExprData::Logical { op, lhs, rhs } => {
                 let lhs = self.evaluate(lhs)?;
-                if op.kind == TokenType::Or {
-                    if lhs.is_truthy() {
-                        return Ok(lhs);
-                    }
-                } else if !lhs.is_truthy() {
+                let short_circuits = match op.kind {
+                    TokenType::Or | TokenType::PipePipeEqual => lhs.is_truthy(),
+                    TokenType::QuestionQuestionEqual => lhs != Object::Nil,
+                    _ => !lhs.is_truthy(),
+                };
+
+                if short_circuits {
                     return Ok(lhs);
                 }
 
@@ -165,15 +1184,33 @@ impl Interpreter {
                 callee,
                 paren,
                 arguments,
+                keywords,
             } => {
-                let callee = self.evaluate(callee)?;
+                let mut callee = self.evaluate(callee)?;
+
+                // A callable instance stands in for its bound `__call__`.
+                let call_method = match &callee {
+                    Object::Instance(instance) => {
+                        let method = instance.borrow().class.find_method("__call__");
+                        method.map(|method| Object::from(method.bind(instance.clone())))
+                    }
+                    _ => None,
+                };
+                if let Some(method) = call_method {
+                    callee = method;
+                }
 
                 let mut args = vec![];
                 for argument in arguments {
                     args.push(self.evaluate(argument)?);
                 }
 
-                let Object::Fn(function) = callee else {
+                let mut named = vec![];
+                for (name, argument) in keywords {
+                    named.push((name, self.evaluate(argument)?));
+                }
+
+                if !callee.is_callable() {
                     let paren = paren.clone();
                     return Err(Exception::new(
                         paren,
@@ -181,30 +1218,130 @@ impl Interpreter {
                     ));
                 };
 
-                let paren = paren.clone();
-                if arguments.len() != function.arity() {
-                    return Err(Exception::new(
-                        paren,
-                        format!(
-                            "Expected {} arguments but got {}.",
-                            function.arity(),
-                            arguments.len()
-                        ),
-                    ));
+                if !named.is_empty() {
+                    args = bind_keywords(&callee, args, named).map_err(|err| err.at(paren))?;
+                }
+
+                if self.trace {
+                    let args = args.iter().map(Object::to_string).collect::<Vec<_>>();
+                    self.trace_line(format_args!("call {callee}({})", args.join(", ")));
+                }
+                self.depth += 1;
+
+                let result = match &callee {
+                    Object::Fn(function) => function.call(self, &args),
+                    Object::Class(class) => LoxClass::call(class, self, &args),
+
+                    _ => unreachable!("checked above"),
+                };
+                let result = result.map_err(|err| err.at(paren));
+
+                self.depth -= 1;
+                if self.trace
+                    && let Ok(value) = &result
+                {
+                    self.trace_line(format_args!("return {callee} -> {value}"));
                 }
-                function.call(self, &args)?
+
+                result?
             }
         };
 
         Ok(value)
     }
 
+    fn import(&mut self, path: &Token, alias: Option<&Token>) -> Result<(), Exception> {
+        let Object::String(relative) = path.literal.as_ref() else {
+            unreachable!("import paths are string literals");
+        };
+
+        let importer = self.state.borrow().importing.last().cloned();
+        let full_path = match importer.as_deref().and_then(Path::parent) {
+            Some(dir) => dir.join(relative),
+            None => PathBuf::from(relative),
+        };
+
+        let (Ok(full_path), Ok(source)) = (canonicalize(&full_path), read_to_string(&full_path))
+        else {
+            return Err(Exception::new(
+                path.clone(),
+                format!("Could not read import '{relative}'."),
+            ));
+        };
+
+        if self.state.borrow().importing.contains(&full_path) {
+            return Err(Exception::new(
+                path.clone(),
+                format!("Import cycle detected at '{relative}'."),
+            ));
+        }
+
+        if let Some(alias) = alias {
+            if let Some(module) = self.modules.get(&full_path).cloned() {
+                self.environment.borrow_mut().define(&alias.lexeme, &module);
+                return Ok(());
+            }
+        } else if self.state.borrow().imported.contains(&full_path) {
+            return Ok(());
+        }
+
+        let (tokens, scan_errors) = Scanner::new(&source).scan_tokens();
+        let (statements, parse_errors) = Parser::new(tokens).parse();
+
+        for error in &scan_errors {
+            Lox::scan_error(self.state.borrow_mut(), error);
+        }
+        for error in &parse_errors {
+            Lox::parse_error(self.state.borrow_mut(), error);
+        }
+
+        if !self.state.borrow().had_error {
+            Resolver::new(self).resolve_statements(&statements);
+        }
+
+        if self.state.borrow().had_error {
+            return Err(Exception::new(
+                path.clone(),
+                format!("Could not import '{relative}'."),
+            ));
+        }
+
+        // A namespaced module gets its own globals, which still see ours.
+        let environment = match alias {
+            Some(_) => Environment::new_enclosed(self.globals.clone()),
+            None => self.globals.clone(),
+        };
+
+        self.state.borrow_mut().importing.push(full_path);
+        let globals = std::mem::replace(&mut self.globals, environment.clone());
+        let result = self.execute_block(&statements, environment.clone());
+        self.globals = globals;
+        let full_path = self.state.borrow_mut().importing.pop().unwrap();
+
+        result?;
+
+        if let Some(alias) = alias {
+            let module = Object::Module(Rc::new(Module {
+                name: alias.lexeme.clone(),
+                environment,
+            }));
+
+            self.environment.borrow_mut().define(&alias.lexeme, &module);
+            self.modules.insert(full_path, module);
+        } else {
+            self.state.borrow_mut().imported.insert(full_path);
+        }
+
+        Ok(())
+    }
+
     pub(crate) fn execute_block(
         &mut self,
         statements: &[Stmt],
         environment: Rc<RefCell<Environment>>,
     ) -> Result<(), Exception> {
         let previous = self.environment.clone();
+        self.deferred.push(vec![]);
 
         let result = 'block: {
             self.environment = environment;
@@ -219,34 +1356,314 @@ impl Interpreter {
             Ok(())
         };
 
+        let deferred = self.deferred.pop().unwrap_or_default();
+        let result = self.run_deferred(&deferred, result);
+
+        self.environment = previous;
+
+        result
+    }
+
+    /// Evaluates a block's deferred expressions, last deferred first, after
+    /// the block exits with `result`. The first error among them replaces a
+    /// normal exit or a return, but not an earlier error. Like `with`, a panic
+    /// skips them.
+    fn run_deferred(
+        &mut self,
+        deferred: &[Expr],
+        mut result: Result<(), Exception>,
+    ) -> Result<(), Exception> {
+        if matches!(result, Err(Exception::Panic(_))) {
+            return result;
+        }
+
+        for expr in deferred.iter().rev() {
+            match self.evaluate(expr) {
+                Ok(_) => (),
+                Err(err @ Exception::Panic(_)) => return Err(err),
+                Err(err)
+                    if matches!(
+                        result,
+                        Ok(()) | Err(Exception::Return(_) | Exception::Continue)
+                    ) =>
+                {
+                    result = Err(err);
+                }
+                Err(_) => (),
+            }
+        }
+
+        result
+    }
+
+    /// Runs a `test` block if in test mode. A runtime error, such as a failed
+    /// `assert`, fails the test without stopping the program; a panic still
+    /// does.
+    fn test(&mut self, name: &Token, body: &[Stmt]) -> Result<(), Exception> {
+        if self.tests.is_none() {
+            return Ok(());
+        }
+
+        let environment = Environment::new_enclosed(self.environment.clone());
+        let result = self.execute_block(body, environment.clone());
+        Environment::clear_cycle(&environment);
+
+        let passed = match result {
+            Err(err @ Exception::Panic(_)) => return Err(err),
+            Ok(()) => true,
+            Err(err) => {
+                let state = self.state.borrow();
+                writeln!(state.out, "test {} ... FAILED", name.literal);
+                writeln!(state.err, "{err}");
+                false
+            }
+        };
+
+        if passed {
+            writeln!(self.state.borrow().out, "test {} ... ok", name.literal);
+        }
+        if let Some(tests) = &mut self.tests {
+            if passed {
+                tests.passed += 1;
+            } else {
+                tests.failed += 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Runs `body` with the resource bound, then closes the resource whether
+    /// or not the body finished normally, unless it panicked. An error from
+    /// the body wins over one from `close`.
+    fn with(
+        &mut self,
+        keyword: &Token,
+        binding: &Token,
+        initializer: &Expr,
+        body: &Stmt,
+    ) -> Result<(), Exception> {
+        let resource = self.open(keyword, initializer)?;
+        let environment = Environment::new_enclosed(self.environment.clone());
+        environment
+            .borrow_mut()
+            .define(&binding.lexeme, &Object::Instance(resource.clone()));
+
+        let result = self.execute_block(std::slice::from_ref(body), environment.clone());
+        Environment::clear_cycle(&environment);
+
+        self.close(keyword, &resource, result)
+    }
+
+    /// Evaluates a `with` statement's resource, checking it can be closed.
+    fn open(
+        &mut self,
+        keyword: &Token,
+        initializer: &Expr,
+    ) -> Result<Rc<RefCell<LoxInstance>>, Exception> {
+        let resource = self.evaluate(initializer)?;
+
+        let not_closable = || {
+            Exception::new(
+                keyword.clone(),
+                "Resource bound by 'with' must have a 'close' method.",
+            )
+        };
+        let Object::Instance(instance) = &resource else {
+            return Err(not_closable());
+        };
+        if instance.borrow().class.find_method("close").is_none() {
+            return Err(not_closable());
+        }
+
+        Ok(instance.clone())
+    }
+
+    /// Closes a `with` statement's resource once its body exits with
+    /// `result`.
+    fn close(
+        &mut self,
+        keyword: &Token,
+        resource: &Rc<RefCell<LoxInstance>>,
+        result: Result<(), Exception>,
+    ) -> Result<(), Exception> {
+        if let Err(Exception::Panic(_)) = result {
+            return result;
+        }
+
+        let closed = LoxInstance::invoke(resource, self, "close", &[])
+            .expect("checked when opened")
+            .map_err(|err| err.at(keyword));
+
+        result?;
+        closed.map(|_| ())
+    }
+
+    /// Runs a `for` loop's body with the loop variable copied into a scope of
+    /// its own, then copies it back out for the increment to see.
+    fn execute_rebound(&mut self, name: &Token, body: &Stmt) -> Result<(), Exception> {
+        let environment = self.rebind(name);
+        let result = self.execute_block(std::slice::from_ref(body), environment.clone());
+        self.unbind(name, &environment);
+
+        result
+    }
+
+    /// A scope for one run of a `for` loop's body, holding a copy of the loop
+    /// variable.
+    fn rebind(&self, name: &Token) -> Rc<RefCell<Environment>> {
+        let environment = Environment::new_enclosed(self.environment.clone());
+        environment
+            .borrow_mut()
+            .define(&name.lexeme, &loop_variable(&self.environment, name));
+
+        environment
+    }
+
+    /// Copies the loop variable back out of the scope made by `rebind`, once
+    /// the body is done with it.
+    fn unbind(&self, name: &Token, environment: &Rc<RefCell<Environment>>) {
+        self.environment
+            .borrow_mut()
+            .define(&name.lexeme, &loop_variable(environment, name));
+        Environment::clear_cycle(environment);
+    }
+
+    /// Evaluates `expr` with `environment` as the innermost scope.
+    pub(crate) fn evaluate_in(
+        &mut self,
+        expr: &Expr,
+        environment: Rc<RefCell<Environment>>,
+    ) -> Result<Object, Exception> {
+        let previous = std::mem::replace(&mut self.environment, environment);
+        let result = self.evaluate(expr);
         self.environment = previous;
 
         result
     }
 
+    /// Checks for an interrupt and traces `stmt` before it runs.
+    fn enter(&mut self, stmt: &Stmt) -> Result<(), Exception> {
+        if self.interrupted.swap(false, Ordering::Relaxed) {
+            return Err(Exception::native("Interrupted."));
+        }
+
+        if self.trace {
+            self.trace_line(stmt.describe());
+        }
+
+        Ok(())
+    }
+
     fn execute(&mut self, stmt: &Stmt) -> Result<(), Exception> {
+        self.enter(stmt)?;
+
         match stmt {
             Stmt::Expr { expr } => {
                 self.evaluate(expr)?;
             }
+            Stmt::Import { path, alias, .. } => self.import(path, alias.as_ref())?,
             Stmt::Print { expr } => {
                 let value = self.evaluate(expr)?;
-                println!("{value}");
+                writeln!(self.state.borrow().out, "{value}");
+            }
+            Stmt::Destructure {
+                paren,
+                names,
+                initializer,
+            } => {
+                let value = self.evaluate(initializer)?;
+                let values = unpack(&value, names.len(), paren)?;
+
+                for (name, value) in names.iter().zip(&values) {
+                    self.environment.borrow_mut().define(&name.lexeme, value);
+                }
             }
             Stmt::Var { name, initializer } => {
                 let value = if let Some(initializer) = initializer {
                     self.evaluate(initializer)?
+                } else if self.strict {
+                    Object::Uninitialized
                 } else {
                     Object::Nil
                 };
 
                 self.environment.borrow_mut().define(&name.lexeme, &value);
             }
-            Stmt::Block { statements } => {
-                self.execute_block(
-                    statements,
-                    Environment::new_enclosed(self.environment.clone()),
-                )?;
+            Stmt::Block { statements, scoped } => {
+                if *scoped {
+                    let environment = Environment::new_enclosed(self.environment.clone());
+                    let result = self.execute_block(statements, environment.clone());
+                    Environment::clear_cycle(&environment);
+                    result?;
+                } else {
+                    self.execute_block(statements, self.environment.clone())?;
+                }
+            }
+            Stmt::Class {
+                name,
+                superclass,
+                methods,
+            } => {
+                let superclass = match superclass {
+                    Some(expr) => match self.evaluate(expr)? {
+                        Object::Class(class) => Some(class),
+                        _ => {
+                            let ExprData::Variable { name } = &expr.data else {
+                                unreachable!("superclasses are always variables");
+                            };
+                            return Err(Exception::new(
+                                name.clone(),
+                                "Superclass must be a class.",
+                            ));
+                        }
+                    },
+                    None => None,
+                };
+
+                self.environment
+                    .borrow_mut()
+                    .define(&name.lexeme, &Object::Nil);
+
+                // Methods of a subclass see `super` in a scope of its own.
+                let enclosing = self.environment.clone();
+                if let Some(superclass) = &superclass {
+                    self.environment = Environment::new_enclosed(enclosing.clone());
+                    self.environment
+                        .borrow_mut()
+                        .define("super", &Object::Class(superclass.clone()));
+                }
+
+                let mut class_methods = HashMap::new();
+                for method in methods {
+                    let Stmt::Function {
+                        name,
+                        parameters,
+                        contract,
+                        body,
+                    } = method
+                    else {
+                        unreachable!("class bodies only contain methods");
+                    };
+
+                    let function = LoxFunction::new(
+                        name.clone(),
+                        parameters.clone(),
+                        contract.as_ref().clone(),
+                        body.clone(),
+                        self.environment.clone(),
+                        self.globals.clone(),
+                        name.lexeme == "init",
+                    );
+                    class_methods.insert(name.lexeme.clone(), Rc::new(function));
+                }
+
+                self.environment = enclosing;
+
+                let class = LoxClass::new(&name.lexeme, superclass, class_methods);
+                self.environment
+                    .borrow_mut()
+                    .assign(name, &Object::Class(Rc::new(class)))?;
             }
             Stmt::If {
                 condition,
@@ -259,21 +1676,63 @@ impl Interpreter {
                     self.execute(else_branch)?;
                 }
             }
-            Stmt::While { condition, body } => {
+            Stmt::Defer { expr } => {
+                if let Some(deferred) = self.deferred.last_mut() {
+                    deferred.push(expr.clone());
+                }
+            }
+            // A generator's body runs through `resume` instead, so this is
+            // a `yield` somewhere it can't suspend from, like a `test` block.
+            Stmt::Yield { keyword, .. } => {
+                return Err(Exception::new(
+                    keyword.clone(),
+                    "Can only yield directly from a generator's body.",
+                ));
+            }
+            Stmt::While {
+                condition,
+                body,
+                increment,
+                rebinds,
+            } => {
                 while self.evaluate(condition)?.is_truthy() {
-                    self.execute(body)?;
+                    let result = match rebinds {
+                        Some(name) => self.execute_rebound(name, body),
+                        None => self.execute(body),
+                    };
+
+                    match result {
+                        Ok(()) | Err(Exception::Continue) => (),
+                        Err(err) => return Err(err),
+                    }
+
+                    if let Some(increment) = increment {
+                        self.evaluate(increment)?;
+                    }
                 }
             }
+            Stmt::Continue { .. } => return Err(Exception::Continue),
+            Stmt::Test { name, body, .. } => self.test(name, body)?,
+            Stmt::With {
+                keyword,
+                binding,
+                initializer,
+                body,
+            } => self.with(keyword, binding, initializer, body)?,
             Stmt::Function {
                 name,
                 parameters,
+                contract,
                 body,
             } => {
                 let function = LoxFunction::new(
                     name.clone(),
                     parameters.clone(),
+                    contract.as_ref().clone(),
                     body.clone(),
                     self.environment.clone(),
+                    self.globals.clone(),
+                    false,
                 );
 
                 self.environment
@@ -294,27 +1753,238 @@ impl Interpreter {
         Ok(())
     }
 
-    pub fn interpret(&mut self, statements: &[Stmt]) {
+    /// Runs `stmt` in a generator's body, starting over or picking up where
+    /// it was suspended. Statements that can't yield just run.
+    fn resume(&mut self, stmt: &Stmt, from: Option<Suspended>) -> Result<Step, Exception> {
+        if !stmt.yields() {
+            return self.execute(stmt).map(|()| Step::Done);
+        }
+        if from.is_none() {
+            self.enter(stmt)?;
+        }
+
+        match stmt {
+            Stmt::Yield { expr, .. } => match from {
+                Some(_) => Ok(Step::Done),
+                None => Ok(Step::Yielded(self.evaluate(expr)?, Suspended::Yield)),
+            },
+            Stmt::Block { statements, scoped } => {
+                let cursor = match from {
+                    Some(Suspended::Block(cursor)) => cursor,
+                    None if *scoped => {
+                        Cursor::start(Environment::new_enclosed(self.environment.clone()))
+                    }
+                    None => Cursor::start(self.environment.clone()),
+                    Some(_) => unreachable!("a block is suspended in its cursor"),
+                };
+                let environment = cursor.environment.clone();
+
+                let result = self.resume_block(statements, cursor);
+                if *scoped && !matches!(result, Ok(Step::Yielded(..))) {
+                    Environment::clear_cycle(&environment);
+                }
+
+                result
+            }
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                let (then, inner) = match from {
+                    Some(Suspended::If { then, inner }) => (then, Some(*inner)),
+                    None => (self.evaluate(condition)?.is_truthy(), None),
+                    Some(_) => unreachable!("an if is suspended in a branch"),
+                };
+
+                let branch = match (then, else_branch) {
+                    (true, _) => then_branch,
+                    (false, Some(else_branch)) => else_branch,
+                    (false, None) => return Ok(Step::Done),
+                };
+
+                Ok(match self.resume(branch, inner)? {
+                    Step::Yielded(value, inner) => Step::Yielded(
+                        value,
+                        Suspended::If {
+                            then,
+                            inner: Box::new(inner),
+                        },
+                    ),
+                    Step::Done => Step::Done,
+                })
+            }
+            Stmt::While {
+                condition,
+                body,
+                increment,
+                rebinds,
+            } => {
+                let mut from = match from {
+                    Some(Suspended::While(inner)) => Some(*inner),
+                    None => None,
+                    Some(_) => unreachable!("a loop is suspended in its body"),
+                };
+
+                while from.is_some() || self.evaluate(condition)?.is_truthy() {
+                    let result = match rebinds {
+                        Some(name) => self.resume_rebound(name, body, from.take()),
+                        None => self.resume(body, from.take()),
+                    };
+
+                    match result {
+                        Ok(Step::Yielded(value, inner)) => {
+                            return Ok(Step::Yielded(value, Suspended::While(Box::new(inner))));
+                        }
+                        Ok(Step::Done) | Err(Exception::Continue) => (),
+                        Err(err) => return Err(err),
+                    }
+
+                    if let Some(increment) = increment {
+                        self.evaluate(increment)?;
+                    }
+                }
+
+                Ok(Step::Done)
+            }
+            Stmt::With {
+                keyword,
+                binding,
+                initializer,
+                body,
+            } => {
+                let (resource, cursor) = match from {
+                    Some(Suspended::With { resource, body }) => (resource, body),
+                    None => {
+                        let resource = self.open(keyword, initializer)?;
+                        let environment = Environment::new_enclosed(self.environment.clone());
+                        environment
+                            .borrow_mut()
+                            .define(&binding.lexeme, &Object::Instance(resource.clone()));
+
+                        (resource, Cursor::start(environment))
+                    }
+                    Some(_) => unreachable!("a with is suspended in its body"),
+                };
+                let environment = cursor.environment.clone();
+
+                let result = match self.resume_block(std::slice::from_ref(body), cursor) {
+                    Ok(Step::Yielded(value, Suspended::Block(body))) => {
+                        return Ok(Step::Yielded(value, Suspended::With { resource, body }));
+                    }
+                    result => result.map(|_| ()),
+                };
+                Environment::clear_cycle(&environment);
+
+                self.close(keyword, &resource, result).map(|()| Step::Done)
+            }
+
+            _ => unreachable!("only blocks, ifs, loops and withs contain a yield"),
+        }
+    }
+
+    /// Runs `statements` in the cursor's environment from where it points,
+    /// like `execute_block`, but handing the cursor back if they yield.
+    pub(crate) fn resume_block(
+        &mut self,
+        statements: &[Stmt],
+        cursor: Cursor,
+    ) -> Result<Step, Exception> {
+        let Cursor {
+            index,
+            mut inner,
+            environment,
+            deferred,
+        } = cursor;
+
+        let previous = std::mem::replace(&mut self.environment, environment.clone());
+        self.deferred.push(deferred);
+
         let result = 'block: {
-            for stmt in statements {
-                match self.execute(stmt) {
-                    Ok(_) => (),
-                    x => break 'block x,
+            for (index, stmt) in statements.iter().enumerate().skip(index) {
+                match self.resume(stmt, inner.take().map(|inner| *inner)) {
+                    Ok(Step::Done) => (),
+                    Ok(Step::Yielded(value, at)) => {
+                        let deferred = self.deferred.pop().unwrap_or_default();
+                        self.environment = previous;
+
+                        let cursor = Cursor {
+                            index,
+                            inner: Some(Box::new(at)),
+                            environment,
+                            deferred,
+                        };
+                        return Ok(Step::Yielded(value, Suspended::Block(cursor)));
+                    }
+                    Err(err) => break 'block Err(err),
                 }
             }
 
             Ok(())
         };
 
+        let deferred = self.deferred.pop().unwrap_or_default();
+        let result = self.run_deferred(&deferred, result);
+
+        self.environment = previous;
+
+        result.map(|()| Step::Done)
+    }
+
+    /// Runs or resumes one iteration of a `for` loop's body in a generator,
+    /// as `execute_rebound` does outside of one.
+    fn resume_rebound(
+        &mut self,
+        name: &Token,
+        body: &Stmt,
+        from: Option<Suspended>,
+    ) -> Result<Step, Exception> {
+        let cursor = match from {
+            Some(Suspended::Block(cursor)) => cursor,
+            None => Cursor::start(self.rebind(name)),
+            Some(_) => unreachable!("an iteration is suspended in its scope"),
+        };
+        let environment = cursor.environment.clone();
+
+        let result = self.resume_block(std::slice::from_ref(body), cursor);
+        if !matches!(result, Ok(Step::Yielded(..))) {
+            self.unbind(name, &environment);
+        }
+
+        result
+    }
+
+    pub fn interpret(&mut self, statements: &[Stmt]) {
+        // An interrupt that arrived while nothing was running is stale.
+        self.interrupted.store(false, Ordering::Relaxed);
+
+        let result = self.execute_block(statements, self.environment.clone());
+
         match result {
             Ok(_) => (),
-            Err(Exception::Error { token, message }) => {
-                Lox::runtime_error(self.state.borrow_mut(), Exception::Error { token, message })
-            }
             Err(Exception::Return(x)) => unreachable!("Escaped return signal: {x}"),
+            Err(Exception::Continue) => unreachable!("Escaped continue signal"),
+            Err(err) => Lox::runtime_error(self.state.borrow_mut(), err),
         }
     }
 
+    /// Resolves a whole script, returning just its resolution rather than
+    /// mixing it into what's already loaded.
+    #[allow(clippy::mutable_key_type)]
+    pub(crate) fn resolve_script(&mut self, statements: &[Stmt]) -> HashMap<Expr, usize> {
+        let loaded = std::mem::take(&mut self.locals);
+        Resolver::new(self).resolve_statements(statements);
+
+        std::mem::replace(&mut self.locals, loaded)
+    }
+
+    /// Makes a script's resolution available to run it.
+    #[allow(clippy::mutable_key_type)]
+    pub(crate) fn load_locals(&mut self, locals: &HashMap<Expr, usize>) {
+        self.locals
+            .extend(locals.iter().map(|(expr, depth)| (expr.clone(), *depth)));
+    }
+
     pub(crate) fn resolve(&mut self, expr: &Expr, depth: usize) {
         self.locals.insert(expr.clone(), depth);
     }
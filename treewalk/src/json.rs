@@ -0,0 +1,302 @@
+use std::cell::RefCell;
+use std::fmt::Write;
+use std::iter::Peekable;
+use std::rc::Rc;
+use std::str::Chars;
+
+use indexmap::IndexMap;
+
+use crate::object::Object;
+
+/// How deeply arrays and objects may nest before `from_json` gives up,
+/// so hostile input can't exhaust the native stack.
+const MAX_DEPTH: usize = 512;
+
+pub fn to_json(value: &Object) -> Result<String, String> {
+    let mut out = String::new();
+    write_value(&mut out, value, &mut vec![])?;
+
+    Ok(out)
+}
+
+fn write_string(out: &mut String, value: &str) {
+    out.push('"');
+
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => write!(out, "\\u{:04x}", c as u32).unwrap(),
+
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+}
+
+/// Writes `value` to `out`. `path` holds the lists and maps currently being
+/// written, so a container that reaches itself is reported instead of
+/// recursing forever.
+fn write_value(out: &mut String, value: &Object, path: &mut Vec<*const ()>) -> Result<(), String> {
+    let container = match value {
+        Object::List(list) => Some(Rc::as_ptr(list).cast()),
+        Object::Map(map) => Some(Rc::as_ptr(map).cast()),
+        _ => None,
+    };
+
+    if let Some(ptr) = container {
+        if path.contains(&ptr) {
+            return Err("Can't serialize a cyclic value.".to_owned());
+        }
+        path.push(ptr);
+    }
+
+    match value {
+        Object::Nil => out.push_str("null"),
+        Object::Boolean(x) => write!(out, "{x}").unwrap(),
+        Object::Number(x) if x.is_finite() => write!(out, "{x}").unwrap(),
+        Object::Number(x) => return Err(format!("Can't convert {x} to JSON.")),
+        #[cfg(feature = "integers")]
+        Object::Int(x) => write!(out, "{x}").unwrap(),
+        Object::String(x) => write_string(out, x),
+        Object::List(list) => {
+            out.push('[');
+            for (i, element) in list.borrow().iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_value(out, element, path)?;
+            }
+            out.push(']');
+        }
+        Object::Map(map) => {
+            out.push('{');
+            for (i, (key, value)) in map.borrow().iter().enumerate() {
+                let Object::String(key) = key else {
+                    return Err("JSON object keys must be strings.".to_owned());
+                };
+
+                if i > 0 {
+                    out.push(',');
+                }
+                write_string(out, key);
+                out.push(':');
+                write_value(out, value, path)?;
+            }
+            out.push('}');
+        }
+
+        x => return Err(format!("Can't convert {x} to JSON.")),
+    }
+
+    if container.is_some() {
+        path.pop();
+    }
+
+    Ok(())
+}
+
+pub fn from_json(source: &str) -> Result<Object, String> {
+    let mut parser = JsonParser {
+        chars: source.chars().peekable(),
+        depth: 0,
+    };
+
+    let value = parser.value()?;
+
+    parser.skip_whitespace();
+    if parser.chars.peek().is_some() {
+        return Err(parser.error("trailing characters"));
+    }
+
+    Ok(value)
+}
+
+struct JsonParser<'src> {
+    chars: Peekable<Chars<'src>>,
+    depth: usize,
+}
+
+impl JsonParser<'_> {
+    fn error(&mut self, message: &str) -> String {
+        match self.chars.peek() {
+            Some(c) => format!("Invalid JSON: {message} at '{c}'."),
+            None => format!("Invalid JSON: {message} at end of input."),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|c| c.is_ascii_whitespace()).is_some() {}
+    }
+
+    fn expect(&mut self, expected: char, message: &str) -> Result<(), String> {
+        match self.chars.next_if_eq(&expected) {
+            Some(_) => Ok(()),
+            None => Err(self.error(message)),
+        }
+    }
+
+    fn keyword(&mut self, word: &str, value: Object) -> Result<Object, String> {
+        for expected in word.chars() {
+            self.expect(expected, &format!("expected '{word}'"))?;
+        }
+
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Object, String> {
+        self.skip_whitespace();
+
+        match self.chars.peek() {
+            Some('[' | '{') if self.depth == MAX_DEPTH => Err(self.error("nesting too deep")),
+            Some('n') => self.keyword("null", Object::Nil),
+            Some('t') => self.keyword("true", Object::Boolean(true)),
+            Some('f') => self.keyword("false", Object::Boolean(false)),
+            Some('"') => Ok(Object::String(self.string()?)),
+            Some('[') => self.nested(Self::array),
+            Some('{') => self.nested(Self::object),
+            Some(c) if *c == '-' || c.is_ascii_digit() => self.number(),
+
+            _ => Err(self.error("expected a value")),
+        }
+    }
+
+    fn nested(&mut self, parse: fn(&mut Self) -> Result<Object, String>) -> Result<Object, String> {
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+
+        value
+    }
+
+    fn number(&mut self) -> Result<Object, String> {
+        let mut text = String::new();
+
+        while let Some(c) = self
+            .chars
+            .next_if(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'))
+        {
+            text.push(c);
+        }
+
+        text.parse::<f64>()
+            .map(Object::from)
+            .map_err(|_| format!("Invalid JSON: malformed number '{text}'."))
+    }
+
+    fn hex_escape(&mut self) -> Result<u32, String> {
+        let mut code = 0;
+
+        for _ in 0..4 {
+            let Some(digit) = self.chars.peek().and_then(|c| c.to_digit(16)) else {
+                return Err(self.error("expected four hex digits"));
+            };
+
+            self.chars.next();
+            code = code * 16 + digit;
+        }
+
+        Ok(code)
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"', "expected a string")?;
+
+        let mut value = String::new();
+
+        loop {
+            match self.chars.next() {
+                Some('"') => return Ok(value),
+                Some('\\') => {
+                    let c = match self.chars.next() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => {
+                            let mut code = self.hex_escape()?;
+
+                            // A high surrogate must be followed by its low half.
+                            if (0xD800..0xDC00).contains(&code) {
+                                self.expect('\\', "expected a low surrogate")?;
+                                self.expect('u', "expected a low surrogate")?;
+                                let low = self.hex_escape()?;
+                                if !(0xDC00..0xE000).contains(&low) {
+                                    return Err(self.error("expected a low surrogate"));
+                                }
+                                code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                            }
+
+                            char::from_u32(code)
+                                .ok_or_else(|| self.error("invalid unicode escape"))?
+                        }
+
+                        _ => return Err(self.error("invalid escape")),
+                    };
+
+                    value.push(c);
+                }
+                Some(c) => value.push(c),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Object, String> {
+        self.expect('[', "expected '['")?;
+        self.skip_whitespace();
+
+        let mut elements = vec![];
+
+        if self.chars.next_if_eq(&']').is_none() {
+            loop {
+                elements.push(self.value()?);
+                self.skip_whitespace();
+
+                if self.chars.next_if_eq(&',').is_none() {
+                    break;
+                }
+            }
+
+            self.expect(']', "expected ',' or ']'")?;
+        }
+
+        Ok(Object::List(Rc::new(RefCell::new(elements))))
+    }
+
+    fn object(&mut self) -> Result<Object, String> {
+        self.expect('{', "expected '{'")?;
+        self.skip_whitespace();
+
+        #[allow(clippy::mutable_key_type)]
+        let mut map = IndexMap::new();
+
+        if self.chars.next_if_eq(&'}').is_none() {
+            loop {
+                self.skip_whitespace();
+                let key = self.string()?;
+                self.skip_whitespace();
+                self.expect(':', "expected ':'")?;
+                let value = self.value()?;
+                map.insert(Object::String(key), value);
+                self.skip_whitespace();
+
+                if self.chars.next_if_eq(&',').is_none() {
+                    break;
+                }
+            }
+
+            self.expect('}', "expected ',' or '}'")?;
+        }
+
+        Ok(Object::Map(Rc::new(RefCell::new(map))))
+    }
+}
@@ -1,14 +1,40 @@
 #![feature(debug_closure_helpers)]
 
+pub mod class;
 pub mod environment;
 pub mod error;
 pub mod expr;
 pub mod function;
+pub mod generator;
+pub mod id;
 pub mod interpreter;
+pub mod json;
 pub mod lox;
 pub mod object;
 pub mod parser;
+pub mod random;
 pub mod resolver;
 pub mod scanner;
 pub mod stmt;
 pub mod token;
+
+use crate::error::{Diagnostic, ParseError, ScanError};
+use crate::parser::Parser;
+use crate::scanner::Scanner;
+use crate::stmt::Stmt;
+
+/// Scans and parses `source` without resolving or running it.
+///
+/// The statements are only complete when there are no diagnostics.
+pub fn parse_program(source: &str) -> (Vec<Stmt>, Vec<Diagnostic>) {
+    let (tokens, scan_errors) = Scanner::new(source).scan_tokens();
+    let (statements, parse_errors) = Parser::new(tokens).parse();
+
+    let diagnostics = scan_errors
+        .iter()
+        .map(ScanError::to_diagnostic)
+        .chain(parse_errors.iter().map(ParseError::to_diagnostic))
+        .collect();
+
+    (statements, diagnostics)
+}
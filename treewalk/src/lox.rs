@@ -1,22 +1,35 @@
 use std::cell::{RefCell, RefMut};
-use std::fmt::Display;
-use std::fs::read_to_string;
+use std::collections::{HashMap, HashSet};
+use std::fs::{canonicalize, read_to_string};
 #[cfg(not(feature = "fancy-repl"))]
 use std::io::{Write, stdin, stdout};
+use std::path::PathBuf;
 use std::rc::Rc;
+use std::sync::Arc;
+#[cfg(feature = "fancy-repl")]
+use std::sync::OnceLock;
+use std::sync::atomic::AtomicBool;
+#[cfg(feature = "fancy-repl")]
+use std::sync::atomic::Ordering;
 
 use common::exit::{RUNTIME_ERROR, SYNTAX_ERROR};
+use common::sink::Sink;
 #[cfg(feature = "fancy-repl")]
 use rustyline::DefaultEditor;
 #[cfg(feature = "fancy-repl")]
 use rustyline::error::ReadlineError;
 
-use crate::error::Exception;
-use crate::interpreter::Interpreter;
+use crate::error::{Diagnostic, Exception, ParseError, ScanError, Severity, Span};
+use crate::expr::Expr;
+use crate::function::NativeCode;
+#[cfg(feature = "integers")]
+use crate::interpreter::Overflow;
+use crate::interpreter::{Interpreter, TestResults};
+use crate::object::Object;
 use crate::parser::Parser;
-use crate::resolver::Resolver;
 use crate::scanner::Scanner;
-use crate::token::{Token, TokenType};
+use crate::stmt::Stmt;
+use crate::token::Token;
 
 pub const MAX_ARGS: usize = 255;
 
@@ -24,92 +37,335 @@ pub const MAX_ARGS: usize = 255;
 pub struct LoxState {
     pub had_error: bool,
     pub had_runtime_error: bool,
+    /// Files currently being imported, innermost last. Relative imports are
+    /// resolved against the last entry.
+    pub importing: Vec<PathBuf>,
+    /// Every file that has finished importing, so each runs at most once.
+    pub imported: HashSet<PathBuf>,
+    /// Where `print` writes.
+    pub out: Sink,
+    /// Where errors and traces are written.
+    pub err: Sink,
+    /// Where the `env` natives look up environment variables.
+    pub env: fn(&str) -> Option<String>,
+    /// Every diagnostic reported since they were last taken.
+    pub diagnostics: Vec<Diagnostic>,
+    /// Whether diagnostics are also written to `err` as they're reported.
+    pub print_diagnostics: bool,
 }
 
 impl LoxState {
-    const fn new() -> Self {
+    fn new() -> Self {
         LoxState {
             had_error: false,
             had_runtime_error: false,
+            importing: vec![],
+            imported: HashSet::new(),
+            out: Sink::Stdout,
+            err: Sink::Stderr,
+            env: |name| std::env::var(name).ok(),
+            diagnostics: vec![],
+            print_diagnostics: true,
+        }
+    }
+}
+
+/// What the REPL shows when it's waiting for input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplConfig {
+    /// Shown before each new line of input.
+    pub prompt: String,
+    /// Shown before a line that continues an unfinished one.
+    pub continuation: String,
+    /// The most lines of history the fancy REPL keeps.
+    pub history_size: usize,
+    /// Where the fancy REPL keeps its history between sessions. Without one,
+    /// history lasts only as long as the session.
+    pub history_file: Option<PathBuf>,
+}
+
+impl ReplConfig {
+    /// The default config, with the prompt taken from `LOX_PROMPT` if it's
+    /// set.
+    pub fn from_env() -> Self {
+        let mut config = ReplConfig::default();
+        if let Ok(prompt) = std::env::var("LOX_PROMPT") {
+            config.prompt = prompt;
+        }
+
+        config
+    }
+
+    /// Where history goes given the user's home directory, if they have one.
+    pub fn history_file_in(home: Option<PathBuf>) -> Option<PathBuf> {
+        home.map(|home| home.join(".cache/lox_history"))
+    }
+
+    /// The prompt to show, depending on whether the line continues the one
+    /// before it.
+    pub fn render(&self, continuing: bool) -> &str {
+        if continuing {
+            &self.continuation
+        } else {
+            &self.prompt
         }
     }
 }
 
+impl Default for ReplConfig {
+    fn default() -> Self {
+        ReplConfig {
+            prompt: "> ".to_owned(),
+            continuation: "... ".to_owned(),
+            history_size: 1000,
+            history_file: ReplConfig::history_file_in(std::env::home_dir()),
+        }
+    }
+}
+
+/// A script that's been scanned, parsed and resolved once, so it can be run
+/// any number of times without doing that again. It carries its own
+/// resolution, so any `Lox` can run it.
+#[derive(Debug, Clone)]
+pub struct CompiledScript {
+    statements: Vec<Stmt>,
+    locals: HashMap<Expr, usize>,
+}
+
 #[derive(Debug)]
 pub struct Lox {
     state: Rc<RefCell<LoxState>>,
-    interpreter: Option<Interpreter>,
+    interpreter: Interpreter,
+    repl: ReplConfig,
+    /// See [`Parser::set_lenient`].
+    lenient: bool,
 }
 
 impl Lox {
     pub fn new() -> Self {
+        Lox::with_interpreter(Interpreter::new)
+    }
+
+    /// See [`Interpreter::with_seed`].
+    pub fn with_seed(seed: u64) -> Self {
+        Lox::with_interpreter(|state| Interpreter::with_seed(state, seed))
+    }
+
+    fn with_interpreter(interpreter: impl FnOnce(Rc<RefCell<LoxState>>) -> Interpreter) -> Self {
         let state = Rc::new(RefCell::new(LoxState::new()));
-        let interpreter = Some(Interpreter::new(state.clone()));
+        let interpreter = interpreter(state.clone());
+
+        Lox {
+            state,
+            interpreter,
+            repl: ReplConfig::default(),
+            lenient: false,
+        }
+    }
 
-        Lox { state, interpreter }
+    /// See [`Interpreter::set_args`].
+    pub fn set_args(&mut self, args: Vec<String>) {
+        self.interpreter.set_args(args);
     }
 
-    fn run(&mut self, source: &str) {
-        let scanner = Scanner::new(self.state.clone(), source);
-        let tokens = scanner.scan_tokens();
+    /// See [`Interpreter::define_native`].
+    pub fn define_native(&mut self, name: &str, arity: usize, code: NativeCode) {
+        self.interpreter.define_native(name, arity, code);
+    }
+
+    /// See [`Interpreter::define_native_closure`].
+    pub fn define_native_closure(
+        &mut self,
+        name: &str,
+        arity: usize,
+        code: impl Fn(&mut Interpreter, &[Object]) -> Result<Object, Exception> + 'static,
+    ) {
+        self.interpreter.define_native_closure(name, arity, code);
+    }
+
+    /// See [`Interpreter::interrupt_handle`].
+    pub fn interrupt_handle(&self) -> Arc<AtomicBool> {
+        self.interpreter.interrupt_handle()
+    }
+
+    /// Replaces where scripts read environment variables from, e.g. to keep
+    /// tests independent of the real environment.
+    pub fn set_env(&mut self, env: fn(&str) -> Option<String>) {
+        self.state.borrow_mut().env = env;
+    }
 
-        let mut parser = Parser::new(self.state.clone(), tokens);
-        let statements = parser.parse();
+    pub fn set_repl_config(&mut self, repl: ReplConfig) {
+        self.repl = repl;
+    }
+
+    pub fn set_trace(&mut self, trace: bool) {
+        self.interpreter.set_trace(trace);
+    }
+
+    pub fn set_strict(&mut self, strict: bool) {
+        self.interpreter.set_strict(strict);
+    }
+
+    /// Redirects program output and error reporting, e.g. to capture them.
+    pub fn set_output(&mut self, out: Sink, err: Sink) {
+        let mut state = self.state.borrow_mut();
+        state.out = out;
+        state.err = err;
+    }
+
+    /// Runs `source` as a script. Errors are reported to the error sink, not
+    /// returned.
+    pub fn run(&mut self, source: &str) {
+        if let Some(script) = self.compile(source) {
+            self.run_compiled(&script);
+        }
+    }
+
+    /// Runs a script compiled earlier, possibly by another `Lox`. Errors are
+    /// reported as `run` reports them.
+    pub fn run_compiled(&mut self, script: &CompiledScript) {
+        self.interpreter.load_locals(&script.locals);
+        self.interpreter.interpret(&script.statements);
+    }
+
+    /// Scans, parses and resolves `source` without running it, reporting any
+    /// errors, and exits as `run_file` would if there were some.
+    pub fn check(&mut self, source: &str) {
+        self.compile(source);
+        self.exit_on_error();
+    }
+
+    /// Runs the front end over `source`, giving a script ready to run, or
+    /// `None` after reporting its errors.
+    pub fn compile(&mut self, source: &str) -> Option<CompiledScript> {
+        let (tokens, errors) = Scanner::new(source).scan_tokens();
+
+        for error in &errors {
+            Lox::scan_error(self.state.borrow_mut(), error);
+        }
+
+        let mut parser = Parser::new(tokens);
+        parser.set_lenient(self.lenient);
+        let (statements, errors) = parser.parse();
+
+        for error in &errors {
+            Lox::parse_error(self.state.borrow_mut(), error);
+        }
+        for warning in parser.take_warnings() {
+            Lox::diagnostic(self.state.borrow_mut(), warning.to_warning());
+        }
 
         // Stop if there was a syntax error.
         if self.state.borrow().had_error {
-            return;
+            return None;
         }
 
-        let mut resolver = Resolver::new(std::mem::take(&mut self.interpreter).unwrap());
-        resolver.resolve_statements(&statements);
+        #[allow(clippy::mutable_key_type)]
+        let locals = self.interpreter.resolve_script(&statements);
 
         // Stop if there was a resolution error.
         if self.state.borrow().had_error {
-            return;
+            return None;
         };
 
-        let mut interpreter = resolver.finish();
-        interpreter.interpret(&statements);
-        self.interpreter = Some(interpreter);
+        Some(CompiledScript { statements, locals })
     }
 
+    /// Reports a resolution error known only by its line.
     pub fn error(state: RefMut<LoxState>, line: usize, message: &str) {
-        Lox::report(state, line, "", message);
+        let diagnostic = Diagnostic {
+            severity: Severity::Error,
+            span: Span {
+                line,
+                ..Span::default()
+            },
+            location: String::new(),
+            message: message.to_owned(),
+            code: "resolve",
+        };
+
+        Lox::diagnostic(state, diagnostic);
     }
 
+    /// Reports a resolution error at `token`.
     pub fn error_at(state: RefMut<LoxState>, token: &Token, message: &str) {
-        if token.kind == TokenType::Eof {
-            Lox::report(state, token.line, " at end", message);
-        } else {
-            Lox::report(
-                state,
-                token.line,
-                format!(" at '{}'", token.lexeme),
-                message,
-            );
+        let diagnostic = Diagnostic::at(token, Severity::Error, "resolve", message);
+        Lox::diagnostic(state, diagnostic);
+    }
+
+    /// Reports a problem that doesn't stop the program from running.
+    pub fn warning_at(state: RefMut<LoxState>, token: &Token, message: &str) {
+        let diagnostic = Diagnostic::at(token, Severity::Warning, "resolve", message);
+        Lox::diagnostic(state, diagnostic);
+    }
+
+    pub fn scan_error(state: RefMut<LoxState>, error: &ScanError) {
+        Lox::diagnostic(state, error.to_diagnostic());
+    }
+
+    pub fn parse_error(state: RefMut<LoxState>, error: &ParseError) {
+        Lox::diagnostic(state, error.to_diagnostic());
+    }
+
+    /// Where every diagnostic ends up: collected, printed unless that's been
+    /// turned off, and, for errors, stopping the program from running.
+    pub fn diagnostic(mut state: RefMut<LoxState>, diagnostic: Diagnostic) {
+        if state.print_diagnostics {
+            writeln!(state.err, "{diagnostic}");
         }
+        if diagnostic.severity == Severity::Error {
+            state.had_error = true;
+        }
+
+        state.diagnostics.push(diagnostic);
     }
 
-    fn report(mut state: RefMut<LoxState>, line: usize, at: impl Display, message: &str) {
-        eprintln!("[line {line}] Error{at}: {message}");
-        state.had_error = true;
+    /// Lets the REPL carry on after a line with errors.
+    fn forget_errors(&mut self) {
+        let mut state = self.state.borrow_mut();
+        state.had_error = false;
+        state.diagnostics.clear();
+    }
+
+    /// Hands over the diagnostics reported so far, leaving none behind.
+    pub fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+        std::mem::take(&mut self.state.borrow_mut().diagnostics)
+    }
+
+    /// Parses leniently, inserting a `;` or `}` missing at the end of the
+    /// source with a warning. See [`Parser::set_lenient`].
+    pub fn set_lenient(&mut self, lenient: bool) {
+        self.lenient = lenient;
+    }
+
+    /// Whether diagnostics are written to the error sink as well as
+    /// collected. Tools that read them with `take_diagnostics` turn it off.
+    pub fn set_print_diagnostics(&mut self, print: bool) {
+        self.state.borrow_mut().print_diagnostics = print;
     }
 
     #[cfg(feature = "fancy-repl")]
     fn fancy_prompt(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        let mut rl = DefaultEditor::new()?;
-        let history_path = std::env::home_dir().unwrap().join(".cache/lox_history");
+        let config = rustyline::Config::builder()
+            .max_history_size(self.repl.history_size)?
+            .build();
+        let mut rl = DefaultEditor::with_config(config)?;
+
+        // Ctrl-C at the prompt is read as a key, but while a line runs it
+        // raises SIGINT, which should stop the program rather than the REPL.
+        interrupt_on_sigint(self.interrupt_handle());
 
-        let _ = rl.load_history(&history_path);
+        if let Some(history_file) = &self.repl.history_file {
+            let _ = rl.load_history(history_file);
+        }
 
         loop {
-            let readline = rl.readline("> ");
+            let readline = rl.readline(self.repl.render(false));
             match readline {
                 Ok(line) => {
                     rl.add_history_entry(line.as_str())?;
                     self.run(&line);
-                    self.state.borrow_mut().had_error = false;
+                    self.forget_errors();
                 }
                 Err(ReadlineError::Interrupted) => {
                     println!("SIGINT");
@@ -126,7 +382,9 @@ impl Lox {
             }
         }
 
-        rl.save_history(&history_path)?;
+        if let Some(history_file) = &self.repl.history_file {
+            rl.save_history(history_file)?;
+        }
         Ok(())
     }
 
@@ -136,7 +394,7 @@ impl Lox {
         let input = stdin();
 
         loop {
-            print!("> ");
+            print!("{}", self.repl.render(false));
             stdout().lock().flush()?;
 
             line.clear();
@@ -148,7 +406,7 @@ impl Lox {
             }
 
             self.run(&line);
-            self.state.borrow_mut().had_error = false;
+            self.forget_errors();
         }
 
         Ok(())
@@ -169,8 +427,58 @@ impl Lox {
     pub fn run_file(&mut self, path: &str) -> std::io::Result<()> {
         let source = read_to_string(path)?;
 
+        let path = canonicalize(path)?;
+        self.state.borrow_mut().importing.push(path);
+
         self.run(&source);
+        self.report_tests();
+        self.exit_on_error();
 
+        Ok(())
+    }
+
+    /// Reads a whole script from stdin and runs it as `run_file` would. Its
+    /// imports are relative to the working directory.
+    pub fn run_stdin(&mut self) -> std::io::Result<()> {
+        let source = std::io::read_to_string(std::io::stdin())?;
+
+        self.run(&source);
+        self.report_tests();
+        self.exit_on_error();
+
+        Ok(())
+    }
+
+    /// See [`Interpreter::set_overflow`].
+    #[cfg(feature = "integers")]
+    pub fn set_overflow(&mut self, overflow: Overflow) {
+        self.interpreter.set_overflow(overflow);
+    }
+
+    /// Runs `test` blocks instead of skipping them. See
+    /// [`Interpreter::set_testing`].
+    pub fn set_testing(&mut self, testing: bool) {
+        self.interpreter.set_testing(testing);
+    }
+
+    pub fn test_results(&self) -> Option<TestResults> {
+        self.interpreter.test_results()
+    }
+
+    /// Prints the summary in test mode, where a failed test fails the run.
+    fn report_tests(&mut self) {
+        let Some(results) = self.test_results() else {
+            return;
+        };
+
+        let mut state = self.state.borrow_mut();
+        writeln!(state.out, "{results}");
+        if results.failed > 0 {
+            state.had_runtime_error = true;
+        }
+    }
+
+    fn exit_on_error(&self) {
         if self.state.borrow().had_error {
             std::process::exit(SYNTAX_ERROR);
         }
@@ -178,16 +486,37 @@ impl Lox {
         if self.state.borrow().had_runtime_error {
             std::process::exit(RUNTIME_ERROR)
         }
-
-        Ok(())
     }
 
     pub fn runtime_error(mut state: RefMut<LoxState>, err: Exception) {
-        eprintln!("{err}");
+        writeln!(state.err, "{err}");
         state.had_runtime_error = true;
     }
 }
 
+/// The flag the SIGINT handler sets. Signal handlers can't capture state,
+/// so it lives here.
+#[cfg(feature = "fancy-repl")]
+static SIGINT_FLAG: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+
+/// Makes SIGINT set `flag` instead of killing the process. Only the first
+/// flag registered is used.
+#[cfg(feature = "fancy-repl")]
+fn interrupt_on_sigint(flag: Arc<AtomicBool>) {
+    extern "C" fn handle(_: libc::c_int) {
+        if let Some(flag) = SIGINT_FLAG.get() {
+            flag.store(true, Ordering::Relaxed);
+        }
+    }
+
+    if SIGINT_FLAG.set(flag).is_ok() {
+        // SAFETY: the handler only performs an atomic load and store.
+        unsafe {
+            libc::signal(libc::SIGINT, handle as *const () as libc::sighandler_t);
+        }
+    }
+}
+
 impl Default for Lox {
     fn default() -> Self {
         Self::new()
@@ -1,22 +1,113 @@
-use common::exit::TOO_MANY_ARGS;
-use treewalk::lox::Lox;
+use std::fs::read_to_string;
+use std::io::IsTerminal;
+
+use common::cli::{Args, Emit};
+use common::exit::{SYNTAX_ERROR, TOO_MANY_ARGS};
+use treewalk::lox::{Lox, ReplConfig};
+use treewalk::scanner::Scanner;
+
+const USAGE: &str = "Usage: treewalk [--trace] [--strict] [--test] [--check] [--emit=run|tokens|ast] [script | -] [args...]";
+
+fn usage() -> ! {
+    eprintln!("{USAGE}");
+    std::process::exit(TOO_MANY_ARGS);
+}
+
+fn version() -> String {
+    let repl = if cfg!(feature = "fancy-repl") {
+        "fancy-repl"
+    } else {
+        "basic repl"
+    };
+
+    format!("treewalk {} ({repl})", env!("CARGO_PKG_VERSION"))
+}
+
+fn emit_tokens(source: &str) {
+    let (tokens, errors) = Scanner::new(source).scan_tokens();
+
+    for token in tokens {
+        println!("{token}");
+    }
+
+    for error in &errors {
+        eprintln!("{}", error.to_diagnostic());
+    }
+    if !errors.is_empty() {
+        std::process::exit(SYNTAX_ERROR);
+    }
+}
+
+fn emit_ast(source: &str) {
+    let (statements, diagnostics) = treewalk::parse_program(source);
+
+    for stmt in statements {
+        println!("{stmt:#?}");
+    }
+
+    for diagnostic in &diagnostics {
+        eprintln!("{diagnostic}");
+    }
+    if !diagnostics.is_empty() {
+        std::process::exit(SYNTAX_ERROR);
+    }
+}
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let mut args = std::env::args().skip(1);
-    let len = args.len();
+    let args = Args::parse(std::env::args().skip(1)).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        usage();
+    });
 
-    if len > 1 {
-        eprintln!("Usage: treewalk [script]");
-        std::process::exit(TOO_MANY_ARGS);
+    // These answer and exit before anything else is looked at.
+    if args.help {
+        println!("{USAGE}");
+        return Ok(());
+    }
+    if args.version {
+        println!("{}", version());
+        return Ok(());
     }
 
     let mut lox = Lox::new();
+    lox.set_repl_config(ReplConfig::from_env());
 
-    if len == 1 {
-        let path = args.next().unwrap();
-        lox.run_file(&path)?;
-    } else {
-        lox.run_prompt()?;
+    for flag in &args.flags {
+        match flag.as_str() {
+            "--trace" => lox.set_trace(true),
+            "--strict" => lox.set_strict(true),
+            "--test" => lox.set_testing(true),
+            _ => usage(),
+        }
+    }
+
+    lox.set_args(args.script_args);
+
+    if args.emit == Emit::Run && !args.check {
+        // A script piped in is run whole rather than line by line.
+        match args.script.as_deref() {
+            Some("-") => lox.run_stdin()?,
+            Some(path) => lox.run_file(path)?,
+            None if !std::io::stdin().is_terminal() => lox.run_stdin()?,
+            None => lox.run_prompt()?,
+        }
+
+        return Ok(());
+    }
+
+    let source = match args.script.as_deref() {
+        Some("-") | None => std::io::read_to_string(std::io::stdin())?,
+        Some(path) => read_to_string(path)?,
+    };
+
+    match args.emit {
+        Emit::Run => lox.check(&source),
+        Emit::Tokens => emit_tokens(&source),
+        Emit::Ast => emit_ast(&source),
+        Emit::Bytecode => {
+            eprintln!("treewalk has no bytecode to emit.");
+            usage();
+        }
     }
 
     Ok(())
@@ -1,16 +1,50 @@
+use std::cell::RefCell;
 use std::fmt::Display;
+use std::hash::Hash;
+use std::rc::Rc;
 
+use indexmap::IndexMap;
 use ordered_float::OrderedFloat;
 
+use crate::class::{LoxClass, LoxInstance};
+use crate::environment::Environment;
+use crate::error::ConversionError;
 use crate::function::{Function, LoxFunction, NativeFn};
+use crate::generator::Generator;
 
 #[derive(Debug, Clone)]
 pub enum Object {
     Nil,
     String(String),
     Number(OrderedFloat<f64>),
+    /// An integer literal, or arithmetic on them. Mixed with a `Number` it's
+    /// promoted to a float, and `/` always gives one.
+    #[cfg(feature = "integers")]
+    Int(i64),
     Boolean(bool),
     Fn(Function),
+    Class(Rc<LoxClass>),
+    Instance(Rc<RefCell<LoxInstance>>),
+    List(Rc<RefCell<Vec<Object>>>),
+    /// Iterated, and so displayed, in insertion order.
+    Map(Rc<RefCell<IndexMap<Object, Object>>>),
+    Module(Rc<Module>),
+    Generator(Rc<RefCell<Generator>>),
+    /// A string that grows in place, so building one piece by piece is
+    /// linear rather than quadratic as with `+`.
+    StringBuilder(Rc<RefCell<String>>),
+    /// Stored by `var x;` in strict mode until the first assignment, and as
+    /// `this` in a method reached through its class. Reading it is an error,
+    /// so it never reaches user code, though embedders walking an environment
+    /// may see it.
+    Uninitialized,
+}
+
+/// The namespace produced by `import "path" as name;`.
+#[derive(Debug)]
+pub struct Module {
+    pub name: String,
+    pub environment: Rc<RefCell<Environment>>,
 }
 
 impl Object {
@@ -22,19 +56,157 @@ impl Object {
             _ => true,
         }
     }
+
+    /// What kind of value this is, as error messages name it.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Object::Nil => "nil",
+            Object::String(_) => "string",
+            Object::Number(_) => "number",
+            #[cfg(feature = "integers")]
+            Object::Int(_) => "number",
+            Object::Boolean(_) => "boolean",
+            Object::Fn(_) => "function",
+            Object::Class(_) => "class",
+            Object::Instance(_) => "instance",
+            Object::List(_) => "list",
+            Object::Map(_) => "map",
+            Object::Module(_) => "module",
+            Object::Generator(_) => "generator",
+            Object::StringBuilder(_) => "string builder",
+            Object::Uninitialized => "uninitialized",
+        }
+    }
+
+    /// Whether the value can be called: functions, natives and classes.
+    pub fn is_callable(&self) -> bool {
+        matches!(self, Object::Fn(_) | Object::Class(_))
+    }
+
+    /// The value of either kind of number as a float.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Object::Number(x) => Some(x.0),
+            #[cfg(feature = "integers")]
+            Object::Int(x) => Some(*x as f64),
+
+            _ => None,
+        }
+    }
+
+    /// Renders the value as source that evaluates back to an equal value.
+    ///
+    /// Only nil, booleans, finite numbers, strings without a `"`, and lists
+    /// and maps of those round-trip; Lox has no literal syntax for anything
+    /// else, so those fall back to their `Display` form (e.g. `<fn name>`).
+    /// As with `Display`, a list or map that contains itself is cut short
+    /// with `[...]` or `{...}`.
+    pub fn repr(&self) -> String {
+        struct Repr<'a>(&'a Object);
+
+        impl Display for Repr<'_> {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                self.0.fmt_nested(f, &mut vec![], |x, f| match x {
+                    Object::String(value) => write!(f, "\"{value}\""),
+
+                    x => write!(f, "{x}"),
+                })
+            }
+        }
+
+        Repr(self).to_string()
+    }
+
+    /// Writes lists and maps element by element, and everything else with
+    /// `leaf`. `path` holds the containers currently being written, so one
+    /// that contains itself is shown as `[...]` or `{...}` instead of
+    /// recursing forever.
+    fn fmt_nested(
+        &self,
+        f: &mut std::fmt::Formatter,
+        path: &mut Vec<*const ()>,
+        leaf: fn(&Object, &mut std::fmt::Formatter) -> std::fmt::Result,
+    ) -> std::fmt::Result {
+        match self {
+            Object::List(list) => {
+                let ptr = Rc::as_ptr(list).cast();
+                if path.contains(&ptr) {
+                    return write!(f, "[...]");
+                }
+
+                path.push(ptr);
+                write!(f, "[")?;
+                for (i, element) in list.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    element.fmt_nested(f, path, leaf)?;
+                }
+                path.pop();
+
+                write!(f, "]")
+            }
+            Object::Map(map) => {
+                let ptr = Rc::as_ptr(map).cast();
+                if path.contains(&ptr) {
+                    return write!(f, "{{...}}");
+                }
+
+                path.push(ptr);
+                write!(f, "{{")?;
+                for (i, (key, value)) in map.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    key.fmt_nested(f, path, leaf)?;
+                    write!(f, ": ")?;
+                    value.fmt_nested(f, path, leaf)?;
+                }
+                path.pop();
+
+                write!(f, "}}")
+            }
+
+            x => leaf(x, f),
+        }
+    }
+
+    /// Whether the value can be used as a map key. See the `Hash` impl.
+    pub fn is_hashable(&self) -> bool {
+        match self {
+            Object::Nil | Object::Boolean(_) | Object::Number(_) | Object::String(_) => true,
+            #[cfg(feature = "integers")]
+            Object::Int(_) => true,
+
+            _ => false,
+        }
+    }
 }
 
 impl Display for Object {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        let repr = match self {
-            Object::Nil => "nil",
-            Object::String(value) => value,
-            Object::Number(x) => &x.to_string(),
-            Object::Boolean(x) => &x.to_string(),
-            Object::Fn(fun) => &fun.to_string(),
-        };
+        self.fmt_nested(f, &mut vec![], |x, f| {
+            let repr = match x {
+                Object::Nil => "nil",
+                Object::String(value) => value,
+                Object::Number(x) => &x.to_string(),
+                #[cfg(feature = "integers")]
+                Object::Int(x) => &x.to_string(),
+                Object::Boolean(x) => &x.to_string(),
+                Object::Fn(fun) => &fun.to_string(),
+                Object::Class(class) => &format!("<class {}>", class.name),
+                Object::Instance(instance) => {
+                    &format!("<instance of {}>", instance.borrow().class.name)
+                }
+                Object::List(_) | Object::Map(_) => unreachable!("written by fmt_nested"),
+                Object::Module(module) => &format!("<module {}>", module.name),
+                Object::Generator(generator) => &format!("<generator {}>", generator.borrow().name),
+                Object::StringBuilder(_) => "<string builder>",
+                Object::Uninitialized => "<uninitialized>",
+            };
 
-        write!(f, "{repr}")
+            write!(f, "{repr}")
+        })
     }
 }
 
@@ -44,6 +216,12 @@ impl From<&str> for Object {
     }
 }
 
+impl From<String> for Object {
+    fn from(value: String) -> Self {
+        Object::String(value)
+    }
+}
+
 impl From<f64> for Object {
     fn from(value: f64) -> Self {
         Object::Number(OrderedFloat(value))
@@ -56,12 +234,40 @@ impl From<OrderedFloat<f64>> for Object {
     }
 }
 
+impl From<i32> for Object {
+    fn from(value: i32) -> Self {
+        Object::from(f64::from(value))
+    }
+}
+
+/// Lox numbers are doubles, so integers beyond 2^53 lose precision.
+impl From<i64> for Object {
+    fn from(value: i64) -> Self {
+        Object::from(value as f64)
+    }
+}
+
 impl From<bool> for Object {
     fn from(value: bool) -> Self {
         Object::Boolean(value)
     }
 }
 
+impl From<Vec<Object>> for Object {
+    fn from(value: Vec<Object>) -> Self {
+        Object::List(Rc::new(RefCell::new(value)))
+    }
+}
+
+impl<T> From<Option<T>> for Object
+where
+    Object: From<T>,
+{
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Object::Nil, Object::from)
+    }
+}
+
 impl From<NativeFn> for Object {
     fn from(value: NativeFn) -> Self {
         Object::Fn(Function::Native(value))
@@ -70,10 +276,91 @@ impl From<NativeFn> for Object {
 
 impl From<LoxFunction> for Object {
     fn from(value: LoxFunction) -> Self {
-        Object::Fn(Function::Lox(value))
+        Object::Fn(Function::Lox(Rc::new(value)))
+    }
+}
+
+impl TryFrom<Object> for String {
+    type Error = ConversionError;
+
+    fn try_from(value: Object) -> Result<Self, Self::Error> {
+        match value {
+            Object::String(value) => Ok(value),
+            found => Err(ConversionError {
+                expected: "a string",
+                found,
+            }),
+        }
+    }
+}
+
+impl TryFrom<Object> for f64 {
+    type Error = ConversionError;
+
+    fn try_from(value: Object) -> Result<Self, Self::Error> {
+        match value.as_f64() {
+            Some(value) => Ok(value),
+            None => Err(ConversionError {
+                expected: "a number",
+                found: value,
+            }),
+        }
+    }
+}
+
+/// Only numbers with no fractional part that fit in an `i64` convert.
+impl TryFrom<Object> for i64 {
+    type Error = ConversionError;
+
+    fn try_from(value: Object) -> Result<Self, Self::Error> {
+        match value {
+            #[cfg(feature = "integers")]
+            Object::Int(x) => Ok(x),
+            Object::Number(x) if let Some(int) = exact_int(x.0) => Ok(int),
+            found => Err(ConversionError {
+                expected: "an integer",
+                found,
+            }),
+        }
     }
 }
 
+impl TryFrom<Object> for bool {
+    type Error = ConversionError;
+
+    fn try_from(value: Object) -> Result<Self, Self::Error> {
+        match value {
+            Object::Boolean(value) => Ok(value),
+            found => Err(ConversionError {
+                expected: "a boolean",
+                found,
+            }),
+        }
+    }
+}
+
+/// Copies the list's elements, so later changes to either side aren't
+/// shared.
+impl TryFrom<Object> for Vec<Object> {
+    type Error = ConversionError;
+
+    fn try_from(value: Object) -> Result<Self, Self::Error> {
+        match value {
+            Object::List(list) => Ok(list.borrow().clone()),
+            found => Err(ConversionError {
+                expected: "a list",
+                found,
+            }),
+        }
+    }
+}
+
+/// The integer `x` holds exactly, if it has no fractional part and fits in
+/// an `i64`.
+fn exact_int(x: f64) -> Option<i64> {
+    (x.fract() == 0.0 && (i64::MIN as f64..i64::MAX as f64).contains(&x)).then_some(x as i64)
+}
+
 impl PartialEq for Object {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
@@ -82,9 +369,55 @@ impl PartialEq for Object {
 
             (Object::String(lhs), Object::String(rhs)) => lhs == rhs,
             (Object::Number(lhs), Object::Number(rhs)) => lhs == rhs,
+            #[cfg(feature = "integers")]
+            (Object::Int(lhs), Object::Int(rhs)) => lhs == rhs,
+            #[cfg(feature = "integers")]
+            (Object::Int(int), Object::Number(x)) | (Object::Number(x), Object::Int(int)) => {
+                exact_int(x.0) == Some(*int)
+            }
             (Object::Boolean(lhs), Object::Boolean(rhs)) => lhs == rhs,
+            (Object::Fn(lhs), Object::Fn(rhs)) => lhs == rhs,
+            (Object::Class(lhs), Object::Class(rhs)) => Rc::ptr_eq(lhs, rhs),
+            (Object::Instance(lhs), Object::Instance(rhs)) => Rc::ptr_eq(lhs, rhs),
+            (Object::List(lhs), Object::List(rhs)) => Rc::ptr_eq(lhs, rhs),
+            (Object::Map(lhs), Object::Map(rhs)) => Rc::ptr_eq(lhs, rhs),
+            (Object::Module(lhs), Object::Module(rhs)) => Rc::ptr_eq(lhs, rhs),
+            (Object::Generator(lhs), Object::Generator(rhs)) => Rc::ptr_eq(lhs, rhs),
+            (Object::StringBuilder(lhs), Object::StringBuilder(rhs)) => Rc::ptr_eq(lhs, rhs),
 
             _ => false,
         }
     }
 }
+
+impl Eq for Object {}
+
+/// Only `nil`, booleans, numbers and strings compare by value, so only they
+/// are hashable (see [`Object::is_hashable`]) and usable as map keys; maps
+/// raise a runtime error for anything else. Other variants compare by
+/// identity and hash by variant alone, which keeps `Hash` consistent with
+/// `Eq` without ever being relied on for lookups.
+impl Hash for Object {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        // An integer equals a float holding exactly its value, so the two
+        // must hash alike.
+        #[cfg(feature = "integers")]
+        if let Some(int) = match self {
+            Object::Int(x) => Some(*x),
+            Object::Number(x) => exact_int(x.0),
+            _ => None,
+        } {
+            return int.hash(state);
+        }
+
+        std::mem::discriminant(self).hash(state);
+
+        match self {
+            Object::Boolean(x) => x.hash(state),
+            Object::Number(x) => x.hash(state),
+            Object::String(x) => x.hash(state),
+
+            _ => (),
+        }
+    }
+}
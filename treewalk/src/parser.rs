@@ -1,46 +1,43 @@
-use std::cell::RefCell;
-use std::rc::Rc;
-
 use crate::error::ParseError;
-use crate::expr::{Expr, ExprData};
-use crate::lox::{Lox, LoxState, MAX_ARGS};
-use crate::stmt::Stmt;
-use crate::token::{Token, TokenType};
+use crate::expr::{Expr, ExprData, MatchArm, Pattern};
+use crate::lox::MAX_ARGS;
+use crate::object::Object;
+use crate::stmt::{Clause, Contract, Stmt};
+use crate::token::{Precedence, Token, TokenType};
 
 pub struct Parser {
-    state: Rc<RefCell<LoxState>>,
     tokens: Vec<Token>,
     current: usize,
-}
-
-macro_rules! rule {
-    ($kind:tt$(| $kinds:tt)* => $name:ident($next:ident) -> $expr:tt) => {
-        fn $name(&mut self) -> Result<Expr, ParseError> {
-            let mut expr = self.$next()?;
-
-            while self.catch(&[TokenType::$kind$(, TokenType::$kinds)*]) {
-                let op = self.previous().clone();
-                let rhs = self.$next()?.into();
-                expr = Expr::new(ExprData::$expr{ op, lhs: expr.into(), rhs });
-            }
-
-            Ok(expr)
-        }
-    };
-    ($kind:tt$(| $kinds:tt)* => $name:ident($next:ident)) => {
-        rule!($kind$(| $kinds)* => $name($next) -> Binary);
-    };
+    errors: Vec<ParseError>,
+    /// Whether a `;` or `}` missing at the end of input is inserted, with a
+    /// warning, rather than being an error.
+    lenient: bool,
+    warnings: Vec<ParseError>,
 }
 
 impl Parser {
-    pub fn new(state: Rc<RefCell<LoxState>>, tokens: Vec<Token>) -> Self {
+    pub fn new(tokens: Vec<Token>) -> Self {
         Parser {
-            state,
             tokens,
             current: 0,
+            errors: vec![],
+            lenient: false,
+            warnings: vec![],
         }
     }
 
+    /// Inserts a `;` or `}` missing at the end of input, so `print 1` parses
+    /// on its own. Off by default.
+    pub fn set_lenient(&mut self, lenient: bool) {
+        self.lenient = lenient;
+    }
+
+    /// The warnings for tokens inserted in lenient mode since they were last
+    /// taken.
+    pub fn take_warnings(&mut self) -> Vec<ParseError> {
+        std::mem::take(&mut self.warnings)
+    }
+
     fn previous(&self) -> &Token {
         &self.tokens[self.current - 1]
     }
@@ -81,31 +78,38 @@ impl Parser {
     }
 
     fn error(&self, token: &Token, message: &str) -> ParseError {
-        Lox::error_at(self.state.borrow_mut(), token, message);
-        ParseError
+        ParseError {
+            token: token.clone(),
+            message: message.to_owned(),
+        }
+    }
+
+    /// Records an error that doesn't stop the current rule from parsing.
+    fn report(&mut self, token: &Token, message: &str) {
+        let error = self.error(token, message);
+        self.errors.push(error);
+    }
+
+    /// Whether the next token can only begin a new statement.
+    fn at_statement_start(&self) -> bool {
+        self.peek().kind.is_statement_start()
     }
 
     fn synchronize(&mut self) {
+        // Statement keywords are consumed before anything can go wrong in
+        // them, so an error reported at one means the previous statement was
+        // cut short. Skipping it would lose the statement it begins.
+        if self.at_statement_start() {
+            return;
+        }
+
         self.advance();
 
         while !self.is_at_end() {
-            if self.previous().kind == TokenType::Semicolon {
+            if self.previous().kind == TokenType::Semicolon || self.at_statement_start() {
                 return;
             }
 
-            match self.peek().kind {
-                TokenType::Class
-                | TokenType::Fun
-                | TokenType::Var
-                | TokenType::For
-                | TokenType::If
-                | TokenType::While
-                | TokenType::Print
-                | TokenType::Return => return,
-
-                _ => (),
-            }
-
             self.advance();
         }
     }
@@ -115,35 +119,132 @@ impl Parser {
             return Ok(self.advance());
         }
 
+        if self.lenient
+            && self.is_at_end()
+            && let Some(missing) = match kind {
+                TokenType::Semicolon => Some(';'),
+                TokenType::RightBrace => Some('}'),
+                _ => None,
+            }
+        {
+            let warning = self.error(self.peek(), &format!("Inserted missing '{missing}'."));
+            self.warnings.push(warning);
+
+            return Ok(self.peek());
+        }
+
         Err(self.error(self.peek(), message))
     }
 
     fn primary(&mut self) -> Result<Expr, ParseError> {
         use TokenType as TT;
 
+        // Literals and lists have no token to take their line from.
+        let line = self.peek().line;
+
         if self.catch(&[TT::False]) {
-            return Ok(Expr::literal(false));
+            return Ok(Expr::literal(false).at_line(line));
         }
 
         if self.catch(&[TT::True]) {
-            return Ok(Expr::literal(true));
+            return Ok(Expr::literal(true).at_line(line));
         }
 
         if self.catch(&[TT::Nil]) {
-            return Ok(Expr::nil());
+            return Ok(Expr::nil().at_line(line));
         }
 
         if self.catch(&[TT::Number, TT::String]) {
-            return Ok(Expr::literal(self.previous().literal.as_ref().clone()));
+            let value = self.previous().literal.as_ref().clone();
+
+            return Ok(Expr::literal(value).at_line(line));
+        }
+
+        if self.at_arrow_function() {
+            return self.arrow_function();
         }
 
         if self.catch(&[TT::LeftParen]) {
             let expr = self.expression()?;
+
+            // A tuple, `(a, b)`, is a list by another name.
+            if self.catch(&[TT::Comma]) {
+                let mut elements = vec![expr];
+                loop {
+                    elements.push(self.expression()?);
+
+                    if !self.catch(&[TT::Comma]) {
+                        break;
+                    }
+                }
+                self.consume(TT::RightParen, "Expect ')' after tuple elements.")?;
+
+                return Ok(Expr::list(elements).at_line(line));
+            }
+
             self.consume(TT::RightParen, "Expect ')' after expression.")?;
 
             return Ok(Expr::grouping(expr));
         }
 
+        if self.catch(&[TT::LeftBracket]) {
+            let mut elements = vec![];
+
+            if !self.check(TT::RightBracket) {
+                loop {
+                    elements.push(self.expression()?);
+
+                    if !self.catch(&[TT::Comma]) {
+                        break;
+                    }
+                }
+            }
+
+            self.consume(TT::RightBracket, "Expect ']' after list elements.")?;
+
+            return Ok(Expr::list(elements).at_line(line));
+        }
+
+        if self.catch(&[TT::LeftBrace]) {
+            let brace = self.previous().clone();
+            let mut entries = vec![];
+
+            if !self.check(TT::RightBrace) {
+                loop {
+                    let key = self.expression()?;
+                    self.consume(TT::Colon, "Expect ':' after map key.")?;
+                    let value = self.expression()?;
+                    entries.push((key, value));
+
+                    if !self.catch(&[TT::Comma]) {
+                        break;
+                    }
+                }
+            }
+
+            self.consume(TT::RightBrace, "Expect '}' after map entries.")?;
+
+            return Ok(Expr::map(brace, entries));
+        }
+
+        if self.catch(&[TT::Super]) {
+            let keyword = self.previous().clone();
+            self.consume(TT::Dot, "Expect '.' after 'super'.")?;
+            let method = self
+                .consume(TT::Identifier, "Expect superclass method name.")?
+                .clone();
+
+            return Ok(Expr::super_(keyword, method));
+        }
+
+        if self.catch(&[TT::This]) {
+            return Ok(Expr::this(self.previous().clone()));
+        }
+
+        if self.catch(&[TT::Match]) {
+            return self.match_expression();
+        }
+
         if self.catch(&[TT::Identifier]) {
             let name = self.previous().clone();
 
@@ -153,18 +254,156 @@ impl Parser {
         Err(self.error(self.peek(), "Expect expression."))
     }
 
+    fn match_expression(&mut self) -> Result<Expr, ParseError> {
+        let keyword = self.previous().clone();
+        let scrutinee = self.expression()?;
+        self.consume(TokenType::LeftBrace, "Expect '{' after match value.")?;
+
+        let mut arms = vec![];
+        while !self.check(TokenType::RightBrace) && !self.is_at_end() {
+            let pattern = self.pattern()?;
+            self.consume(TokenType::Arrow, "Expect '=>' after pattern.")?;
+            let body = self.expression()?;
+            arms.push(MatchArm { pattern, body });
+
+            if !self.catch(&[TokenType::Comma]) {
+                break;
+            }
+        }
+        self.consume(TokenType::RightBrace, "Expect '}' after match arms.")?;
+
+        Ok(Expr::match_(keyword, scrutinee, arms))
+    }
+
+    fn pattern(&mut self) -> Result<Pattern, ParseError> {
+        if self.catch(&[TokenType::False]) {
+            return Ok(Pattern::Literal(false.into()));
+        }
+
+        if self.catch(&[TokenType::True]) {
+            return Ok(Pattern::Literal(true.into()));
+        }
+
+        if self.catch(&[TokenType::Nil]) {
+            return Ok(Pattern::Literal(Object::Nil));
+        }
+
+        if self.catch(&[TokenType::Number, TokenType::String]) {
+            return Ok(Pattern::Literal(self.previous().literal.as_ref().clone()));
+        }
+
+        if self.catch(&[TokenType::Identifier]) {
+            let name = self.previous().clone();
+            if name.lexeme == "_" {
+                return Ok(Pattern::Wildcard);
+            }
+
+            return Ok(Pattern::Binding(name));
+        }
+
+        Err(self.error(self.peek(), "Expect pattern."))
+    }
+
+    /// Whether the next tokens are `identifier =>` or a parenthesized list of
+    /// identifiers followed by `=>`, starting an arrow function rather than a
+    /// variable or grouping.
+    fn at_arrow_function(&self) -> bool {
+        let kind_at = |offset: usize| self.tokens.get(self.current + offset).map(|t| t.kind);
+
+        if self.check(TokenType::Identifier) {
+            return kind_at(1) == Some(TokenType::Arrow);
+        }
+        if !self.check(TokenType::LeftParen) {
+            return false;
+        }
+
+        let mut offset = 1;
+        while matches!(
+            kind_at(offset),
+            Some(TokenType::Identifier | TokenType::Comma)
+        ) {
+            offset += 1;
+        }
+
+        kind_at(offset) == Some(TokenType::RightParen)
+            && kind_at(offset + 1) == Some(TokenType::Arrow)
+    }
+
+    fn arrow_function(&mut self) -> Result<Expr, ParseError> {
+        let mut parameters = vec![];
+
+        if self.catch(&[TokenType::LeftParen]) {
+            if !self.check(TokenType::RightParen) {
+                loop {
+                    if parameters.len() >= MAX_ARGS {
+                        self.report(
+                            &self.peek().clone(),
+                            &format!("Can't have more than {MAX_ARGS} parameters."),
+                        );
+                    }
+
+                    parameters.push(
+                        self.consume(TokenType::Identifier, "Expect parameter name.")?
+                            .clone(),
+                    );
+
+                    if !self.catch(&[TokenType::Comma]) {
+                        break;
+                    }
+                }
+            }
+            self.consume(TokenType::RightParen, "Expect ')' after parameters.")?;
+        } else {
+            parameters.push(
+                self.consume(TokenType::Identifier, "Expect parameter name.")?
+                    .clone(),
+            );
+        }
+
+        let arrow = self
+            .consume(TokenType::Arrow, "Expect '=>' after parameters.")?
+            .clone();
+        let body = self.expression()?;
+
+        Ok(Expr::lambda(arrow, parameters, body))
+    }
+
+    /// Whether the next tokens are `identifier :`, starting a keyword
+    /// argument.
+    fn at_keyword_argument(&self) -> bool {
+        self.check(TokenType::Identifier)
+            && self
+                .tokens
+                .get(self.current + 1)
+                .is_some_and(|token| token.kind == TokenType::Colon)
+    }
+
     fn finish_call(&mut self, callee: Expr) -> Result<Expr, ParseError> {
         let mut arguments = vec![];
+        let mut keywords = vec![];
 
         if !self.check(TokenType::RightParen) {
             loop {
-                if arguments.len() >= MAX_ARGS {
-                    self.error(
-                        self.peek(),
+                if arguments.len() + keywords.len() >= MAX_ARGS {
+                    self.report(
+                        &self.peek().clone(),
                         &format!("Can't have more than {MAX_ARGS} arguments."),
                     );
                 }
-                arguments.push(self.expression()?);
+
+                if self.at_keyword_argument() {
+                    let name = self.advance().clone();
+                    self.advance();
+                    keywords.push((name, self.expression()?));
+                } else {
+                    if !keywords.is_empty() {
+                        self.report(
+                            &self.peek().clone(),
+                            "Positional arguments must come before keyword arguments.",
+                        );
+                    }
+                    arguments.push(self.expression()?);
+                }
 
                 if !self.catch(&[TokenType::Comma]) {
                     break;
@@ -176,7 +415,7 @@ impl Parser {
             .consume(TokenType::RightParen, "Expect ')' after arguments.")?
             .clone();
 
-        Ok(Expr::call(callee, paren, arguments))
+        Ok(Expr::call(callee, paren, arguments, keywords))
     }
 
     fn call(&mut self) -> Result<Expr, ParseError> {
@@ -185,6 +424,17 @@ impl Parser {
         loop {
             if self.catch(&[TokenType::LeftParen]) {
                 expr = self.finish_call(expr)?;
+            } else if self.catch(&[TokenType::LeftBracket]) {
+                let index = self.expression()?;
+                let bracket = self
+                    .consume(TokenType::RightBracket, "Expect ']' after index.")?
+                    .clone();
+                expr = Expr::index(expr, bracket, index);
+            } else if self.catch(&[TokenType::Dot]) {
+                let name = self
+                    .consume(TokenType::Identifier, "Expect property name after '.'.")?
+                    .clone();
+                expr = Expr::get(expr, name);
             } else {
                 break;
             }
@@ -204,16 +454,28 @@ impl Parser {
         }
     }
 
-    rule!(Slash | Star => factor(unary));
-    rule!(Minus | Plus => term(factor));
-    rule!(Greater | GreaterEqual | Less | LessEqual => comparison(term));
-    rule!(BangEqual | EqualEqual => equality(comparison));
+    /// Parses operators that bind tighter than `above`, by precedence
+    /// climbing over [`TokenType::precedence`]. Each operator's right operand
+    /// may only hold tighter operators, which makes them all left-associative.
+    fn binary(&mut self, above: Precedence) -> Result<Expr, ParseError> {
+        let mut expr = self.unary()?;
+
+        while self.peek().kind.precedence() > above {
+            let op = self.advance().clone();
+            let rhs = self.binary(op.kind.precedence())?;
+
+            expr = if op.kind.is_binary_operator() {
+                Expr::binary(op, expr, rhs)
+            } else {
+                Expr::logical(op, expr, rhs)
+            };
+        }
 
-    rule!(And => and(equality) -> Logical);
-    rule!(Or => or(and) -> Logical);
+        Ok(expr)
+    }
 
     fn assignment(&mut self) -> Result<Expr, ParseError> {
-        let expr = self.or()?;
+        let expr = self.binary(Precedence::None)?;
 
         if self.catch(&[TokenType::Equal]) {
             let equals = self.previous().clone();
@@ -225,7 +487,50 @@ impl Parser {
                 return Ok(Expr::assign(name, value));
             }
 
-            self.error(&equals, "Invalid assignment target.");
+            if let ExprData::Get { object, name } = &expr.data {
+                return Ok(Expr::set(object.as_ref().clone(), name.clone(), value));
+            }
+
+            if let ExprData::Index {
+                object,
+                bracket,
+                index,
+            } = &expr.data
+            {
+                let (object, index) = (object.as_ref().clone(), index.as_ref().clone());
+
+                return Ok(Expr::set_index(object, bracket.clone(), index, value));
+            }
+
+            if let ExprData::List { elements } = &expr.data
+                && elements
+                    .iter()
+                    .all(|element| matches!(element.data, ExprData::Variable { .. }))
+            {
+                return Ok(Expr::destructure(equals, elements.clone(), value));
+            }
+
+            self.report(&equals, "Invalid assignment target.");
+        }
+
+        if self.catch(&[
+            TokenType::QuestionQuestionEqual,
+            TokenType::AmpAmpEqual,
+            TokenType::PipePipeEqual,
+        ]) {
+            let op = self.previous().clone();
+            let value = self.assignment()?;
+
+            // Reading a variable has no side effects, so `x ||= y` can be
+            // `x || (x = y)` without changing what runs. Other targets would
+            // evaluate their object twice.
+            if let ExprData::Variable { name } = &expr.data {
+                let assign = Expr::assign(name.clone(), value);
+
+                return Ok(Expr::logical(op, expr, assign));
+            }
+
+            self.report(&op, "Invalid assignment target.");
         }
 
         Ok(expr)
@@ -235,6 +540,33 @@ impl Parser {
         self.assignment()
     }
 
+    fn import_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().clone();
+        let path = self
+            .consume(TokenType::String, "Expect path string after 'import'.")?
+            .clone();
+
+        // `as` is contextual so it stays usable as an identifier elsewhere.
+        let alias = if self.check(TokenType::Identifier) && self.peek().lexeme == "as" {
+            self.advance();
+            let alias = self
+                .consume(TokenType::Identifier, "Expect module name after 'as'.")?
+                .clone();
+
+            Some(alias)
+        } else {
+            None
+        };
+
+        self.consume(TokenType::Semicolon, "Expect ';' after import.")?;
+
+        Ok(Stmt::Import {
+            keyword,
+            path,
+            alias,
+        })
+    }
+
     fn print_statement(&mut self) -> Result<Stmt, ParseError> {
         let expr = self.expression()?;
 
@@ -291,7 +623,47 @@ impl Parser {
         self.consume(TokenType::RightParen, "Expect ')' after condition.")?;
         let body = self.statement()?.into();
 
-        Ok(Stmt::While { condition, body })
+        Ok(Stmt::While {
+            condition,
+            body,
+            increment: None,
+            rebinds: None,
+        })
+    }
+
+    fn test_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().clone();
+        let name = self
+            .consume(TokenType::String, "Expect test name after 'test'.")?
+            .clone();
+        self.consume(TokenType::LeftBrace, "Expect '{' before test body.")?;
+        let body = self.block()?;
+
+        Ok(Stmt::Test {
+            keyword,
+            name,
+            body,
+        })
+    }
+
+    fn with_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().clone();
+        self.consume(TokenType::LeftParen, "Expect '(' after 'with'.")?;
+        self.consume(TokenType::Var, "Expect 'var' after '('.")?;
+        let binding = self
+            .consume(TokenType::Identifier, "Expect variable name.")?
+            .clone();
+        self.consume(TokenType::Equal, "Expect '=' after variable name.")?;
+        let initializer = self.expression()?;
+        self.consume(TokenType::RightParen, "Expect ')' after resource.")?;
+        let body = self.statement()?.into();
+
+        Ok(Stmt::With {
+            keyword,
+            binding,
+            initializer,
+            body,
+        })
     }
 
     fn for_statement(&mut self) -> Result<Stmt, ParseError> {
@@ -319,23 +691,22 @@ impl Parser {
         };
         self.consume(TokenType::RightParen, "Expect ')' after for clauses.")?;
 
-        let body = self.statement()?;
-
-        let body = match increment {
-            Some(increment) => Stmt::Block {
-                statements: vec![body, Stmt::Expr { expr: increment }],
-            },
-            None => body,
-        };
+        let body = self.statement()?.into();
 
         let condition = condition.unwrap_or(Expr::literal(true));
-        let body = body.into();
-        let body = Stmt::While { condition, body };
+        let rebinds = match &initializer {
+            Some(Stmt::Var { name, .. }) => Some(name.clone()),
+            _ => None,
+        };
+        let body = Stmt::While {
+            condition,
+            body,
+            increment,
+            rebinds,
+        };
 
         let body = if let Some(initializer) = initializer {
-            Stmt::Block {
-                statements: vec![initializer, body],
-            }
+            Stmt::block(vec![initializer, body])
         } else {
             body
         };
@@ -355,10 +726,40 @@ impl Parser {
         Ok(Stmt::Return { keyword, expr })
     }
 
+    fn defer_statement(&mut self) -> Result<Stmt, ParseError> {
+        let expr = self.expression()?;
+        self.consume(
+            TokenType::Semicolon,
+            "Expect ';' after deferred expression.",
+        )?;
+
+        Ok(Stmt::Defer { expr })
+    }
+
+    fn yield_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().clone();
+        let expr = self.expression()?;
+        self.consume(TokenType::Semicolon, "Expect ';' after yield value.")?;
+
+        Ok(Stmt::Yield { keyword, expr })
+    }
+
     fn statement(&mut self) -> Result<Stmt, ParseError> {
         if self.catch(&[TokenType::Return]) {
             return self.return_statement();
         }
+        if self.catch(&[TokenType::Yield]) {
+            return self.yield_statement();
+        }
+        if self.catch(&[TokenType::Continue]) {
+            let keyword = self.previous().clone();
+            self.consume(TokenType::Semicolon, "Expect ';' after 'continue'.")?;
+
+            return Ok(Stmt::Continue { keyword });
+        }
+        if self.catch(&[TokenType::Defer]) {
+            return self.defer_statement();
+        }
         if self.catch(&[TokenType::For]) {
             return self.for_statement();
         }
@@ -366,6 +767,10 @@ impl Parser {
             return self.if_statement();
         }
 
+        if self.catch(&[TokenType::Import]) {
+            return self.import_statement();
+        }
+
         if self.catch(&[TokenType::Print]) {
             return self.print_statement();
         }
@@ -374,16 +779,61 @@ impl Parser {
             return self.while_statement();
         };
 
+        if self.catch(&[TokenType::With]) {
+            return self.with_statement();
+        }
+
+        if self.catch(&[TokenType::Test]) {
+            return self.test_statement();
+        }
+
         if self.catch(&[TokenType::LeftBrace]) {
             let statements = self.block()?;
 
-            return Ok(Stmt::Block { statements });
+            return Ok(Stmt::block(statements));
+        }
+
+        // A lone ';' is an empty statement, which runs as an empty block.
+        if self.catch(&[TokenType::Semicolon]) {
+            return Ok(Stmt::block(vec![]));
         }
 
         self.expression_statement()
     }
 
+    fn destructure_declaration(&mut self) -> Result<Stmt, ParseError> {
+        let paren = self.previous().clone();
+
+        let mut names = vec![];
+        loop {
+            let name = self.consume(TokenType::Identifier, "Expect variable name.")?;
+            names.push(name.clone());
+
+            if !self.catch(&[TokenType::Comma]) {
+                break;
+            }
+        }
+        self.consume(TokenType::RightParen, "Expect ')' after variable names.")?;
+
+        self.consume(TokenType::Equal, "Expect '=' after variable names.")?;
+        let initializer = self.expression()?;
+        self.consume(
+            TokenType::Semicolon,
+            "Expect ';' after variable declaration.",
+        )?;
+
+        Ok(Stmt::Destructure {
+            paren,
+            names,
+            initializer,
+        })
+    }
+
     fn var_declaration(&mut self) -> Result<Stmt, ParseError> {
+        if self.catch(&[TokenType::LeftParen]) {
+            return self.destructure_declaration();
+        }
+
         let name = self
             .consume(TokenType::Identifier, "Expect variable name.")?
             .clone();
@@ -414,8 +864,8 @@ impl Parser {
         if !self.check(TokenType::RightParen) {
             loop {
                 if parameters.len() >= MAX_ARGS {
-                    self.error(
-                        self.peek(),
+                    self.report(
+                        &self.peek().clone(),
                         &format!("Can't have more than {MAX_ARGS} parameters."),
                     );
                 }
@@ -432,6 +882,11 @@ impl Parser {
         }
         self.consume(TokenType::RightParen, "Expect ')' after parameters.")?;
 
+        let contract = Box::new(Contract {
+            requires: self.contract_clause("requires")?,
+            ensures: self.contract_clause("ensures")?,
+        });
+
         self.consume(
             TokenType::LeftBrace,
             &format!("Expect '{{' before {kind} body."),
@@ -441,13 +896,58 @@ impl Parser {
         Ok(Stmt::Function {
             name,
             parameters,
+            contract,
             body,
         })
     }
 
+    fn contract_clause(&mut self, word: &str) -> Result<Option<Clause>, ParseError> {
+        // Like `as`, contract keywords are contextual.
+        if !(self.check(TokenType::Identifier) && self.peek().lexeme == word) {
+            return Ok(None);
+        }
+
+        let keyword = self.advance().clone();
+        let condition = self.expression()?;
+
+        Ok(Some(Clause { keyword, condition }))
+    }
+
+    fn class_declaration(&mut self) -> Result<Stmt, ParseError> {
+        let name = self
+            .consume(TokenType::Identifier, "Expect class name.")?
+            .clone();
+
+        let superclass = if self.catch(&[TokenType::Less]) {
+            let name = self
+                .consume(TokenType::Identifier, "Expect superclass name.")?
+                .clone();
+            Some(Expr::variable(name))
+        } else {
+            None
+        };
+
+        self.consume(TokenType::LeftBrace, "Expect '{' before class body.")?;
+
+        let mut methods = vec![];
+        while !self.check(TokenType::RightBrace) && !self.is_at_end() {
+            methods.push(self.function("method")?);
+        }
+
+        self.consume(TokenType::RightBrace, "Expect '}' after class body.")?;
+
+        Ok(Stmt::Class {
+            name,
+            superclass,
+            methods,
+        })
+    }
+
     fn declaration(&mut self) -> Option<Stmt> {
         let result = {
-            if self.catch(&[TokenType::Fun]) {
+            if self.catch(&[TokenType::Class]) {
+                self.class_declaration()
+            } else if self.catch(&[TokenType::Fun]) {
                 self.function("function")
             } else if self.catch(&[TokenType::Var]) {
                 self.var_declaration()
@@ -458,14 +958,17 @@ impl Parser {
 
         match result {
             Ok(stmt) => Some(stmt),
-            Err(_) => {
+            Err(err) => {
+                self.errors.push(err);
                 self.synchronize();
                 None
             }
         }
     }
 
-    pub fn parse(&mut self) -> Vec<Stmt> {
+    /// Parses every declaration, recovering after each error. The returned
+    /// statements are incomplete whenever there are errors.
+    pub fn parse(&mut self) -> (Vec<Stmt>, Vec<ParseError>) {
         let mut statements = vec![];
 
         while !self.is_at_end() {
@@ -474,6 +977,6 @@ impl Parser {
             }
         }
 
-        statements
+        (statements, std::mem::take(&mut self.errors))
     }
 }
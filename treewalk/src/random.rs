@@ -0,0 +1,60 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Fallback state, since xorshift never leaves an all-zero state.
+const ZERO_SEED: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// A small xorshift64* generator so that seeded runs are reproducible.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = Rng { state: 0 };
+        rng.reseed(seed);
+
+        rng
+    }
+
+    pub fn from_clock() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+
+        Rng::new(nanos as u64)
+    }
+
+    pub fn reseed(&mut self, seed: u64) {
+        self.state = if seed == 0 { ZERO_SEED } else { seed };
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.state = x;
+
+        x.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Returns a float in `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        // Keep the top 53 bits, the width of an f64 mantissa.
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Returns an integer in `[lo, hi]`. Callers must ensure `lo <= hi`.
+    pub fn range(&mut self, lo: i64, hi: i64) -> i64 {
+        let span = hi.abs_diff(lo).wrapping_add(1);
+
+        if span == 0 {
+            // The full i64 range.
+            return self.next_u64() as i64;
+        }
+
+        lo.wrapping_add((self.next_u64() % span) as i64)
+    }
+}
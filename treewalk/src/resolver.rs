@@ -1,39 +1,63 @@
 use std::collections::HashMap;
 
-use crate::expr::{Expr, ExprData};
+use crate::expr::{Expr, ExprData, ExprVisitor, MatchArm, Pattern};
 use crate::interpreter::Interpreter;
 use crate::lox::Lox;
-use crate::stmt::Stmt;
+use crate::object::Object;
+use crate::stmt::{Contract, Stmt, StmtVisitor};
 use crate::token::Token;
 
 #[derive(Clone, Copy, PartialEq)]
 enum FunctionType {
     None,
     Function,
+    Initializer,
+    Method,
 }
 
-pub struct Resolver {
-    interpreter: Interpreter,
+#[derive(Clone, Copy, PartialEq)]
+enum ClassType {
+    None,
+    Class,
+    Subclass,
+}
+
+pub struct Resolver<'a> {
+    interpreter: &'a mut Interpreter,
     scopes: Vec<HashMap<String, bool>>,
     current_function: FunctionType,
+    current_class: ClassType,
+    /// How many loops enclose the current statement in this function.
+    loop_depth: usize,
 }
 
-impl Resolver {
-    pub fn new(interpreter: Interpreter) -> Self {
+impl<'a> Resolver<'a> {
+    pub fn new(interpreter: &'a mut Interpreter) -> Self {
         Resolver {
             interpreter,
             scopes: vec![],
             current_function: FunctionType::None,
+            current_class: ClassType::None,
+            loop_depth: 0,
         }
     }
 
-    pub fn finish(self) -> Interpreter {
-        self.interpreter
-    }
-
     pub fn resolve_statements(&mut self, statements: &[Stmt]) {
-        for stmt in statements {
+        let mut warned = false;
+        for (i, stmt) in statements.iter().enumerate() {
             self.resolve_stmt(stmt);
+
+            if !warned
+                && i + 1 < statements.len()
+                && let Some(keyword) = stmt.terminator()
+            {
+                Lox::warning_at(
+                    self.interpreter.state.borrow_mut(),
+                    keyword,
+                    "Unreachable code.",
+                );
+                warned = true;
+            }
         }
     }
 
@@ -75,115 +99,430 @@ impl Resolver {
     fn resolve_local_expr(&mut self, expr: &Expr, name: &Token) {
         for (i, scope) in self.scopes.iter().rev().enumerate() {
             if scope.contains_key(&name.lexeme) {
-                self.interpreter.resolve(expr, self.scopes.len() - 1 - i)
+                self.interpreter.resolve(expr, i);
+                return;
             }
         }
     }
 
     fn resolve_expr(&mut self, expr: &Expr) {
-        match &expr.data {
-            ExprData::Assign { name, value } => {
-                self.resolve_expr(value);
-                self.resolve_local_expr(expr, name);
-            }
-            ExprData::Binary { lhs, rhs, .. } | ExprData::Logical { lhs, rhs, .. } => {
-                self.resolve_expr(lhs);
-                self.resolve_expr(rhs);
-            }
-            ExprData::Call {
-                callee, arguments, ..
-            } => {
-                self.resolve_expr(callee);
-                for argument in arguments {
-                    self.resolve_expr(argument);
-                }
-            }
-            ExprData::Grouping { expr } => self.resolve_expr(expr),
-            ExprData::Literal { .. } => (),
-            ExprData::Unary { rhs, .. } => self.resolve_expr(rhs),
-            ExprData::Variable { name } => {
-                if let Some(scope) = self.scopes.last()
-                    && let Some(false) = scope.get(&name.lexeme)
-                {
-                    Lox::error_at(
-                        self.interpreter.state.borrow_mut(),
-                        name,
-                        "Can't read local variable in its own initializer.",
-                    );
-                }
-
-                self.resolve_local_expr(expr, name);
-            }
-        }
+        expr.accept(self)
     }
 
-    fn resolve_function(&mut self, parameters: &[Token], body: &[Stmt], kind: FunctionType) {
+    fn resolve_function(
+        &mut self,
+        parameters: &[Token],
+        contract: &Contract,
+        body: &[Stmt],
+        kind: FunctionType,
+    ) {
         let enclosing_function = self.current_function;
         self.current_function = kind;
+        // A loop around the function doesn't let its body `continue`.
+        let enclosing_loops = std::mem::take(&mut self.loop_depth);
 
         self.begin_scope();
         for param in parameters {
             self.declare(param);
             self.define(param);
         }
+
+        if let Some(requires) = &contract.requires {
+            self.resolve_expr(&requires.condition);
+        }
+        if let Some(ensures) = &contract.ensures {
+            self.begin_scope();
+            if let Some(scope) = self.scopes.last_mut() {
+                scope.insert("result".to_owned(), true);
+            }
+            self.resolve_expr(&ensures.condition);
+            self.end_scope();
+        }
+
         self.resolve_statements(body);
         self.end_scope();
 
         self.current_function = enclosing_function;
+        self.loop_depth = enclosing_loops;
     }
 
     fn resolve_stmt(&mut self, stmt: &Stmt) {
-        match stmt {
-            Stmt::Block { statements } => {
+        stmt.accept(self)
+    }
+}
+
+impl ExprVisitor<()> for Resolver<'_> {
+    fn visit_assign(&mut self, expr: &Expr, name: &Token, value: &Expr) {
+        self.resolve_expr(value);
+        self.resolve_local_expr(expr, name);
+    }
+
+    fn visit_binary(&mut self, _op: &Token, lhs: &Expr, rhs: &Expr) {
+        self.resolve_expr(lhs);
+        self.resolve_expr(rhs);
+    }
+
+    fn visit_call(
+        &mut self,
+        callee: &Expr,
+        _paren: &Token,
+        arguments: &[Expr],
+        keywords: &[(Token, Expr)],
+    ) {
+        self.resolve_expr(callee);
+        for argument in arguments {
+            self.resolve_expr(argument);
+        }
+        for (_, argument) in keywords {
+            self.resolve_expr(argument);
+        }
+    }
+
+    fn visit_get(&mut self, object: &Expr, _name: &Token) {
+        self.resolve_expr(object);
+    }
+
+    fn visit_grouping(&mut self, expr: &Expr) {
+        self.resolve_expr(expr);
+    }
+
+    fn visit_index(&mut self, object: &Expr, _bracket: &Token, index: &Expr) {
+        self.resolve_expr(object);
+        self.resolve_expr(index);
+    }
+
+    fn visit_lambda(&mut self, _arrow: &Token, parameters: &[Token], body: &[Stmt]) {
+        self.resolve_function(
+            parameters,
+            &Contract::default(),
+            body,
+            FunctionType::Function,
+        );
+    }
+
+    fn visit_list(&mut self, elements: &[Expr]) {
+        for element in elements {
+            self.resolve_expr(element);
+        }
+    }
+
+    fn visit_logical(&mut self, _op: &Token, lhs: &Expr, rhs: &Expr) {
+        self.resolve_expr(lhs);
+        self.resolve_expr(rhs);
+    }
+
+    fn visit_literal(&mut self, _value: &Object) {}
+
+    fn visit_map(&mut self, _brace: &Token, entries: &[(Expr, Expr)]) {
+        for (key, value) in entries {
+            self.resolve_expr(key);
+            self.resolve_expr(value);
+        }
+    }
+
+    fn visit_destructure(&mut self, _equals: &Token, targets: &[Expr], value: &Expr) {
+        self.resolve_expr(value);
+        for target in targets {
+            self.resolve_expr(target);
+        }
+    }
+
+    fn visit_match(&mut self, _keyword: &Token, scrutinee: &Expr, arms: &[MatchArm]) {
+        self.resolve_expr(scrutinee);
+
+        for arm in arms {
+            // Only binding arms get a scope, matching the environment the
+            // interpreter creates for them.
+            if let Pattern::Binding(name) = &arm.pattern {
                 self.begin_scope();
-                self.resolve_statements(statements);
-                self.end_scope();
-            }
-            Stmt::Expr { expr } => self.resolve_expr(expr),
-            Stmt::Function {
-                name,
-                parameters,
-                body,
-            } => {
                 self.declare(name);
                 self.define(name);
-                self.resolve_function(parameters, body, FunctionType::Function);
+                self.resolve_expr(&arm.body);
+                self.end_scope();
+            } else {
+                self.resolve_expr(&arm.body);
             }
-            Stmt::If {
-                condition,
-                then_branch,
-                else_branch,
-            } => {
-                self.resolve_expr(condition);
-                self.resolve_stmt(then_branch);
-                if let Some(else_branch) = else_branch {
-                    self.resolve_stmt(else_branch);
-                }
+        }
+    }
+
+    fn visit_set(&mut self, object: &Expr, _name: &Token, value: &Expr) {
+        self.resolve_expr(value);
+        self.resolve_expr(object);
+    }
+
+    fn visit_set_index(&mut self, object: &Expr, _bracket: &Token, index: &Expr, value: &Expr) {
+        self.resolve_expr(value);
+        self.resolve_expr(object);
+        self.resolve_expr(index);
+    }
+
+    fn visit_super(&mut self, expr: &Expr, keyword: &Token, _method: &Token) {
+        let message = match self.current_class {
+            ClassType::None => "Can't use 'super' outside of a class.",
+            ClassType::Class => "Can't use 'super' in a class with no superclass.",
+            ClassType::Subclass => {
+                self.resolve_local_expr(expr, keyword);
+                return;
             }
-            Stmt::Print { expr } => self.resolve_expr(expr),
-            Stmt::Return { keyword, expr } => {
-                if self.current_function == FunctionType::None {
-                    Lox::error(
-                        self.interpreter.state.borrow_mut(),
-                        keyword.line,
-                        "Can't return from top-level code.",
-                    );
-                }
-                if let Some(expr) = expr {
-                    self.resolve_expr(expr);
-                }
+        };
+
+        Lox::error_at(self.interpreter.state.borrow_mut(), keyword, message);
+    }
+
+    fn visit_this(&mut self, expr: &Expr, keyword: &Token) {
+        if self.current_class == ClassType::None {
+            Lox::error_at(
+                self.interpreter.state.borrow_mut(),
+                keyword,
+                "Can't use 'this' outside of a class.",
+            );
+            return;
+        }
+
+        self.resolve_local_expr(expr, keyword);
+    }
+
+    fn visit_unary(&mut self, _op: &Token, rhs: &Expr) {
+        self.resolve_expr(rhs);
+    }
+
+    fn visit_variable(&mut self, expr: &Expr, name: &Token) {
+        if let Some(scope) = self.scopes.last()
+            && let Some(false) = scope.get(&name.lexeme)
+        {
+            Lox::error_at(
+                self.interpreter.state.borrow_mut(),
+                name,
+                "Can't read local variable in its own initializer.",
+            );
+        }
+
+        self.resolve_local_expr(expr, name);
+    }
+}
+
+impl StmtVisitor<()> for Resolver<'_> {
+    fn visit_block(&mut self, statements: &[Stmt], scoped: bool) {
+        if scoped {
+            self.begin_scope();
+            self.resolve_statements(statements);
+            self.end_scope();
+        } else {
+            self.resolve_statements(statements);
+        }
+    }
+
+    fn visit_class(&mut self, name: &Token, superclass: Option<&Expr>, methods: &[Stmt]) {
+        let enclosing_class = self.current_class;
+        self.current_class = ClassType::Class;
+
+        self.declare(name);
+        self.define(name);
+
+        if let Some(superclass) = superclass {
+            if let ExprData::Variable { name: superclass } = &superclass.data
+                && superclass.lexeme == name.lexeme
+            {
+                Lox::error_at(
+                    self.interpreter.state.borrow_mut(),
+                    superclass,
+                    "A class can't inherit from itself.",
+                );
             }
-            Stmt::Var { name, initializer } => {
-                self.declare(name);
-                if let Some(initializer) = initializer {
-                    self.resolve_expr(initializer);
-                }
-                self.define(name);
+
+            self.current_class = ClassType::Subclass;
+            self.resolve_expr(superclass);
+
+            // Methods close over a scope holding the superclass.
+            self.begin_scope();
+            if let Some(scope) = self.scopes.last_mut() {
+                scope.insert("super".to_owned(), true);
             }
-            Stmt::While { condition, body } => {
-                self.resolve_expr(condition);
-                self.resolve_stmt(body);
+        }
+
+        self.begin_scope();
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert("this".to_owned(), true);
+        }
+
+        for method in methods {
+            let Stmt::Function {
+                name,
+                parameters,
+                contract,
+                body,
+            } = method
+            else {
+                unreachable!("class bodies only contain methods");
+            };
+
+            let kind = if name.lexeme == "init" {
+                FunctionType::Initializer
+            } else {
+                FunctionType::Method
+            };
+
+            self.resolve_function(parameters, contract, body, kind);
+        }
+
+        self.end_scope();
+
+        if superclass.is_some() {
+            self.end_scope();
+        }
+
+        self.current_class = enclosing_class;
+    }
+
+    fn visit_destructure(&mut self, _paren: &Token, names: &[Token], initializer: &Expr) {
+        self.resolve_expr(initializer);
+        for name in names {
+            self.declare(name);
+            self.define(name);
+        }
+    }
+
+    fn visit_continue(&mut self, keyword: &Token) {
+        if self.loop_depth == 0 {
+            Lox::error_at(
+                self.interpreter.state.borrow_mut(),
+                keyword,
+                "Can't use 'continue' outside of a loop.",
+            );
+        }
+    }
+
+    fn visit_test(&mut self, _keyword: &Token, _name: &Token, body: &[Stmt]) {
+        // Like a function body, a test can't `continue` a loop around it.
+        let enclosing_loops = std::mem::take(&mut self.loop_depth);
+
+        self.begin_scope();
+        self.resolve_statements(body);
+        self.end_scope();
+
+        self.loop_depth = enclosing_loops;
+    }
+
+    fn visit_defer(&mut self, expr: &Expr) {
+        self.resolve_expr(expr);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        self.resolve_expr(expr);
+    }
+
+    fn visit_function(
+        &mut self,
+        name: &Token,
+        parameters: &[Token],
+        contract: &Contract,
+        body: &[Stmt],
+    ) {
+        self.declare(name);
+        self.define(name);
+        self.resolve_function(parameters, contract, body, FunctionType::Function);
+    }
+
+    fn visit_if(&mut self, condition: &Expr, then_branch: &Stmt, else_branch: Option<&Stmt>) {
+        self.resolve_expr(condition);
+        self.resolve_stmt(then_branch);
+        if let Some(else_branch) = else_branch {
+            self.resolve_stmt(else_branch);
+        }
+    }
+
+    // Imported files are resolved on their own when they're loaded.
+    fn visit_import(&mut self, _keyword: &Token, _path: &Token, alias: Option<&Token>) {
+        if let Some(alias) = alias {
+            self.declare(alias);
+            self.define(alias);
+        }
+    }
+
+    fn visit_print(&mut self, expr: &Expr) {
+        self.resolve_expr(expr);
+    }
+
+    fn visit_return(&mut self, keyword: &Token, expr: Option<&Expr>) {
+        if self.current_function == FunctionType::None {
+            Lox::error(
+                self.interpreter.state.borrow_mut(),
+                keyword.line,
+                "Can't return from top-level code.",
+            );
+        }
+        if let Some(expr) = expr {
+            if self.current_function == FunctionType::Initializer {
+                Lox::error_at(
+                    self.interpreter.state.borrow_mut(),
+                    keyword,
+                    "Can't return a value from an initializer.",
+                );
             }
+
+            self.resolve_expr(expr);
+        }
+    }
+
+    fn visit_yield(&mut self, keyword: &Token, expr: &Expr) {
+        match self.current_function {
+            FunctionType::None => Lox::error_at(
+                self.interpreter.state.borrow_mut(),
+                keyword,
+                "Can't yield from top-level code.",
+            ),
+            FunctionType::Initializer => Lox::error_at(
+                self.interpreter.state.borrow_mut(),
+                keyword,
+                "Can't yield from an initializer.",
+            ),
+            FunctionType::Function | FunctionType::Method => (),
+        }
+
+        self.resolve_expr(expr);
+    }
+
+    fn visit_var(&mut self, name: &Token, initializer: Option<&Expr>) {
+        self.declare(name);
+        if let Some(initializer) = initializer {
+            self.resolve_expr(initializer);
+        }
+        self.define(name);
+    }
+
+    fn visit_while(
+        &mut self,
+        condition: &Expr,
+        body: &Stmt,
+        increment: Option<&Expr>,
+        rebinds: Option<&Token>,
+    ) {
+        self.resolve_expr(condition);
+
+        self.loop_depth += 1;
+        if let Some(name) = rebinds {
+            self.begin_scope();
+            self.declare(name);
+            self.define(name);
+            self.resolve_stmt(body);
+            self.end_scope();
+        } else {
+            self.resolve_stmt(body);
+        }
+        self.loop_depth -= 1;
+
+        if let Some(increment) = increment {
+            self.resolve_expr(increment);
         }
     }
+
+    fn visit_with(&mut self, _keyword: &Token, binding: &Token, initializer: &Expr, body: &Stmt) {
+        self.resolve_expr(initializer);
+
+        self.begin_scope();
+        self.declare(binding);
+        self.define(binding);
+        self.resolve_stmt(body);
+        self.end_scope();
+    }
 }
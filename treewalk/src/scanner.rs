@@ -1,7 +1,4 @@
-use std::cell::RefCell;
-use std::rc::Rc;
-
-use crate::lox::{Lox, LoxState};
+use crate::error::{ScanError, Span};
 use crate::object::Object;
 use crate::token::{Token, TokenType};
 
@@ -16,9 +13,9 @@ fn is_identic(c: char, first: bool) -> bool {
 }
 
 pub struct Scanner {
-    state: Rc<RefCell<LoxState>>,
     source: String,
     tokens: Vec<Token>,
+    errors: Vec<ScanError>,
     start: usize,
     current: usize,
     line: usize,
@@ -27,19 +24,30 @@ pub struct Scanner {
 // use TokenType as TT;
 
 impl Scanner {
-    pub fn new(state: Rc<RefCell<LoxState>>, source: &str) -> Self {
+    pub fn new(source: &str) -> Self {
         let source = source.to_owned();
 
         Scanner {
-            state,
             source,
             tokens: vec![],
+            errors: vec![],
             start: 0,
             current: 0,
             line: 1,
         }
     }
 
+    fn error(&mut self, message: &str) {
+        self.errors.push(ScanError {
+            span: Span {
+                line: self.line,
+                start: self.start,
+                len: self.current - self.start,
+            },
+            message: message.to_owned(),
+        });
+    }
+
     fn char_at(&self, pos: usize) -> char {
         self.source.as_bytes()[pos..=pos][0] as char
     }
@@ -54,7 +62,13 @@ impl Scanner {
 
     fn add_token_literal(&mut self, kind: TokenType, literal: Object) {
         let text = &self.source[self.start..self.current];
-        self.tokens.push(Token::new(kind, text, literal, self.line));
+        let token = Token::new(kind, text, literal, self.line);
+
+        self.tokens.push(Token {
+            start: self.start,
+            len: self.current - self.start,
+            ..token
+        });
     }
 
     fn add_token(&mut self, kind: TokenType) {
@@ -80,7 +94,7 @@ impl Scanner {
     }
 
     fn peek_next(&self) -> Option<char> {
-        if self.current + 1 > self.source.len() {
+        if self.current + 1 >= self.source.len() {
             None
         } else {
             Some(self.char_at(self.current + 1))
@@ -98,7 +112,7 @@ impl Scanner {
         }
 
         if self.is_at_end() {
-            Lox::error(self.state.borrow_mut(), self.line, "Unterminated string.");
+            self.error("Unterminated string.");
             return;
         }
 
@@ -127,9 +141,22 @@ impl Scanner {
             self.digits();
         }
 
-        let x = self.source[self.start..self.current]
+        let text = &self.source[self.start..self.current];
+        #[cfg(feature = "integers")]
+        let integer = (!text.contains('.')).then(|| text.parse());
+        let x = text
             .parse()
             .expect("currently windowed lexeme should always be a valid int or float literal");
+
+        #[cfg(feature = "integers")]
+        match integer {
+            Some(Ok(x)) => return self.add_token_literal(TokenType::Number, Object::Int(x)),
+            // Still scanned as a float, so parsing carries on without
+            // tripping over a missing token.
+            Some(Err(_)) => self.error("Integer literal is too large."),
+            None => (),
+        }
+
         self.add_token_literal(TokenType::Number, Object::Number(x));
     }
 
@@ -142,20 +169,27 @@ impl Scanner {
         let kind = match &self.source[self.start..self.current] {
             "and" => TokenType::And,
             "class" => TokenType::Class,
+            "continue" => TokenType::Continue,
+            "defer" => TokenType::Defer,
             "else" => TokenType::Else,
             "false" => TokenType::False,
             "for" => TokenType::For,
             "fun" => TokenType::Fun,
             "if" => TokenType::If,
+            "import" => TokenType::Import,
+            "match" => TokenType::Match,
             "nil" => TokenType::Nil,
             "or" => TokenType::Or,
             "print" => TokenType::Print,
             "return" => TokenType::Return,
             "super" => TokenType::Super,
+            "test" => TokenType::Test,
             "this" => TokenType::This,
             "true" => TokenType::True,
             "var" => TokenType::Var,
             "while" => TokenType::While,
+            "with" => TokenType::With,
+            "yield" => TokenType::Yield,
 
             _ => TokenType::Identifier,
         };
@@ -163,6 +197,16 @@ impl Scanner {
         self.add_token(kind);
     }
 
+    /// Scans the rest of `??=`, `&&=` or `||=`, whose first character is
+    /// `c`. None of these characters mean anything on their own.
+    fn logical_assignment(&mut self, c: char, kind: TokenType) {
+        if self.catch(c) && self.catch('=') {
+            self.add_token(kind);
+        } else {
+            self.error("Unexpected character.");
+        }
+    }
+
     fn scan_token(&mut self) {
         let c = self.advance();
 
@@ -198,6 +242,9 @@ impl Scanner {
             ')' => emit_token!(RightParen),
             '{' => emit_token!(LeftBrace),
             '}' => emit_token!(RightBrace),
+            '[' => emit_token!(LeftBracket),
+            ']' => emit_token!(RightBracket),
+            ':' => emit_token!(Colon),
             ',' => emit_token!(Comma),
             '.' => emit_token!(Dot),
             '-' => emit_token!(Minus),
@@ -206,7 +253,20 @@ impl Scanner {
             '*' => emit_token!(Star),
 
             '!' => emit_token!('=' => BangEqual else Bang),
-            '=' => emit_token!('=' => EqualEqual else Equal),
+            '=' => {
+                let kind = if self.catch('=') {
+                    TokenType::EqualEqual
+                } else if self.catch('>') {
+                    TokenType::Arrow
+                } else {
+                    TokenType::Equal
+                };
+
+                self.add_token(kind)
+            }
+            '?' => self.logical_assignment('?', TokenType::QuestionQuestionEqual),
+            '&' => self.logical_assignment('&', TokenType::AmpAmpEqual),
+            '|' => self.logical_assignment('|', TokenType::PipePipeEqual),
             '<' => emit_token!('=' => LessEqual else Less),
             '>' => emit_token!('=' => GreaterEqual else Greater),
 
@@ -231,20 +291,25 @@ impl Scanner {
             '\n' => self.line += 1,
             c if c.is_ascii_whitespace() => (),
 
-            _ => Lox::error(self.state.borrow_mut(), self.line, "Unexpected character."),
+            _ => self.error("Unexpected character."),
         }
     }
 
-    pub fn scan_tokens(mut self) -> Vec<Token> {
+    /// Scans the whole source. The tokens always end with `Eof`, even when
+    /// there are errors.
+    pub fn scan_tokens(mut self) -> (Vec<Token>, Vec<ScanError>) {
         while !self.is_at_end() {
             self.start = self.current;
             self.scan_token();
         }
 
-        self.tokens
-            .push(Token::new(TokenType::Eof, "", Object::Nil, self.line));
+        let eof = Token::new(TokenType::Eof, "", Object::Nil, self.line);
+        self.tokens.push(Token {
+            start: self.source.len(),
+            ..eof
+        });
 
-        self.tokens
+        (self.tokens, self.errors)
     }
 
     fn is_at_end(&self) -> bool {
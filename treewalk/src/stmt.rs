@@ -4,10 +4,49 @@ use std::ops::{Deref, DerefMut};
 use crate::expr::Expr;
 use crate::token::Token;
 
+/// A `requires` or `ensures` clause on a function.
+#[derive(Debug, Clone)]
+pub struct Clause {
+    pub keyword: Token,
+    pub condition: Expr,
+}
+
+/// The conditions checked around each call to a function. `ensures` sees
+/// the return value as `result`.
+#[derive(Debug, Clone, Default)]
+pub struct Contract {
+    pub requires: Option<Clause>,
+    pub ensures: Option<Clause>,
+}
+
 #[derive(Debug, Clone)]
 pub enum Stmt {
     Block {
         statements: Vec<Stmt>,
+        /// Whether the block declares anything of its own. Blocks that don't
+        /// run in the enclosing environment instead of allocating a new one.
+        scoped: bool,
+    },
+    Class {
+        name: Token,
+        /// Always an `ExprData::Variable`.
+        superclass: Option<Expr>,
+        /// Always `Stmt::Function`s.
+        methods: Vec<Stmt>,
+    },
+    /// `continue;`, which skips to the next iteration of the enclosing loop.
+    Continue {
+        keyword: Token,
+    },
+    /// `defer expr;`, which evaluates `expr` when the enclosing block exits.
+    Defer {
+        expr: Expr,
+    },
+    /// `var (a, b) = initializer;`
+    Destructure {
+        paren: Token,
+        names: Vec<Token>,
+        initializer: Expr,
     },
     Expr {
         expr: Expr,
@@ -15,6 +54,7 @@ pub enum Stmt {
     Function {
         name: Token,
         parameters: Vec<Token>,
+        contract: Box<Contract>,
         body: Vec<Stmt>,
     },
     If {
@@ -22,6 +62,11 @@ pub enum Stmt {
         then_branch: SubStmt,
         else_branch: Option<SubStmt>,
     },
+    Import {
+        keyword: Token,
+        path: Token,
+        alias: Option<Token>,
+    },
     Print {
         expr: Expr,
     },
@@ -29,6 +74,14 @@ pub enum Stmt {
         keyword: Token,
         expr: Option<Expr>,
     },
+    /// `test "name" { body }`, which only runs in test mode, in a scope of
+    /// its own.
+    Test {
+        keyword: Token,
+        /// Always a string token.
+        name: Token,
+        body: Vec<Stmt>,
+    },
     Var {
         name: Token,
         initializer: Option<Expr>,
@@ -36,7 +89,200 @@ pub enum Stmt {
     While {
         condition: Expr,
         body: SubStmt,
+        /// A `for` loop's increment, run after the body however it finishes,
+        /// so `continue` doesn't skip it.
+        increment: Option<Expr>,
+        /// The variable a `for` loop declares, which each run of the body
+        /// gets a fresh copy of, so closures capture that iteration's value.
+        rebinds: Option<Token>,
+    },
+    /// `with (var binding = initializer) body`, which calls the bound
+    /// value's `close` method however the body exits.
+    With {
+        keyword: Token,
+        binding: Token,
+        initializer: Expr,
+        body: SubStmt,
     },
+    Yield {
+        keyword: Token,
+        expr: Expr,
+    },
+}
+
+/// A pass over statements, with one method per `Stmt` variant.
+pub trait StmtVisitor<T> {
+    fn visit_block(&mut self, statements: &[Stmt], scoped: bool) -> T;
+    fn visit_class(&mut self, name: &Token, superclass: Option<&Expr>, methods: &[Stmt]) -> T;
+    fn visit_continue(&mut self, keyword: &Token) -> T;
+    fn visit_defer(&mut self, expr: &Expr) -> T;
+    fn visit_destructure(&mut self, paren: &Token, names: &[Token], initializer: &Expr) -> T;
+    fn visit_expr(&mut self, expr: &Expr) -> T;
+    fn visit_function(
+        &mut self,
+        name: &Token,
+        parameters: &[Token],
+        contract: &Contract,
+        body: &[Stmt],
+    ) -> T;
+    fn visit_if(&mut self, condition: &Expr, then_branch: &Stmt, else_branch: Option<&Stmt>) -> T;
+    fn visit_import(&mut self, keyword: &Token, path: &Token, alias: Option<&Token>) -> T;
+    fn visit_print(&mut self, expr: &Expr) -> T;
+    fn visit_return(&mut self, keyword: &Token, expr: Option<&Expr>) -> T;
+    fn visit_test(&mut self, keyword: &Token, name: &Token, body: &[Stmt]) -> T;
+    fn visit_var(&mut self, name: &Token, initializer: Option<&Expr>) -> T;
+    fn visit_while(
+        &mut self,
+        condition: &Expr,
+        body: &Stmt,
+        increment: Option<&Expr>,
+        rebinds: Option<&Token>,
+    ) -> T;
+    fn visit_with(
+        &mut self,
+        keyword: &Token,
+        binding: &Token,
+        initializer: &Expr,
+        body: &Stmt,
+    ) -> T;
+    fn visit_yield(&mut self, keyword: &Token, expr: &Expr) -> T;
+}
+
+impl Stmt {
+    pub fn accept<T>(&self, visitor: &mut (impl StmtVisitor<T> + ?Sized)) -> T {
+        match self {
+            Stmt::Block { statements, scoped } => visitor.visit_block(statements, *scoped),
+            Stmt::Class {
+                name,
+                superclass,
+                methods,
+            } => visitor.visit_class(name, superclass.as_ref(), methods),
+            Stmt::Continue { keyword } => visitor.visit_continue(keyword),
+            Stmt::Defer { expr } => visitor.visit_defer(expr),
+            Stmt::Destructure {
+                paren,
+                names,
+                initializer,
+            } => visitor.visit_destructure(paren, names, initializer),
+            Stmt::Expr { expr } => visitor.visit_expr(expr),
+            Stmt::Function {
+                name,
+                parameters,
+                contract,
+                body,
+            } => visitor.visit_function(name, parameters, contract, body),
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => visitor.visit_if(condition, then_branch, else_branch.as_deref()),
+            Stmt::Import {
+                keyword,
+                path,
+                alias,
+            } => visitor.visit_import(keyword, path, alias.as_ref()),
+            Stmt::Print { expr } => visitor.visit_print(expr),
+            Stmt::Return { keyword, expr } => visitor.visit_return(keyword, expr.as_ref()),
+            Stmt::Test {
+                keyword,
+                name,
+                body,
+            } => visitor.visit_test(keyword, name, body),
+            Stmt::Var { name, initializer } => visitor.visit_var(name, initializer.as_ref()),
+            Stmt::While {
+                condition,
+                body,
+                increment,
+                rebinds,
+            } => visitor.visit_while(condition, body, increment.as_ref(), rebinds.as_ref()),
+            Stmt::With {
+                keyword,
+                binding,
+                initializer,
+                body,
+            } => visitor.visit_with(keyword, binding, initializer, body),
+            Stmt::Yield { keyword, expr } => visitor.visit_yield(keyword, expr),
+        }
+    }
+
+    pub fn block(statements: Vec<Stmt>) -> Self {
+        let scoped = statements.iter().any(Stmt::declares);
+
+        Stmt::Block { statements, scoped }
+    }
+
+    /// Whether the statement binds a name in the environment it runs in.
+    fn declares(&self) -> bool {
+        matches!(
+            self,
+            Stmt::Class { .. }
+                | Stmt::Destructure { .. }
+                | Stmt::Function { .. }
+                | Stmt::Var { .. }
+                | Stmt::Import { alias: Some(_), .. }
+        )
+    }
+
+    /// Whether the statement yields, making the function it's in a
+    /// generator. Functions and classes declared inside don't count.
+    pub fn yields(&self) -> bool {
+        match self {
+            Stmt::Yield { .. } => true,
+            Stmt::Block { statements, .. } => statements.iter().any(Stmt::yields),
+            Stmt::If {
+                then_branch,
+                else_branch,
+                ..
+            } => then_branch.yields() || else_branch.as_ref().is_some_and(|stmt| stmt.yields()),
+            Stmt::While { body, .. } | Stmt::With { body, .. } => body.yields(),
+            _ => false,
+        }
+    }
+
+    /// The `return` or `continue` that makes the statement always jump out of
+    /// the block it's in, if it does, so nothing after it there can run.
+    pub fn terminator(&self) -> Option<&Token> {
+        match self {
+            Stmt::Return { keyword, .. } | Stmt::Continue { keyword } => Some(keyword),
+            Stmt::Block { statements, .. } => statements.iter().find_map(Stmt::terminator),
+            Stmt::If {
+                then_branch,
+                else_branch: Some(else_branch),
+                ..
+            } => else_branch.terminator().and(then_branch.terminator()),
+            _ => None,
+        }
+    }
+
+    /// A one-line summary of the statement, as shown by `--trace`.
+    pub fn describe(&self) -> String {
+        match self {
+            Stmt::Block { .. } => "block".to_owned(),
+            Stmt::Class { name, .. } => format!("class {}", name.lexeme),
+            Stmt::Continue { keyword } => format!("continue (line {})", keyword.line),
+            Stmt::Defer { .. } => "defer".to_owned(),
+            Stmt::Destructure { names, .. } => {
+                let names: Vec<_> = names.iter().map(|name| name.lexeme.as_str()).collect();
+                format!("var ({})", names.join(", "))
+            }
+            Stmt::Expr { .. } => "expression".to_owned(),
+            Stmt::Function { name, .. } => format!("fun {}", name.lexeme),
+            Stmt::If { .. } => "if".to_owned(),
+            Stmt::Import {
+                path,
+                alias: Some(alias),
+                ..
+            } => format!("import {} as {}", path.lexeme, alias.lexeme),
+            Stmt::Import { path, .. } => format!("import {}", path.lexeme),
+            Stmt::Print { .. } => "print".to_owned(),
+            Stmt::Return { keyword, .. } => format!("return (line {})", keyword.line),
+            Stmt::Test { name, .. } => format!("test {}", name.lexeme),
+            Stmt::Var { name, .. } => format!("var {} (line {})", name.lexeme, name.line),
+            Stmt::While { .. } => "while".to_owned(),
+            Stmt::With { binding, .. } => format!("with {}", binding.lexeme),
+            Stmt::Yield { keyword, .. } => format!("yield (line {})", keyword.line),
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -1,5 +1,6 @@
 use std::fmt::{Debug, Display};
 
+use crate::error::Span;
 use crate::object::Object;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -9,6 +10,9 @@ pub enum TokenType {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
+    Colon,
     Comma,
     Dot,
     Minus,
@@ -22,11 +26,21 @@ pub enum TokenType {
     BangEqual,
     Equal,
     EqualEqual,
+    /// `=>`, between an arrow function's parameters and its body.
+    Arrow,
     Greater,
     GreaterEqual,
     Less,
     LessEqual,
 
+    // Three character tokens: logical assignments.
+    /// `??=`, assigning only if the variable is `nil`.
+    QuestionQuestionEqual,
+    /// `&&=`, assigning only if the variable is truthy.
+    AmpAmpEqual,
+    /// `||=`, assigning only if the variable is falsey.
+    PipePipeEqual,
+
     // Literals.
     Identifier,
     String,
@@ -35,24 +49,130 @@ pub enum TokenType {
     // Keywords.
     And,
     Class,
+    Continue,
+    Defer,
     Else,
     False,
     For,
     Fun,
     If,
+    Import,
+    Match,
     Nil,
     Or,
     Print,
     Return,
     Super,
+    Test,
     This,
     True,
     Var,
     While,
+    With,
+    Yield,
 
     Eof,
 }
 
+/// How tightly a binary operator binds, from loosest to tightest, so that
+/// precedences compare with `<`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Precedence {
+    /// Not a binary operator.
+    None,
+    Or,         // or
+    And,        // and
+    Equality,   // == !=
+    Comparison, // < > <= >=
+    Term,       // + -
+    Factor,     // * /
+}
+
+impl TokenType {
+    pub fn is_keyword(self) -> bool {
+        use TokenType as TT;
+
+        matches!(
+            self,
+            TT::And
+                | TT::Class
+                | TT::Continue
+                | TT::Defer
+                | TT::Else
+                | TT::False
+                | TT::For
+                | TT::Fun
+                | TT::If
+                | TT::Import
+                | TT::Match
+                | TT::Nil
+                | TT::Or
+                | TT::Print
+                | TT::Return
+                | TT::Super
+                | TT::Test
+                | TT::This
+                | TT::True
+                | TT::Var
+                | TT::While
+                | TT::With
+                | TT::Yield
+        )
+    }
+
+    /// Identifiers, strings and numbers: the tokens whose text is their value.
+    pub fn is_literal(self) -> bool {
+        matches!(
+            self,
+            TokenType::Identifier | TokenType::String | TokenType::Number
+        )
+    }
+
+    /// Keywords that can only begin a statement, which error recovery
+    /// resumes at.
+    pub fn is_statement_start(self) -> bool {
+        use TokenType as TT;
+
+        matches!(
+            self,
+            TT::Class
+                | TT::Fun
+                | TT::Var
+                | TT::For
+                | TT::If
+                | TT::Import
+                | TT::While
+                | TT::With
+                | TT::Print
+                | TT::Return
+                | TT::Continue
+                | TT::Yield
+                | TT::Defer
+                | TT::Test
+        )
+    }
+
+    /// Operators that make an `ExprData::Binary`. `and` and `or` have a
+    /// precedence too, but short-circuit, so they aren't counted.
+    pub fn is_binary_operator(self) -> bool {
+        self.precedence() > Precedence::And
+    }
+
+    pub fn precedence(self) -> Precedence {
+        use TokenType as TT;
+
+        match self {
+            TT::Or => Precedence::Or,
+            TT::And => Precedence::And,
+            TT::BangEqual | TT::EqualEqual => Precedence::Equality,
+            TT::Greater | TT::GreaterEqual | TT::Less | TT::LessEqual => Precedence::Comparison,
+            TT::Minus | TT::Plus => Precedence::Term,
+            TT::Slash | TT::Star => Precedence::Factor,
+            _ => Precedence::None,
+        }
+    }
+}
+
 impl Display for TokenType {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(f, "{self:?}")
@@ -65,9 +185,14 @@ pub struct Token {
     pub(crate) lexeme: String,
     pub(crate) line: usize,
     pub(crate) literal: Box<Object>,
+    /// The byte offset in the source where the token starts.
+    pub(crate) start: usize,
+    /// How many bytes of source the token covers.
+    pub(crate) len: usize,
 }
 
 impl Token {
+    /// A token made up rather than scanned, which covers no source.
     pub fn new(kind: TokenType, lexeme: &str, literal: Object, line: usize) -> Self {
         let lexeme = lexeme.to_owned();
         let literal = Box::new(literal);
@@ -77,6 +202,16 @@ impl Token {
             lexeme,
             literal,
             line,
+            start: 0,
+            len: 0,
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        Span {
+            line: self.line,
+            start: self.start,
+            len: self.len,
         }
     }
 }
@@ -0,0 +1,153 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+use common::exit::{RUNTIME_ERROR, TOO_MANY_ARGS};
+
+fn script(name: &str) -> String {
+    format!("{}/tests/scripts/{name}", env!("CARGO_MANIFEST_DIR"))
+}
+
+/// Runs the binary with `args` and nothing on stdin.
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_treewalk"))
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[test]
+fn version_is_semver() {
+    let output = run(&["--version"]);
+    assert!(output.status.success());
+
+    let stdout = stdout(&output);
+    let version = stdout.split_whitespace().nth(1).unwrap();
+    let parts: Vec<_> = version.split('.').collect();
+    assert_eq!(parts.len(), 3, "{version}");
+    assert!(
+        parts.iter().all(|part| part.parse::<u32>().is_ok()),
+        "{version}"
+    );
+}
+
+#[test]
+fn help_prints_usage() {
+    let output = run(&["--help"]);
+    assert!(output.status.success());
+    assert!(stdout(&output).starts_with("Usage: treewalk"));
+}
+
+#[test]
+fn script_path_runs_the_script() {
+    let output = run(&[&script("recursion.lox")]);
+    assert!(output.status.success());
+    assert!(stdout(&output).starts_with("55\n"), "{}", stdout(&output));
+}
+
+/// Runs the binary with `args`, piping `source` to its stdin.
+fn pipe(args: &[&str], source: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_treewalk"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(source.as_bytes())
+        .unwrap();
+
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn no_arguments_runs_an_empty_pipe() {
+    let output = run(&[]);
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn piped_script_runs_whole() {
+    // The function spans several lines, so it only parses if the script is
+    // read whole rather than a line at a time.
+    let source = "fun greet() {\n  print \"hi\";\n}\ngreet();\nprint 1 + 2;\n";
+
+    let output = pipe(&[], source);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "hi\n3\n");
+
+    let output = pipe(&["-"], source);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "hi\n3\n");
+}
+
+#[test]
+fn arguments_after_the_script_are_its_own() {
+    let output = run(&[&script("argv.lox"), "one", "--two"]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "[one, --two]\n");
+}
+
+#[test]
+fn unknown_flags_are_rejected() {
+    let output = run(&["--bogus", &script("argv.lox")]);
+    assert_eq!(output.status.code(), Some(TOO_MANY_ARGS));
+}
+
+#[test]
+fn strict_flag_rejects_reading_an_uninitialized_variable() {
+    let output = run(&[&script("uninitialized.lox")]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "nil\n1\n");
+
+    let output = run(&["--strict", &script("uninitialized.lox")]);
+    assert_eq!(output.status.code(), Some(RUNTIME_ERROR));
+    assert_eq!(stdout(&output), "");
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Variable 'x' used before assignment.\n[line 4]\n"
+    );
+}
+
+#[test]
+fn test_flag_prints_a_summary_and_fails_on_failures() {
+    let output = run(&["--test", &script("test_blocks.lox")]);
+
+    assert_eq!(output.status.code(), Some(RUNTIME_ERROR));
+    assert_eq!(
+        stdout(&output),
+        "test addition ... ok\nnever\ntest broken ... FAILED\ndone\n\
+         test result: 1 passed; 1 failed.\n"
+    );
+}
+
+#[test]
+fn blank_scripts_run_cleanly() {
+    for (name, source) in [
+        ("empty", ""),
+        ("whitespace", " \n\t\r\n"),
+        ("comment", "// just a comment"),
+    ] {
+        let path =
+            std::env::temp_dir().join(format!("lox-treewalk-{name}-{}.lox", std::process::id()));
+        std::fs::write(&path, source).unwrap();
+
+        let output = run(&[path.to_str().unwrap()]);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(output.status.success(), "{name}");
+        assert!(output.stdout.is_empty(), "{name}");
+        assert!(output.stderr.is_empty(), "{name}");
+    }
+}
@@ -0,0 +1,31 @@
+//! A `Lox` writing into buffers, for the tests that configure it in ways a
+//! `tests/scripts` annotation can't.
+
+#![allow(dead_code)]
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use ::common::sink::Sink;
+use treewalk::lox::Lox;
+
+pub type Buffer = Rc<RefCell<String>>;
+
+/// `lox` printing into the first buffer and reporting into the second.
+pub fn buffered(mut lox: Lox) -> (Lox, Buffer, Buffer) {
+    let (out, printed) = Sink::buffer();
+    let (err, reported) = Sink::buffer();
+    lox.set_output(out, err);
+
+    (lox, printed, reported)
+}
+
+/// Runs `source` with `setup` applied first, returning what it printed and
+/// reported.
+pub fn run(setup: impl FnOnce(&mut Lox), source: &str) -> (String, String) {
+    let (mut lox, printed, reported) = buffered(Lox::new());
+    setup(&mut lox);
+    lox.run(source);
+
+    (printed.take(), reported.take())
+}
@@ -0,0 +1,58 @@
+mod common;
+
+use treewalk::lox::Lox;
+
+const SOURCE: &str = "
+fun counter() {
+  var count = 0;
+  fun tick() {
+    count = count + 1;
+    return count;
+  }
+  return tick;
+}
+
+var tick = counter();
+tick();
+print tick();
+";
+
+#[test]
+fn compiled_script_runs_repeatedly() {
+    let (mut lox, printed, reported) = common::buffered(Lox::new());
+
+    // The source is gone before the script runs, so nothing can re-scan it.
+    let source = SOURCE.to_owned();
+    let script = lox.compile(&source).expect("script should compile");
+    drop(source);
+
+    lox.run_compiled(&script);
+    lox.run_compiled(&script);
+
+    assert_eq!(printed.take(), "2\n2\n");
+    assert_eq!(reported.take(), "");
+}
+
+#[test]
+fn compiled_script_runs_in_another_lox() {
+    let (mut compiler, _, _) = common::buffered(Lox::new());
+    let script = compiler.compile(SOURCE).expect("script should compile");
+
+    let (mut lox, printed, reported) = common::buffered(Lox::new());
+    lox.run_compiled(&script);
+
+    assert_eq!(printed.take(), "2\n");
+    assert_eq!(reported.take(), "");
+}
+
+#[test]
+fn compile_reports_errors() {
+    let (mut lox, printed, reported) = common::buffered(Lox::new());
+
+    assert!(lox.compile("print ;").is_none());
+    assert_eq!(printed.take(), "");
+    assert_eq!(
+        reported.take(),
+        "[line 1] Error at ';': Expect expression.\n"
+    );
+}
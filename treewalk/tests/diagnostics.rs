@@ -0,0 +1,101 @@
+mod common;
+
+use treewalk::error::{Severity, Span};
+use treewalk::lox::Lox;
+
+/// A `Lox` that collects diagnostics without printing them, along with
+/// everything written to its error sink.
+fn quiet_lox() -> (Lox, common::Buffer) {
+    let (mut lox, _, reported) = common::buffered(Lox::new());
+    lox.set_print_diagnostics(false);
+
+    (lox, reported)
+}
+
+#[test]
+fn syntax_errors_are_collected_with_spans() {
+    let (mut lox, reported) = quiet_lox();
+
+    assert!(lox.compile("var x = 1 @;\nprint (x;").is_none());
+    let diagnostics = lox.take_diagnostics();
+
+    let summary: Vec<_> = diagnostics
+        .iter()
+        .map(|d| (d.severity, d.code, d.span, d.message.as_str()))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            (
+                Severity::Error,
+                "scan",
+                Span {
+                    line: 1,
+                    start: 10,
+                    len: 1,
+                },
+                "Unexpected character.",
+            ),
+            (
+                Severity::Error,
+                "parse",
+                Span {
+                    line: 2,
+                    start: 21,
+                    len: 1,
+                },
+                "Expect ')' after expression.",
+            ),
+        ]
+    );
+    assert_eq!(reported.take(), "");
+    assert!(lox.take_diagnostics().is_empty());
+}
+
+#[test]
+fn resolution_problems_have_severities() {
+    let (mut lox, reported) = quiet_lox();
+
+    lox.compile("fun f() {\n  return;\n  print 1;\n}\nreturn;");
+    let diagnostics = lox.take_diagnostics();
+
+    let summary: Vec<_> = diagnostics
+        .iter()
+        .map(|d| (d.severity, d.span.line, d.message.as_str()))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            (Severity::Warning, 2, "Unreachable code."),
+            (Severity::Error, 5, "Can't return from top-level code."),
+        ]
+    );
+    assert_eq!(diagnostics[0].span.start, 12);
+    assert_eq!(diagnostics[0].span.len, 6);
+    assert_eq!(reported.take(), "");
+}
+
+#[test]
+fn diagnostics_are_printed_by_default() {
+    let (mut lox, _, reported) = common::buffered(Lox::new());
+    lox.run("print ;");
+
+    assert_eq!(
+        reported.take(),
+        "[line 1] Error at ';': Expect expression.\n"
+    );
+    assert_eq!(lox.take_diagnostics().len(), 1);
+}
+
+#[test]
+fn lenient_parsing_runs_with_a_warning() {
+    let (mut lox, _) = quiet_lox();
+    lox.set_lenient(true);
+
+    assert!(lox.compile("print 1").is_some());
+    let diagnostics = lox.take_diagnostics();
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].severity, Severity::Warning);
+    assert_eq!(diagnostics[0].message, "Inserted missing ';'.");
+}
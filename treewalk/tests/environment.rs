@@ -0,0 +1,141 @@
+//! A function declared in a scope and stored there forms a reference cycle
+//! with it. These check that the cycle is real and that clearing it frees
+//! both.
+
+mod common;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use treewalk::environment::Environment;
+use treewalk::function::LoxFunction;
+use treewalk::lox::Lox;
+use treewalk::object::Object;
+use treewalk::stmt::Contract;
+use treewalk::token::{Token, TokenType};
+
+/// Declares `fun name() {}` in `scope`, as the interpreter does.
+fn declare(scope: &Rc<RefCell<Environment>>, globals: &Rc<RefCell<Environment>>, name: &str) {
+    let token = Token::new(TokenType::Identifier, name, Object::Nil, 1);
+    let function = LoxFunction::new(
+        token,
+        vec![],
+        Contract::default(),
+        vec![],
+        scope.clone(),
+        globals.clone(),
+        false,
+    );
+
+    scope.borrow_mut().define(name, &function.into());
+}
+
+#[test]
+fn function_in_its_own_scope_is_a_cycle() {
+    let globals = Environment::new();
+    let scope = Environment::new_enclosed(globals.clone());
+    declare(&scope, &globals, "f");
+
+    // One handle here, one from the function's closure.
+    assert_eq!(Rc::strong_count(&scope), 2);
+
+    let weak = Rc::downgrade(&scope);
+    drop(scope);
+    assert!(
+        weak.upgrade().is_some(),
+        "the cycle should keep the scope alive"
+    );
+}
+
+#[test]
+fn clearing_the_cycle_frees_the_scope() {
+    let globals = Environment::new();
+    let scope = Environment::new_enclosed(globals.clone());
+    declare(&scope, &globals, "f");
+    declare(&scope, &globals, "g");
+
+    assert!(Environment::clear_cycle(&scope));
+    assert_eq!(Rc::strong_count(&scope), 1);
+
+    let weak = Rc::downgrade(&scope);
+    drop(scope);
+    assert!(weak.upgrade().is_none());
+}
+
+#[test]
+fn reachable_scope_is_left_alone() {
+    let globals = Environment::new();
+    let scope = Environment::new_enclosed(globals.clone());
+    declare(&scope, &globals, "f");
+
+    // A closure that escaped still needs the scope.
+    let escaped = scope.borrow().get_own("f").unwrap();
+    assert!(!Environment::clear_cycle(&scope));
+    assert!(scope.borrow().get_own("f").is_some());
+
+    drop(escaped);
+    assert!(Environment::clear_cycle(&scope));
+}
+
+#[test]
+fn bindings_lists_the_frame() {
+    let scope = Environment::new();
+    scope.borrow_mut().define("a", &Object::from(1.0));
+    scope.borrow_mut().define("b", &Object::from("two"));
+
+    let scope = scope.borrow();
+    let mut bindings: Vec<_> = scope.bindings().collect();
+    bindings.sort_by_key(|(name, _)| *name);
+
+    assert_eq!(
+        bindings,
+        [("a", &Object::from(1.0)), ("b", &Object::from("two"))]
+    );
+}
+
+#[test]
+fn bindings_recursive_walks_enclosing_frames() {
+    let globals = Environment::new();
+    globals.borrow_mut().define("a", &Object::from(1.0));
+    globals.borrow_mut().define("b", &Object::from(2.0));
+    let scope = Environment::new_enclosed(globals.clone());
+    scope.borrow_mut().define("b", &Object::from(3.0));
+    scope.borrow_mut().define("c", &Object::from(4.0));
+
+    let mut bindings = scope.borrow().bindings_recursive();
+    // Only the frames come in order, so sort within each one.
+    bindings[..2].sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    assert_eq!(
+        bindings,
+        [
+            ("b".to_owned(), Object::from(3.0)),
+            ("c".to_owned(), Object::from(4.0)),
+            ("a".to_owned(), Object::from(1.0)),
+        ]
+    );
+}
+
+#[test]
+fn interpreters_count_only_their_own_environments() {
+    let count = "print gc_stats()[\"environments\"];";
+
+    let (mut watched, watched_printed, _) = common::buffered(Lox::new());
+    watched.run(count);
+
+    // Another interpreter on the same thread, holding on to a scope for
+    // each closure it keeps.
+    let (mut other, other_printed, _) = common::buffered(Lox::new());
+    other.run(
+        "var keep = []; \
+         for (var i = 0; i < 50; i = i + 1) push(keep, () => i); \
+         print gc_stats()[\"environments\"] > 50;",
+    );
+    assert_eq!(other_printed.take(), "true\n");
+
+    watched.run(count);
+    let printed = watched_printed.take();
+    let counts: Vec<_> = printed.lines().collect();
+    assert_eq!(counts.len(), 2);
+    assert_eq!(counts[0], counts[1]);
+}
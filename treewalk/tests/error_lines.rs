@@ -0,0 +1,35 @@
+mod common;
+
+use treewalk::stmt::Stmt;
+
+/// Runs `source`, returning the runtime error it reported.
+fn reported(source: &str) -> String {
+    common::run(|_| (), source).1
+}
+
+#[test]
+fn standalone_variable_reports_its_own_line() {
+    assert_eq!(
+        reported("var a = 1;\n\nmissing;"),
+        "Undefined variable 'missing'.\n[line 3]\n"
+    );
+}
+
+#[test]
+fn variable_inside_a_multiline_expression_reports_its_own_line() {
+    assert_eq!(
+        reported("print 1 +\n  2 +\n  missing;"),
+        "Undefined variable 'missing'.\n[line 3]\n"
+    );
+}
+
+#[test]
+fn literals_and_lists_know_their_line() {
+    let (statements, diagnostics) = treewalk::parse_program("print\n  [\n    1,\n    2\n  ];");
+    assert!(diagnostics.is_empty());
+
+    let [Stmt::Print { expr }] = &statements[..] else {
+        panic!("expected a print statement");
+    };
+    assert_eq!(expr.line(), 2);
+}
@@ -0,0 +1,79 @@
+//! AST nodes, environments and functions compare by an `Id` handed out when
+//! they're created. Cloning keeps it, so a copy is still the same node;
+//! creating another, even from the same source, doesn't.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use treewalk::environment::Environment;
+use treewalk::expr::Expr;
+use treewalk::function::LoxFunction;
+use treewalk::object::Object;
+use treewalk::parse_program;
+use treewalk::stmt::{Contract, Stmt};
+use treewalk::token::{Token, TokenType};
+
+fn parse_expr(source: &str) -> Expr {
+    let (statements, diagnostics) = parse_program(source);
+    assert!(diagnostics.is_empty());
+
+    match statements.into_iter().next() {
+        Some(Stmt::Expr { expr }) => expr,
+        statement => panic!("expected an expression statement, got {statement:?}"),
+    }
+}
+
+fn function(globals: &Rc<RefCell<Environment>>) -> LoxFunction {
+    let name = Token::new(TokenType::Identifier, "f", Object::Nil, 1);
+
+    LoxFunction::new(
+        name,
+        vec![],
+        Contract::default(),
+        vec![],
+        globals.clone(),
+        globals.clone(),
+        false,
+    )
+}
+
+#[test]
+fn cloned_expr_is_the_same_node() {
+    let expr = parse_expr("1 + 2;");
+
+    assert_eq!(expr.clone(), expr);
+}
+
+#[test]
+fn separately_parsed_exprs_are_different_nodes() {
+    assert_ne!(parse_expr("1 + 2;"), parse_expr("1 + 2;"));
+}
+
+#[test]
+fn cloned_function_is_the_same_function() {
+    let globals = Environment::new();
+    let f = Object::from(function(&globals));
+
+    assert_eq!(f.clone(), f);
+}
+
+#[test]
+fn separately_created_functions_are_different() {
+    let globals = Environment::new();
+
+    assert_ne!(
+        Object::from(function(&globals)),
+        Object::from(function(&globals))
+    );
+}
+
+#[test]
+fn separately_created_environments_are_different() {
+    let globals = Environment::new();
+    let first = Environment::new_enclosed(globals.clone());
+    let second = Environment::new_enclosed(globals.clone());
+
+    assert_eq!(*first.borrow(), *first.borrow());
+    assert_ne!(*first.borrow(), *second.borrow());
+    assert_ne!(*globals.borrow(), *Environment::new().borrow());
+}
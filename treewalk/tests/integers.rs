@@ -0,0 +1,24 @@
+//! Overflow modes other than the default. Everything else about integers
+//! is checked by the scripts in `tests/scripts/integers`.
+
+#![cfg(feature = "integers")]
+
+mod common;
+
+use treewalk::interpreter::Overflow;
+
+#[test]
+fn overflow_can_wrap() {
+    let source = "print 9223372036854775807 + 1; print -(-9223372036854775807 - 1);";
+    let (printed, _) = common::run(|lox| lox.set_overflow(Overflow::Wrap), source);
+
+    assert_eq!(printed, "-9223372036854775808\n-9223372036854775808\n");
+}
+
+#[test]
+fn overflow_can_saturate() {
+    let source = "print 9223372036854775807 + 1; print -9223372036854775807 - 2;";
+    let (printed, _) = common::run(|lox| lox.set_overflow(Overflow::Saturate), source);
+
+    assert_eq!(printed, "9223372036854775807\n-9223372036854775808\n");
+}
@@ -0,0 +1,50 @@
+mod common;
+
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+use treewalk::lox::Lox;
+
+#[test]
+fn interrupt_stops_a_running_program() {
+    let (mut lox, printed, reported) = common::buffered(Lox::new());
+
+    let interrupt = lox.interrupt_handle();
+    let interrupter = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        interrupt.store(true, Ordering::Relaxed);
+    });
+
+    lox.run("print \"started\"; while (true) {}");
+    interrupter.join().unwrap();
+
+    assert_eq!(*printed.borrow(), "started\n");
+    assert_eq!(*reported.borrow(), "Interrupted.\n");
+}
+
+#[test]
+fn interpreter_runs_again_after_an_interrupt() {
+    let (mut lox, printed, _) = common::buffered(Lox::new());
+
+    lox.interrupt_handle().store(true, Ordering::Relaxed);
+    lox.run("print 1;");
+
+    assert_eq!(*printed.borrow(), "1\n");
+}
+
+#[test]
+fn interrupt_reaches_into_function_calls() {
+    let (mut lox, _, reported) = common::buffered(Lox::new());
+
+    let interrupt = lox.interrupt_handle();
+    let interrupter = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        interrupt.store(true, Ordering::Relaxed);
+    });
+
+    lox.run("fun spin() { while (true) {} }\nspin();");
+    interrupter.join().unwrap();
+
+    assert_eq!(*reported.borrow(), "Interrupted.\n[line 2]\n");
+}
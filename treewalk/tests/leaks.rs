@@ -0,0 +1,93 @@
+//! Checks that scripts free what they discard, using a `tracked()` native
+//! whose values count their own drops.
+
+mod common;
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use treewalk::function::NativeFn;
+use treewalk::lox::Lox;
+use treewalk::object::Object;
+
+/// Counts its drop in a shared counter.
+struct Tracker(Rc<Cell<usize>>);
+
+impl Drop for Tracker {
+    fn drop(&mut self) {
+        self.0.set(self.0.get() + 1);
+    }
+}
+
+/// A session with `tracked()`, which returns a value that is only freed
+/// once nothing can reach it, and the count of those freed so far.
+fn session() -> (Lox, Rc<Cell<usize>>) {
+    let drops = Rc::new(Cell::new(0));
+
+    let (mut lox, _, _) = common::buffered(Lox::new());
+
+    let counter = drops.clone();
+    lox.define_native_closure("tracked", 0, move |_, _| {
+        let tracker = Tracker(counter.clone());
+        let token = NativeFn::closure("token", 0, move |_, _| {
+            let _ = &tracker;
+            Ok(Object::Nil)
+        });
+
+        Ok(Object::from(token))
+    });
+
+    (lox, drops)
+}
+
+const MAKE: &str = r#"
+fun make() {
+  var token = tracked();
+  fun recur(n) {
+    token;
+    if (n > 0) return recur(n - 1);
+    return n;
+  }
+  return recur;
+}
+"#;
+
+#[test]
+fn closures_made_in_a_loop_are_freed() {
+    let (mut lox, drops) = session();
+
+    lox.run(MAKE);
+    lox.run(
+        "for (var i = 0; i < 10; i = i + 1) {
+           var f = make();
+           f(3);
+         }",
+    );
+
+    assert_eq!(drops.get(), 10);
+}
+
+#[test]
+fn discarded_globals_are_freed_across_runs() {
+    let (mut lox, drops) = session();
+
+    lox.run(MAKE);
+    for _ in 0..5 {
+        lox.run("var f = make(); f(2);");
+    }
+    // Each redefinition frees the previous closure; the last is still held.
+    assert_eq!(drops.get(), 4);
+
+    lox.run("f = nil;");
+    assert_eq!(drops.get(), 5);
+}
+
+#[test]
+fn reachable_closures_are_kept() {
+    let (mut lox, drops) = session();
+
+    lox.run(MAKE);
+    lox.run("var kept = [make(), make()];");
+
+    assert_eq!(drops.get(), 0);
+}
@@ -0,0 +1,132 @@
+mod common;
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use treewalk::error::Exception;
+use treewalk::lox::Lox;
+use treewalk::object::Object;
+
+#[test]
+fn custom_native_is_callable() {
+    let (printed, reported) = common::run(
+        |lox| {
+            lox.define_native("double", 1, |_, args| {
+                let x = f64::try_from(args[0].clone())?;
+                Ok(Object::from(x * 2.0))
+            })
+        },
+        "print double(21);",
+    );
+
+    assert_eq!(printed, "42\n");
+    assert_eq!(reported, "");
+}
+
+#[test]
+fn custom_native_errors_are_reported_at_the_call() {
+    let (_, reported) = common::run(
+        |lox| lox.define_native("fail", 0, |_, _| Err(Exception::native("Nope."))),
+        "\nfail();",
+    );
+
+    assert_eq!(reported, "Nope.\n[line 2]\n");
+}
+
+#[test]
+fn closure_native_keeps_captured_state() {
+    let count = Rc::new(Cell::new(0));
+    let counter = count.clone();
+
+    let (printed, _) = common::run(
+        |lox| {
+            lox.define_native_closure("tick", 0, move |_, _| {
+                counter.set(counter.get() + 1);
+                Ok(Object::from(counter.get()))
+            })
+        },
+        "tick(); tick(); print tick();",
+    );
+
+    assert_eq!(printed, "3\n");
+    assert_eq!(count.get(), 3);
+}
+
+#[test]
+fn natives_compare_by_identity() {
+    let (printed, _) = common::run(
+        |lox| {
+            lox.define_native_closure("a", 0, |_, _| Ok(Object::Nil));
+            lox.define_native_closure("b", 0, |_, _| Ok(Object::Nil));
+        },
+        "print a == a; print a == b;",
+    );
+
+    assert_eq!(printed, "true\nfalse\n");
+}
+
+#[test]
+fn files_round_trip() {
+    let path = std::env::temp_dir().join(format!("lox-natives-{}.txt", std::process::id()));
+    let source = format!(
+        "write_file({path:?}, \"line one\nline two\");\nprint read_file({path:?});",
+        path = path.to_str().unwrap()
+    );
+
+    let (printed, reported) = common::run(|_| (), &source);
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(printed, "line one\nline two\n");
+    assert_eq!(reported, "");
+}
+
+#[test]
+fn reading_a_missing_file_is_a_runtime_error() {
+    let (_, reported) = common::run(|_| (), "read_file(\"/nonexistent/lox/file.txt\");");
+
+    assert!(
+        reported.starts_with("Could not read file '/nonexistent/lox/file.txt': "),
+        "{reported}"
+    );
+    assert!(reported.ends_with("[line 1]\n"), "{reported}");
+}
+
+#[test]
+fn env_reads_from_the_injected_source() {
+    let (printed, reported) = common::run(
+        |lox| lox.set_env(|name| (name == "GREETING").then(|| "hello".to_owned())),
+        r#"
+            print env("GREETING");
+            print env("MISSING");
+            print env_or("GREETING", "default");
+            print env_or("MISSING", "default");
+        "#,
+    );
+
+    assert_eq!(printed, "hello\nnil\nhello\ndefault\n");
+    assert_eq!(reported, "");
+}
+
+#[test]
+fn env_names_must_be_strings() {
+    let (_, reported) = common::run(|lox| lox.set_env(|_| None), "env(nil);");
+
+    assert_eq!(reported, "Expected a string but got nil.\n[line 1]\n");
+}
+
+#[test]
+fn same_seed_gives_the_same_sequence() {
+    let source =
+        "print random(); print random(); print random_int(1, 100); print random_int(-5, 5);";
+    let run_seeded = |seed| {
+        let (mut lox, printed, _) = common::buffered(Lox::with_seed(seed));
+        lox.run(source);
+
+        printed.take()
+    };
+
+    let first = run_seeded(42);
+    assert_eq!(first.lines().count(), 4, "{first}");
+    assert_eq!(first, run_seeded(42));
+    assert_ne!(first, run_seeded(7));
+}
@@ -0,0 +1,49 @@
+use treewalk::object::Object;
+
+#[test]
+fn numbers_round_trip() {
+    assert_eq!(f64::try_from(Object::from(1.5)), Ok(1.5));
+    assert_eq!(i64::try_from(Object::from(-7i64)), Ok(-7));
+    assert_eq!(i64::try_from(Object::from(42i32)), Ok(42));
+}
+
+#[test]
+fn strings_round_trip() {
+    assert_eq!(String::try_from(Object::from("lox")), Ok("lox".to_owned()));
+    assert_eq!(
+        String::try_from(Object::from("owned".to_owned())),
+        Ok("owned".to_owned())
+    );
+}
+
+#[test]
+fn booleans_round_trip() {
+    assert_eq!(bool::try_from(Object::from(true)), Ok(true));
+}
+
+#[test]
+fn lists_round_trip() {
+    let list = Object::from(vec![Object::from(1.0), Object::from("two")]);
+    assert_eq!(list.to_string(), "[1, two]");
+
+    let elements = Vec::<Object>::try_from(list).unwrap();
+    assert_eq!(elements, [Object::from(1.0), Object::from("two")]);
+}
+
+#[test]
+fn options_become_nil() {
+    assert_eq!(Object::from(None::<f64>), Object::Nil);
+    assert_eq!(Object::from(Some(3.0)), Object::from(3.0));
+}
+
+#[test]
+fn mismatches_name_what_was_expected() {
+    let error = f64::try_from(Object::from("3")).unwrap_err();
+    assert_eq!(error.to_string(), "Expected a number but got \"3\".");
+
+    let error = i64::try_from(Object::from(2.5)).unwrap_err();
+    assert_eq!(error.to_string(), "Expected an integer but got 2.5.");
+
+    let error = bool::try_from(Object::Nil).unwrap_err();
+    assert_eq!(error.to_string(), "Expected a boolean but got nil.");
+}
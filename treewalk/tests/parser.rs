@@ -0,0 +1,168 @@
+use treewalk::error::{Diagnostic, Severity, Span};
+use treewalk::expr::{Expr, ExprData};
+use treewalk::parser::Parser;
+use treewalk::scanner::Scanner;
+use treewalk::stmt::Stmt;
+use treewalk::token::Token;
+
+#[test]
+fn parse_collects_every_error() {
+    let (tokens, scan_errors) = Scanner::new("var = 1;\nprint 1\nprint 2;").scan_tokens();
+    assert!(scan_errors.is_empty());
+
+    let (statements, errors) = Parser::new(tokens).parse();
+    let diagnostics: Vec<_> = errors.iter().map(|error| error.to_diagnostic()).collect();
+
+    assert_eq!(
+        diagnostics,
+        [
+            Diagnostic {
+                severity: Severity::Error,
+                span: Span {
+                    line: 1,
+                    start: 4,
+                    len: 1,
+                },
+                location: " at '='".to_owned(),
+                message: "Expect variable name.".to_owned(),
+                code: "parse",
+            },
+            Diagnostic {
+                severity: Severity::Error,
+                span: Span {
+                    line: 3,
+                    start: 17,
+                    len: 5,
+                },
+                location: " at 'print'".to_owned(),
+                message: "Expect ';' after value.".to_owned(),
+                code: "parse",
+            },
+        ]
+    );
+    // The parser recovers at each statement boundary and keeps the rest.
+    assert_eq!(statements.len(), 1);
+}
+
+/// The lexeme of a token, which is the second word of its `Display`.
+fn lexeme(token: &Token) -> String {
+    token.to_string().split(' ').nth(1).unwrap().to_owned()
+}
+
+/// Renders an expression's shape in prefix form, e.g. `(+ 1 (* 2 3))`.
+fn render(expr: &Expr) -> String {
+    match &expr.data {
+        ExprData::Binary { op, lhs, rhs } | ExprData::Logical { op, lhs, rhs } => {
+            format!("({} {} {})", lexeme(op), render(lhs), render(rhs))
+        }
+        ExprData::Unary { op, rhs } => format!("({} {})", lexeme(op), render(rhs)),
+        ExprData::Grouping { expr } => format!("(group {})", render(expr)),
+        ExprData::Call {
+            callee, arguments, ..
+        } => {
+            let arguments: Vec<_> = arguments.iter().map(render).collect();
+            format!("(call {} {})", render(callee), arguments.join(" "))
+        }
+        ExprData::Get { object, name } => format!("(. {} {})", render(object), lexeme(name)),
+        ExprData::Assign { name, value } => format!("(= {} {})", lexeme(name), render(value)),
+        ExprData::Literal { value } => value.to_string(),
+        ExprData::Variable { name } => lexeme(name),
+        data => panic!("can't render {data:?}"),
+    }
+}
+
+fn parse_expression(source: &str) -> String {
+    let (statements, diagnostics) = treewalk::parse_program(&format!("{source};"));
+    assert!(diagnostics.is_empty(), "{diagnostics:?}");
+
+    let [Stmt::Expr { expr }] = &statements[..] else {
+        panic!("expected one expression statement");
+    };
+    render(expr)
+}
+
+#[test]
+fn operators_follow_precedence_and_associativity() {
+    let cases = [
+        ("1 + 2 * 3", "(+ 1 (* 2 3))"),
+        ("1 * 2 + 3", "(+ (* 1 2) 3)"),
+        ("1 - 2 - 3", "(- (- 1 2) 3)"),
+        ("8 / 4 / 2", "(/ (/ 8 4) 2)"),
+        ("-1 * -2", "(* (- 1) (- 2))"),
+        ("!a == b", "(== (! a) b)"),
+        ("1 < 2 == 3 >= 4", "(== (< 1 2) (>= 3 4))"),
+        ("a == b != c", "(!= (== a b) c)"),
+        ("a or b and c", "(or a (and b c))"),
+        ("a and b or c and d", "(or (and a b) (and c d))"),
+        ("a or b or c", "(or (or a b) c)"),
+        ("a + b < c * d and e", "(and (< (+ a b) (* c d)) e)"),
+        ("(1 + 2) * 3", "(* (group (+ 1 2)) 3)"),
+        ("f(1 + 2) * x.y", "(* (call f (+ 1 2)) (. x y))"),
+        ("x = y = 1 + 2", "(= x (= y (+ 1 2)))"),
+    ];
+
+    for (source, expected) in cases {
+        assert_eq!(parse_expression(source), expected, "parsing {source}");
+    }
+}
+
+fn parse_lenient(source: &str) -> (Vec<Stmt>, Vec<String>, Vec<String>) {
+    let (tokens, _) = Scanner::new(source).scan_tokens();
+
+    let mut parser = Parser::new(tokens);
+    parser.set_lenient(true);
+    let (statements, errors) = parser.parse();
+
+    let errors = errors.iter().map(|e| e.to_diagnostic().to_string());
+    let warnings = parser.take_warnings();
+    let warnings = warnings.iter().map(|w| w.to_warning().to_string());
+
+    (statements, errors.collect(), warnings.collect())
+}
+
+#[test]
+fn lenient_mode_inserts_a_final_semicolon() {
+    let (statements, errors, warnings) = parse_lenient("print 1");
+
+    assert!(matches!(statements[..], [Stmt::Print { .. }]));
+    assert!(errors.is_empty(), "{errors:?}");
+    assert_eq!(warnings, ["[line 1] Warning at end: Inserted missing ';'."]);
+}
+
+#[test]
+fn lenient_mode_closes_unfinished_blocks() {
+    let (statements, errors, warnings) = parse_lenient("{\n  if (true) {\n    print 1");
+
+    assert_eq!(statements.len(), 1);
+    assert!(errors.is_empty(), "{errors:?}");
+    assert_eq!(
+        warnings,
+        [
+            "[line 3] Warning at end: Inserted missing ';'.",
+            "[line 3] Warning at end: Inserted missing '}'.",
+            "[line 3] Warning at end: Inserted missing '}'.",
+        ]
+    );
+}
+
+#[test]
+fn lenient_mode_only_fills_in_at_the_end() {
+    let (_, errors, warnings) = parse_lenient("print 1 print 2");
+
+    assert_eq!(
+        errors,
+        ["[line 1] Error at 'print': Expect ';' after value."]
+    );
+    assert_eq!(warnings, ["[line 1] Warning at end: Inserted missing ';'."]);
+}
+
+#[test]
+fn strict_mode_is_the_default() {
+    let (tokens, _) = Scanner::new("print 1").scan_tokens();
+
+    let mut parser = Parser::new(tokens);
+    let (_, errors) = parser.parse();
+
+    assert_eq!(errors.len(), 1);
+    assert!(parser.take_warnings().is_empty());
+}
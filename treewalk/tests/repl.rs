@@ -0,0 +1,32 @@
+use std::path::PathBuf;
+
+use treewalk::lox::ReplConfig;
+
+#[test]
+fn default_prompts() {
+    let config = ReplConfig::default();
+    assert_eq!(config.render(false), "> ");
+    assert_eq!(config.render(true), "... ");
+}
+
+#[test]
+fn custom_prompts_are_rendered() {
+    let config = ReplConfig {
+        prompt: "lox> ".to_owned(),
+        continuation: "   | ".to_owned(),
+        ..ReplConfig::default()
+    };
+    assert_eq!(config.render(false), "lox> ");
+    assert_eq!(config.render(true), "   | ");
+}
+
+#[test]
+fn history_is_kept_under_home() {
+    let file = ReplConfig::history_file_in(Some(PathBuf::from("/home/lox")));
+    assert_eq!(file, Some(PathBuf::from("/home/lox/.cache/lox_history")));
+}
+
+#[test]
+fn history_stays_in_memory_without_home() {
+    assert_eq!(ReplConfig::history_file_in(None), None);
+}
@@ -0,0 +1,34 @@
+use treewalk::scanner::Scanner;
+
+fn tokens(source: &str) -> Vec<String> {
+    let (tokens, errors) = Scanner::new(source).scan_tokens();
+    assert!(errors.is_empty(), "{source:?}");
+
+    tokens.iter().map(ToString::to_string).collect()
+}
+
+#[test]
+fn blank_sources_scan_to_eof() {
+    for source in ["", " \n\t\r\n", "// just a comment", "// a comment\n"] {
+        assert_eq!(tokens(source), ["Eof  nil"], "{source:?}");
+    }
+}
+
+#[test]
+fn a_trailing_dot_is_not_part_of_the_number() {
+    assert_eq!(tokens("1."), ["Number 1 1", "Dot . nil", "Eof  nil"]);
+}
+
+#[test]
+fn tokens_ending_the_source_terminate() {
+    for source in ["a", "12", "1.5", "\"s\"", "!=", "<", "/"] {
+        let tokens = tokens(source);
+
+        assert_eq!(tokens.len(), 2, "{source:?}");
+        assert_eq!(tokens[1], "Eof  nil", "{source:?}");
+    }
+
+    let (tokens, errors) = Scanner::new("\"open").scan_tokens();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(tokens.len(), 1);
+}
@@ -0,0 +1,141 @@
+//! Runs every script in `tests/scripts` and checks it against its
+//! annotations:
+//!
+//! - `// expect: <line>` for each line the script prints, in order;
+//! - `// expect stderr: <line>` for each warning or `eprint` line on stderr;
+//! - `// expect runtime error: <message>` for the error that ends it.
+//!
+//! Scripts in `tests/scripts/integers` are only run with the `integers`
+//! feature, which changes what their literals mean.
+
+use std::fs::{read_dir, read_to_string};
+use std::path::{Path, PathBuf};
+
+use common::sink::Sink;
+use treewalk::lox::Lox;
+
+const EXPECT: &str = "// expect: ";
+const EXPECT_STDERR: &str = "// expect stderr: ";
+const EXPECT_RUNTIME_ERROR: &str = "// expect runtime error: ";
+
+struct Expectations {
+    output: Vec<String>,
+    stderr: Vec<String>,
+    runtime_error: Option<String>,
+}
+
+impl Expectations {
+    fn parse(source: &str) -> Self {
+        let mut output = vec![];
+        let mut stderr = vec![];
+        let mut runtime_error = None;
+
+        for line in source.lines() {
+            if let Some((_, expected)) = line.split_once(EXPECT) {
+                output.push(expected.to_owned());
+            } else if let Some((_, expected)) = line.split_once(EXPECT_STDERR) {
+                stderr.push(expected.to_owned());
+            } else if let Some((_, message)) = line.split_once(EXPECT_RUNTIME_ERROR) {
+                runtime_error = Some(message.to_owned());
+            }
+        }
+
+        Expectations {
+            output,
+            stderr,
+            runtime_error,
+        }
+    }
+}
+
+fn scripts(dir: &str) -> Vec<PathBuf> {
+    let mut scripts: Vec<_> = read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "lox"))
+        .collect();
+    scripts.sort();
+
+    scripts
+}
+
+/// Runs the script at `path`, returning a description of each mismatch.
+fn check(path: &Path) -> Vec<String> {
+    let source = read_to_string(path).unwrap();
+    let expected = Expectations::parse(&source);
+
+    let (out, printed) = Sink::buffer();
+    let (err, reported) = Sink::buffer();
+
+    let mut lox = Lox::new();
+    lox.set_output(out, err);
+    lox.run(&source);
+
+    let printed: Vec<_> = printed.borrow().lines().map(str::to_owned).collect();
+    let reported = reported.borrow();
+
+    let mut failures = vec![];
+
+    if printed != expected.output {
+        failures.push(format!(
+            "expected output {:?}\n  got {printed:?}",
+            expected.output
+        ));
+    }
+
+    // The script's own writes come first, then any runtime error.
+    let mut lines = reported.lines();
+    let written: Vec<_> = lines
+        .by_ref()
+        .take(expected.stderr.len())
+        .map(str::to_owned)
+        .collect();
+    if written != expected.stderr {
+        failures.push(format!(
+            "expected stderr {:?}\n  got {written:?}",
+            expected.stderr
+        ));
+    }
+
+    // A runtime error is reported as its message followed by its line.
+    match (&expected.runtime_error, lines.next()) {
+        (Some(message), Some(actual)) if message == actual => (),
+        (None, None) => (),
+
+        (Some(message), _) => failures.push(format!(
+            "expected runtime error {message:?}\n  got {reported:?}"
+        )),
+        (None, Some(_)) => failures.push(format!("unexpected errors {reported:?}")),
+    }
+
+    failures
+}
+
+/// Checks every script in `dir`, failing with all the mismatches at once.
+fn check_all(dir: &str) {
+    let scripts = scripts(dir);
+    assert!(!scripts.is_empty(), "no test scripts found in {dir}");
+
+    let mut failures = vec![];
+
+    for path in scripts {
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+
+        for failure in check(&path) {
+            failures.push(format!("{name}: {failure}"));
+        }
+    }
+
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+#[test]
+fn scripts_match_expectations() {
+    check_all(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/scripts"));
+}
+
+#[cfg(feature = "integers")]
+#[test]
+fn integer_scripts_match_expectations() {
+    check_all(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/scripts/integers"));
+}
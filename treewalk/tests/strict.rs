@@ -0,0 +1,37 @@
+mod common;
+
+/// Runs `source` in strict mode, returning what it printed and reported.
+fn strict(source: &str) -> (String, String) {
+    common::run(|lox| lox.set_strict(true), source)
+}
+
+#[test]
+fn reading_before_assignment_is_an_error_in_strict_mode() {
+    let (printed, reported) = strict("var x;\nprint x;");
+
+    assert_eq!(printed, "");
+    assert_eq!(reported, "Variable 'x' used before assignment.\n[line 2]\n");
+}
+
+#[test]
+fn locals_are_checked_too() {
+    let (_, reported) = strict("{\n  var x;\n  print x;\n}");
+
+    assert_eq!(reported, "Variable 'x' used before assignment.\n[line 3]\n");
+}
+
+#[test]
+fn assigning_first_makes_reads_fine() {
+    let (printed, reported) = strict("var x; x = 1; print x; { var y; y = 2; print y; }");
+
+    assert_eq!(printed, "1\n2\n");
+    assert_eq!(reported, "");
+}
+
+#[test]
+fn assigning_an_undeclared_global_is_an_error_in_strict_mode() {
+    let (printed, reported) = strict("print 1;\nmissing = 2;\nprint 3;");
+
+    assert_eq!(printed, "1\n");
+    assert_eq!(reported, "Undefined variable 'missing'.\n[line 2]\n");
+}
@@ -0,0 +1,53 @@
+mod common;
+
+use treewalk::interpreter::TestResults;
+use treewalk::lox::Lox;
+
+const SOURCE: &str = "
+var shared = 1;
+
+test \"passes\" {
+  var shared = 2;
+  assert(shared == 2, \"shadowed\");
+}
+
+test \"fails\" {
+  assert(shared == 2, \"shared should be 2\");
+  print \"unreachable\";
+}
+
+print shared;
+";
+
+#[test]
+fn test_mode_runs_each_block_and_counts_results() {
+    let (mut lox, printed, reported) = common::buffered(Lox::new());
+    lox.set_testing(true);
+    lox.run(SOURCE);
+
+    assert_eq!(
+        lox.test_results(),
+        Some(TestResults {
+            passed: 1,
+            failed: 1
+        })
+    );
+    // A failing test doesn't stop the rest of the program.
+    assert_eq!(
+        printed.take(),
+        "test passes ... ok\ntest fails ... FAILED\n1\n"
+    );
+    assert_eq!(
+        reported.take(),
+        "Assertion failed: shared should be 2\n[line 10]\n"
+    );
+}
+
+#[test]
+fn tests_are_skipped_outside_test_mode() {
+    let (mut lox, printed, _) = common::buffered(Lox::new());
+    lox.run(SOURCE);
+
+    assert_eq!(lox.test_results(), None);
+    assert_eq!(printed.take(), "1\n");
+}
@@ -0,0 +1,59 @@
+use treewalk::token::{Precedence, TokenType};
+
+#[test]
+fn keywords() {
+    for kind in [
+        TokenType::And,
+        TokenType::Class,
+        TokenType::Nil,
+        TokenType::Yield,
+    ] {
+        assert!(kind.is_keyword(), "{kind} should be a keyword");
+    }
+    for kind in [TokenType::Identifier, TokenType::Plus, TokenType::Eof] {
+        assert!(!kind.is_keyword(), "{kind} shouldn't be a keyword");
+    }
+}
+
+#[test]
+fn literals() {
+    for kind in [TokenType::Identifier, TokenType::String, TokenType::Number] {
+        assert!(kind.is_literal(), "{kind} should be a literal");
+    }
+    for kind in [TokenType::True, TokenType::Nil, TokenType::LeftParen] {
+        assert!(!kind.is_literal(), "{kind} shouldn't be a literal");
+    }
+}
+
+#[test]
+fn statement_starts_are_keywords() {
+    assert!(TokenType::Var.is_statement_start());
+    assert!(TokenType::Return.is_statement_start());
+    assert!(!TokenType::Else.is_statement_start());
+    assert!(!TokenType::This.is_statement_start());
+}
+
+#[test]
+fn binary_operators() {
+    for kind in [TokenType::Plus, TokenType::EqualEqual, TokenType::LessEqual] {
+        assert!(kind.is_binary_operator(), "{kind} should be binary");
+    }
+    for kind in [
+        TokenType::And,
+        TokenType::Or,
+        TokenType::Bang,
+        TokenType::Equal,
+    ] {
+        assert!(!kind.is_binary_operator(), "{kind} shouldn't be binary");
+    }
+}
+
+#[test]
+fn precedence_orders_operators() {
+    assert_eq!(TokenType::Star.precedence(), Precedence::Factor);
+    assert_eq!(TokenType::Dot.precedence(), Precedence::None);
+
+    assert!(TokenType::Or.precedence() < TokenType::And.precedence());
+    assert!(TokenType::EqualEqual.precedence() < TokenType::Less.precedence());
+    assert!(TokenType::Plus.precedence() < TokenType::Slash.precedence());
+}
@@ -0,0 +1,19 @@
+mod common;
+
+#[test]
+fn trace_goes_to_the_error_sink() {
+    let (printed, reported) = common::run(
+        |lox| lox.set_trace(true),
+        "fun add(a, b) { return a + b; }\nprint add(1, 2);",
+    );
+
+    assert_eq!(printed, "3\n");
+    assert_eq!(
+        reported,
+        "[trace] fun add\n\
+         [trace] print\n\
+         [trace] call <fn add>(1, 2)\n\
+         [trace]   return (line 1)\n\
+         [trace] return <fn add> -> 3\n"
+    );
+}
@@ -0,0 +1,244 @@
+//! A visitor outside the crate, counting the nodes of a parsed program, so
+//! the traits can be implemented by tooling as well as by the resolver.
+
+use treewalk::expr::{Expr, ExprVisitor, MatchArm};
+use treewalk::object::Object;
+use treewalk::parse_program;
+use treewalk::stmt::{Contract, Stmt, StmtVisitor};
+use treewalk::token::Token;
+
+#[derive(Default)]
+struct Counter {
+    exprs: usize,
+    stmts: usize,
+}
+
+impl Counter {
+    fn exprs<'a>(&mut self, exprs: impl IntoIterator<Item = &'a Expr>) {
+        exprs.into_iter().for_each(|expr| expr.accept(self));
+    }
+
+    fn stmts<'a>(&mut self, stmts: impl IntoIterator<Item = &'a Stmt>) {
+        stmts.into_iter().for_each(|stmt| stmt.accept(self));
+    }
+}
+
+impl ExprVisitor<()> for Counter {
+    fn visit_assign(&mut self, _: &Expr, _: &Token, value: &Expr) {
+        self.exprs += 1;
+        self.exprs([value]);
+    }
+
+    fn visit_binary(&mut self, _: &Token, lhs: &Expr, rhs: &Expr) {
+        self.exprs += 1;
+        self.exprs([lhs, rhs]);
+    }
+
+    fn visit_call(
+        &mut self,
+        callee: &Expr,
+        _: &Token,
+        arguments: &[Expr],
+        keywords: &[(Token, Expr)],
+    ) {
+        self.exprs += 1;
+        self.exprs([callee]);
+        self.exprs(arguments);
+        self.exprs(keywords.iter().map(|(_, value)| value));
+    }
+
+    fn visit_destructure(&mut self, _: &Token, targets: &[Expr], value: &Expr) {
+        self.exprs += 1;
+        self.exprs(targets);
+        self.exprs([value]);
+    }
+
+    fn visit_get(&mut self, object: &Expr, _: &Token) {
+        self.exprs += 1;
+        self.exprs([object]);
+    }
+
+    fn visit_grouping(&mut self, expr: &Expr) {
+        self.exprs += 1;
+        self.exprs([expr]);
+    }
+
+    fn visit_index(&mut self, object: &Expr, _: &Token, index: &Expr) {
+        self.exprs += 1;
+        self.exprs([object, index]);
+    }
+
+    fn visit_lambda(&mut self, _: &Token, _: &[Token], body: &[Stmt]) {
+        self.exprs += 1;
+        self.stmts(body);
+    }
+
+    fn visit_list(&mut self, elements: &[Expr]) {
+        self.exprs += 1;
+        self.exprs(elements);
+    }
+
+    fn visit_logical(&mut self, _: &Token, lhs: &Expr, rhs: &Expr) {
+        self.exprs += 1;
+        self.exprs([lhs, rhs]);
+    }
+
+    fn visit_literal(&mut self, _: &Object) {
+        self.exprs += 1;
+    }
+
+    fn visit_map(&mut self, _: &Token, entries: &[(Expr, Expr)]) {
+        self.exprs += 1;
+        for (key, value) in entries {
+            self.exprs([key, value]);
+        }
+    }
+
+    fn visit_match(&mut self, _: &Token, scrutinee: &Expr, arms: &[MatchArm]) {
+        self.exprs += 1;
+        self.exprs([scrutinee]);
+        self.exprs(arms.iter().map(|arm| &arm.body));
+    }
+
+    fn visit_set(&mut self, object: &Expr, _: &Token, value: &Expr) {
+        self.exprs += 1;
+        self.exprs([object, value]);
+    }
+
+    fn visit_set_index(&mut self, object: &Expr, _: &Token, index: &Expr, value: &Expr) {
+        self.exprs += 1;
+        self.exprs([object, index, value]);
+    }
+
+    fn visit_super(&mut self, _: &Expr, _: &Token, _: &Token) {
+        self.exprs += 1;
+    }
+
+    fn visit_this(&mut self, _: &Expr, _: &Token) {
+        self.exprs += 1;
+    }
+
+    fn visit_unary(&mut self, _: &Token, rhs: &Expr) {
+        self.exprs += 1;
+        self.exprs([rhs]);
+    }
+
+    fn visit_variable(&mut self, _: &Expr, _: &Token) {
+        self.exprs += 1;
+    }
+}
+
+impl StmtVisitor<()> for Counter {
+    fn visit_block(&mut self, statements: &[Stmt], _: bool) {
+        self.stmts += 1;
+        self.stmts(statements);
+    }
+
+    fn visit_class(&mut self, _: &Token, superclass: Option<&Expr>, methods: &[Stmt]) {
+        self.stmts += 1;
+        self.exprs(superclass);
+        self.stmts(methods);
+    }
+
+    fn visit_continue(&mut self, _: &Token) {
+        self.stmts += 1;
+    }
+
+    fn visit_defer(&mut self, expr: &Expr) {
+        self.stmts += 1;
+        self.exprs([expr]);
+    }
+
+    fn visit_destructure(&mut self, _: &Token, _: &[Token], initializer: &Expr) {
+        self.stmts += 1;
+        self.exprs([initializer]);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        self.stmts += 1;
+        self.exprs([expr]);
+    }
+
+    fn visit_function(&mut self, _: &Token, _: &[Token], _: &Contract, body: &[Stmt]) {
+        self.stmts += 1;
+        self.stmts(body);
+    }
+
+    fn visit_if(&mut self, condition: &Expr, then_branch: &Stmt, else_branch: Option<&Stmt>) {
+        self.stmts += 1;
+        self.exprs([condition]);
+        self.stmts([then_branch]);
+        self.stmts(else_branch);
+    }
+
+    fn visit_import(&mut self, _: &Token, _: &Token, _: Option<&Token>) {
+        self.stmts += 1;
+    }
+
+    fn visit_print(&mut self, expr: &Expr) {
+        self.stmts += 1;
+        self.exprs([expr]);
+    }
+
+    fn visit_return(&mut self, _: &Token, expr: Option<&Expr>) {
+        self.stmts += 1;
+        self.exprs(expr);
+    }
+
+    fn visit_test(&mut self, _: &Token, _: &Token, body: &[Stmt]) {
+        self.stmts += 1;
+        self.stmts(body);
+    }
+
+    fn visit_var(&mut self, _: &Token, initializer: Option<&Expr>) {
+        self.stmts += 1;
+        self.exprs(initializer);
+    }
+
+    fn visit_while(
+        &mut self,
+        condition: &Expr,
+        body: &Stmt,
+        increment: Option<&Expr>,
+        _: Option<&Token>,
+    ) {
+        self.stmts += 1;
+        self.exprs([condition]);
+        self.stmts([body]);
+        self.exprs(increment);
+    }
+
+    fn visit_with(&mut self, _: &Token, _: &Token, initializer: &Expr, body: &Stmt) {
+        self.stmts += 1;
+        self.exprs([initializer]);
+        self.stmts([body]);
+    }
+
+    fn visit_yield(&mut self, _: &Token, expr: &Expr) {
+        self.stmts += 1;
+        self.exprs([expr]);
+    }
+}
+
+#[test]
+fn counts_every_node() {
+    let source = r#"
+var x = 1 + 2;
+if (x > 2) {
+  print x;
+} else print -x;
+fun f(a) {
+  return a(x);
+}
+"#;
+    let (statements, diagnostics) = parse_program(source);
+    assert!(diagnostics.is_empty());
+
+    let mut counter = Counter::default();
+    counter.stmts(&statements);
+
+    // var, if, block, print, print, fun, return
+    assert_eq!(counter.stmts, 7);
+    // 1 + 2: 3, x > 2: 3, x: 1, -x: 2, a(x): 3
+    assert_eq!(counter.exprs, 12);
+}